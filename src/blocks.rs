@@ -1,13 +1,22 @@
-use std::{collections::HashMap, iter::repeat, sync::RwLock};
+use std::{collections::HashMap, sync::RwLock};
+#[cfg(feature = "client")]
+use std::{
+    io::{self, ErrorKind},
+    path::Path,
+};
 
+#[cfg(feature = "client")]
 use glium::{
-    texture::{RawImage2d, RawImage3d, SrgbTexture3d},
-    Display,
+    backend::Facade,
+    texture::{MipmapsOption, RawImage2d, SrgbTexture2dArray},
 };
-use image::GenericImageView;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "client")]
 use crate::client::chunk::BlockTextures;
+use crate::model::{BlockModel, ModelBox};
+#[cfg(feature = "client")]
+use crate::textures::{self, TextureArrayBuilder};
 
 pub const CHUNK_SIZE: usize = 16;
 
@@ -17,12 +26,12 @@ lazy_static! {
     static ref BLOCK_ID_NAME_MAP: RwLock<Vec<String>> = RwLock::new(Vec::new());
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Block(u32);
 
 #[repr(u32)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum FaceDirection {
     Up = 0,
     Down = 1,
@@ -32,6 +41,52 @@ pub enum FaceDirection {
     Right = 5,
 }
 
+/// How a block's faces are drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderLayer {
+    /// Fully opaque, hides the faces of any block behind it.
+    Opaque,
+    /// Either fully opaque or fully transparent per pixel, like glass or leaves. Drawn with the
+    /// opaque blocks, discarding transparent pixels.
+    Cutout,
+    /// Partially transparent, like water. Drawn after every other block, back to front.
+    Translucent,
+}
+
+impl FaceDirection {
+    pub const ALL: [FaceDirection; 6] = [
+        FaceDirection::Up,
+        FaceDirection::Down,
+        FaceDirection::Front,
+        FaceDirection::Back,
+        FaceDirection::Left,
+        FaceDirection::Right,
+    ];
+
+    /// Returns the offset to the neighbouring block this face looks at.
+    pub fn offset(&self) -> [isize; 3] {
+        match self {
+            FaceDirection::Up => [0, 1, 0],
+            FaceDirection::Down => [0, -1, 0],
+            FaceDirection::Front => [1, 0, 0],
+            FaceDirection::Back => [-1, 0, 0],
+            FaceDirection::Left => [0, 0, 1],
+            FaceDirection::Right => [0, 0, -1],
+        }
+    }
+
+    pub fn opposite(&self) -> FaceDirection {
+        match self {
+            FaceDirection::Up => FaceDirection::Down,
+            FaceDirection::Down => FaceDirection::Up,
+            FaceDirection::Front => FaceDirection::Back,
+            FaceDirection::Back => FaceDirection::Front,
+            FaceDirection::Left => FaceDirection::Right,
+            FaceDirection::Right => FaceDirection::Left,
+        }
+    }
+}
+
 impl Block {
     pub fn register_defaults() {
         Block::register(String::from("air"), BlockData::new(false, vec![]));
@@ -111,43 +166,204 @@ impl Block {
                 )],
             ),
         );
+        Block::register(
+            String::from("lava"),
+            BlockData::new(
+                false,
+                vec![(
+                    String::from("assets/textures/animated/lava.png"),
+                    vec![
+                        FaceDirection::Up,
+                        FaceDirection::Down,
+                        FaceDirection::Left,
+                        FaceDirection::Right,
+                        FaceDirection::Front,
+                        FaceDirection::Back,
+                    ],
+                )],
+            )
+            .with_animation(6)
+            .with_light(15, 9, 3),
+        );
+
+        for &(name, texture) in [
+            ("iron_ore", "stone_iron.png"),
+            ("silver_ore", "stone_silver.png"),
+            ("gold_ore", "stone_gold.png"),
+            ("ruby_ore", "greystone_ruby.png"),
+            ("diamond_ore", "stone_diamond.png"),
+        ]
+        .iter()
+        {
+            Block::register(
+                String::from(name),
+                BlockData::new(
+                    true,
+                    vec![(
+                        format!("assets/textures/PNG/Tiles/{}", texture),
+                        vec![
+                            FaceDirection::Up,
+                            FaceDirection::Down,
+                            FaceDirection::Left,
+                            FaceDirection::Right,
+                            FaceDirection::Front,
+                            FaceDirection::Back,
+                        ],
+                    )],
+                ),
+            );
+        }
+
+        for &(name, texture, solid, layer) in [
+            ("glass", "glass.png", true, RenderLayer::Cutout),
+            (
+                "leaves",
+                "leaves_transparent.png",
+                true,
+                RenderLayer::Cutout,
+            ),
+        ]
+        .iter()
+        {
+            Block::register(
+                String::from(name),
+                BlockData::new(
+                    solid,
+                    vec![(
+                        format!("assets/textures/PNG/Tiles/{}", texture),
+                        vec![
+                            FaceDirection::Up,
+                            FaceDirection::Down,
+                            FaceDirection::Left,
+                            FaceDirection::Right,
+                            FaceDirection::Front,
+                            FaceDirection::Back,
+                        ],
+                    )],
+                )
+                .with_layer(layer),
+            );
+        }
+
+        Block::register(
+            String::from("water"),
+            BlockData::new(
+                false,
+                vec![(
+                    String::from("assets/textures/animated/water.png"),
+                    vec![
+                        FaceDirection::Up,
+                        FaceDirection::Down,
+                        FaceDirection::Left,
+                        FaceDirection::Right,
+                        FaceDirection::Front,
+                        FaceDirection::Back,
+                    ],
+                )],
+            )
+            .with_layer(RenderLayer::Translucent)
+            .with_animation(4),
+        );
+
+        let fence = BlockModel::Boxes(vec![
+            ModelBox::new([0, 0, 7], [16, 16, 9]),
+            ModelBox::new([7, 0, 0], [9, 16, 16]),
+        ]);
+        for (name, texture, solid, model) in vec![
+            ("tall_grass_1", "grass1.png", false, BlockModel::Cross),
+            ("tall_grass_2", "grass2.png", false, BlockModel::Cross),
+            ("tall_grass_3", "grass3.png", false, BlockModel::Cross),
+            ("tall_grass_4", "grass4.png", false, BlockModel::Cross),
+            (
+                "brown_mushroom",
+                "mushroom_brown.png",
+                false,
+                BlockModel::Cross,
+            ),
+            ("red_mushroom", "mushroom_red.png", false, BlockModel::Cross),
+            ("tan_mushroom", "mushroom_tan.png", false, BlockModel::Cross),
+            ("wheat_1", "wheat_stage1.png", false, BlockModel::Cross),
+            ("wheat_2", "wheat_stage2.png", false, BlockModel::Cross),
+            ("wheat_3", "wheat_stage3.png", false, BlockModel::Cross),
+            ("wheat_4", "wheat_stage4.png", false, BlockModel::Cross),
+            ("stone_slab", "stone.png", true, BlockModel::Slab),
+            ("wood_slab", "wood.png", true, BlockModel::Slab),
+            ("wood_fence", "fence_wood.png", true, fence.clone()),
+            ("stone_fence", "fence_stone.png", true, fence),
+            ("track", "track_straight.png", false, BlockModel::Flat),
+            ("track_corner", "track_corner.png", false, BlockModel::Flat),
+        ] {
+            let layer = match model {
+                BlockModel::Slab => RenderLayer::Opaque,
+                _ => RenderLayer::Cutout,
+            };
+
+            Block::register(
+                String::from(name),
+                BlockData::new(
+                    solid,
+                    vec![(
+                        format!("assets/textures/PNG/Tiles/{}", texture),
+                        vec![
+                            FaceDirection::Up,
+                            FaceDirection::Down,
+                            FaceDirection::Left,
+                            FaceDirection::Right,
+                            FaceDirection::Front,
+                            FaceDirection::Back,
+                        ],
+                    )],
+                )
+                .with_layer(layer)
+                .with_model(model),
+            );
+        }
     }
 
-    pub fn generate_atlas(display: &Display) -> BlockTextures {
-        let mut textures = vec![RawImage2d::from_raw_rgba(vec![], (0, 0))];
+    /// Loads the textures of every registered block into a texture array, preferring files from
+    /// the resource pack directory if one is given.
+    #[cfg(feature = "client")]
+    pub fn generate_atlas<F: Facade>(
+        display: &F,
+        resource_pack: Option<&Path>,
+    ) -> io::Result<BlockTextures> {
+        let mut builder = TextureArrayBuilder::new();
+        if let Some(dir) = resource_pack {
+            builder = builder.with_resource_pack(dir);
+        }
 
         for data in BLOCK_DATA_MAP.write().unwrap().iter_mut() {
             data.textures = [0; 6];
             for (asset, dirs) in data.texture_assets.iter() {
-                let texture = image::open(asset).unwrap();
-                let dims = texture.dimensions();
-                let texture = RawImage2d::from_raw_rgba_reversed(&texture.into_rgba8(), dims);
-                let index = textures.len() as u32;
-                textures.push(texture);
+                let texture = match data.frame_ticks {
+                    Some(frame_ticks) => {
+                        let (layer, frames) = builder.add_animated(asset)?;
+                        textures::pack_texture(layer, frames, frame_ticks)?
+                    }
+                    None => textures::pack_texture(builder.add(asset)?, 1, 0)?,
+                };
                 for &dir in dirs {
-                    data.textures[dir as usize] = index;
+                    data.textures[dir as usize] = texture;
                 }
             }
         }
 
-        if let Some(texture) = textures.get(1) {
-            let width = texture.width;
-            let height = texture.height;
-            textures[0] = RawImage2d::from_raw_rgba_reversed(
-                &repeat(0u8)
-                    .take(width as usize * height as usize * 4)
-                    .collect::<Vec<_>>(),
-                (width, height),
-            );
-        }
-
-        let texture_count = textures.len() as u32;
-        let textures = RawImage3d::from_vec_raw2d(&textures);
-        let textures = SrgbTexture3d::new(display, textures).unwrap();
-        BlockTextures {
-            textures,
-            texture_count,
-        }
+        let layers = builder.build();
+        let size = layers.size;
+        let images = layers
+            .layers
+            .into_iter()
+            .map(|layer| RawImage2d::from_raw_rgba_reversed(&layer.into_raw(), (size, size)))
+            .collect();
+        let textures =
+            SrgbTexture2dArray::with_mipmaps(display, images, MipmapsOption::AutoGeneratedMipmaps)
+                .map_err(|e| {
+                    io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("could not create the block texture array: {}", e),
+                    )
+                })?;
+        Ok(BlockTextures { textures })
     }
 
     pub fn register(name: String, data: BlockData) -> Block {
@@ -168,11 +384,19 @@ impl Block {
         Block(u32::MAX)
     }
 
+    /// Returns every registered block other than air, in registration order.
+    pub fn registered() -> Vec<Block> {
+        let count = BLOCK_DATA_MAP.read().unwrap().len() as u32;
+        (1..count).map(Block).collect()
+    }
+
     pub fn get(name: &str) -> Option<Block> {
         let lock = ID_BLOCK_MAP.read().unwrap();
         lock.get(name).cloned()
     }
 
+    /// Returns the texture word of a face, see [`crate::textures::pack_texture`]. Textures are
+    /// only assigned once the atlas has been generated.
     pub fn get_texture(&self, face: FaceDirection) -> Option<u32> {
         BLOCK_DATA_MAP
             .read()
@@ -181,6 +405,19 @@ impl Block {
             .map(|v| v.textures[face as usize])
     }
 
+    pub fn get_texture_asset(&self, face: FaceDirection) -> Option<String> {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .and_then(|v| {
+                v.texture_assets
+                    .iter()
+                    .find(|(_, dirs)| dirs.contains(&face))
+                    .map(|(asset, _)| asset.clone())
+            })
+    }
+
     pub fn is_solid(&self) -> Option<bool> {
         BLOCK_DATA_MAP
             .read()
@@ -188,12 +425,112 @@ impl Block {
             .get(self.0 as usize)
             .map(|v| v.solid)
     }
+
+    pub fn render_layer(&self) -> Option<RenderLayer> {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.layer)
+    }
+
+    pub fn model(&self) -> Option<BlockModel> {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.model.clone())
+    }
+
+    /// Returns whether the block is drawn as a full cube.
+    pub fn is_cube(&self) -> bool {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.model == BlockModel::Cube)
+            .unwrap_or(false)
+    }
+
+    /// Returns the boxes the block collides with. Blocks that are not solid have none.
+    pub fn collision_boxes(&self) -> Vec<ModelBox> {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .filter(|v| v.solid)
+            .map(|v| v.model.collision_boxes())
+            .unwrap_or_default()
+    }
+
+    /// Returns the boxes the block can be targeted by, whether it is solid or not. Air has none.
+    pub fn selection_boxes(&self) -> Vec<ModelBox> {
+        if *self == Block::air() {
+            return vec![];
+        }
+
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.model.selection_boxes())
+            .unwrap_or_default()
+    }
+
+    /// Returns the red, green and blue light the block gives off, or `None` if it gives off none.
+    pub fn light_colour(&self) -> Option<[u8; 3]> {
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.light)
+            .filter(|&colour| colour != [0; 3])
+    }
+
+    /// Returns whether light can pass through the block. Only opaque full cubes stop it.
+    pub fn transmits_light(&self) -> bool {
+        if *self == Block::air() {
+            return true;
+        }
+
+        BLOCK_DATA_MAP
+            .read()
+            .unwrap()
+            .get(self.0 as usize)
+            .map(|v| v.layer != RenderLayer::Opaque || v.model != BlockModel::Cube)
+            .unwrap_or(false)
+    }
+
+    /// Returns whether this block hides the face of `other` that looks towards it in the given
+    /// direction.
+    ///
+    /// Only blocks whose model covers the whole side touching the face hide it. Of those, opaque
+    /// blocks hide every face while cutout and translucent blocks only hide faces of the same
+    /// block, so that the inside of a lake or a glass wall is not drawn.
+    pub fn hides_face_of(&self, other: Block, direction: FaceDirection) -> bool {
+        if *self == Block::air() {
+            return false;
+        }
+
+        match BLOCK_DATA_MAP.read().unwrap().get(self.0 as usize) {
+            Some(data) => {
+                data.covers[direction.opposite() as usize]
+                    && (data.layer == RenderLayer::Opaque || *self == other)
+            }
+            None => true,
+        }
+    }
 }
 
 pub struct BlockData {
     texture_assets: Vec<(String, Vec<FaceDirection>)>,
     textures: [u32; 6],
     solid: bool,
+    layer: RenderLayer,
+    model: BlockModel,
+    covers: [bool; 6],
+    frame_ticks: Option<u32>,
+    light: [u8; 3],
 }
 
 impl BlockData {
@@ -202,6 +539,41 @@ impl BlockData {
             texture_assets,
             textures: [0; 6],
             solid,
+            layer: RenderLayer::Opaque,
+            model: BlockModel::Cube,
+            covers: [true; 6],
+            frame_ticks: None,
+            light: [0; 3],
+        }
+    }
+
+    /// Sets the layer the block is drawn in. Blocks are opaque by default.
+    pub fn with_layer(mut self, layer: RenderLayer) -> BlockData {
+        self.layer = layer;
+        self
+    }
+
+    /// Makes the block's textures animated. Each texture is a vertical strip of frames, which are
+    /// shown for the given number of client ticks each.
+    pub fn with_animation(mut self, frame_ticks: u32) -> BlockData {
+        self.frame_ticks = Some(frame_ticks);
+        self
+    }
+
+    /// Makes the block give off light of the given colour, each channel from `0` to
+    /// [`MAX_LIGHT`](crate::lighting::MAX_LIGHT). The client draws it as a dynamic light.
+    pub fn with_light(mut self, red: u8, green: u8, blue: u8) -> BlockData {
+        let max = crate::lighting::MAX_LIGHT;
+        self.light = [red.min(max), green.min(max), blue.min(max)];
+        self
+    }
+
+    /// Sets the shape of the block. Blocks are full cubes by default.
+    pub fn with_model(mut self, model: BlockModel) -> BlockData {
+        for &direction in FaceDirection::ALL.iter() {
+            self.covers[direction as usize] = model.covers(direction);
         }
+        self.model = model;
+        self
     }
 }
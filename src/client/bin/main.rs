@@ -1,5 +1,9 @@
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
@@ -12,38 +16,122 @@ use glium::glutin::{
     ContextBuilder,
 };
 use glium::{Display, PolygonMode, Program, Surface};
-use minecraft_rust::blocks::Block;
-use minecraft_rust::client::light::LightSource;
+use minecraft_rust::blocks::{Block, FaceDirection};
+use minecraft_rust::clock::WorldClock;
 use minecraft_rust::collision::DetectCollision;
+use minecraft_rust::coords;
+use minecraft_rust::lighting::LightMap;
+use minecraft_rust::lod::{self, LodChunk};
+use minecraft_rust::mesh::MeshMode;
+use minecraft_rust::visibility::{self, Visibility};
 use tokio::net::UdpSocket;
 use tokio::sync::mpsc;
 
 use minecraft_rust::client::camera::{Camera, RaycastAction};
-use minecraft_rust::client::chunk::{Chunk, ChunkWaiter, InstanceData, Mesh};
+use minecraft_rust::client::capture::{self, CameraPath, PathPlayback};
+use minecraft_rust::client::chunk::{self, Chunk, ChunkMesh, Mesh, ANIMATION_TICK};
+use minecraft_rust::client::mesher::MeshScheduler;
+use minecraft_rust::client::overlay::{BlockOverlay, BreakProgress};
 use minecraft_rust::client::player::Player;
+use minecraft_rust::client::renderer::ChunkRenderer;
+use minecraft_rust::client::settings::ClientSettings;
+use minecraft_rust::client::store::ChunkStore;
 use minecraft_rust::packet::{ServerPacket, UserPacket};
 
 const USERNAME: &str = "uwu";
+const SETTINGS: &str = "client.toml";
+/// Textures in this directory replace the default ones, see [`minecraft_rust::textures`].
+const RESOURCE_PACK_DIR: &str = "resourcepack";
 const ADDRESS: &str = "0.0.0.0:6942";
+/// Chunks up to this many chunks from the origin are loaded in full detail.
+const FULL_DETAIL_DISTANCE: i32 = 3;
+/// Heights of the chunks that are loaded.
+const CHUNK_HEIGHTS: RangeInclusive<i32> = -1..=1;
+/// Downsampled chunks are requested again if they have not arrived after this long.
+const LOD_REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+/// Most downsampled chunks requested in a single frame, so that they do not crowd out other
+/// packets.
+const LOD_REQUESTS_PER_FRAME: usize = 8;
+/// Seconds of a camera path between captured frames, unless given on the command line.
+const DEFAULT_CAPTURE_INTERVAL: f32 = 1.0 / 30.0;
+/// Most threads meshing chunks.
+const MAX_MESH_WORKERS: usize = 4;
+/// Chunks queued for each mesh worker at a time.
+const MESH_JOBS_PER_WORKER: usize = 2;
 
 const CHUNKS_VERTEX_SHADER: &str = include_str!("../shaders/chunks-vertex.glsl");
 const CHUNKS_FRAGMENT_SHADER: &str = include_str!("../shaders/chunks-fragment.glsl");
 const ENTITY_VERTEX_SHADER: &str = include_str!("../shaders/entity-vertex.glsl");
 const ENTITY_FRAGMENT_SHADER: &str = include_str!("../shaders/entity-fragment.glsl");
+const OVERLAY_VERTEX_SHADER: &str = include_str!("../shaders/overlay-vertex.glsl");
+const OVERLAY_FRAGMENT_SHADER: &str = include_str!("../shaders/overlay-fragment.glsl");
 
 fn main() {
     let (send_tx, send_rx) = mpsc::channel(128);
     let (recv_tx, recv_rx) = mpsc::channel(128);
     let send_tx2 = send_tx.clone();
+    let playback = parse_args();
     thread::spawn(|| networking_loop(send_tx2, send_rx, recv_tx));
-    main_loop(send_tx, recv_rx);
+    main_loop(send_tx, recv_rx, playback);
 }
 
-fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>) {
+/// Reads the camera path to play back from `--camera-path <file>`, capturing a frame every
+/// `--capture-interval <seconds>` of it into a directory named after the file.
+fn parse_args() -> Option<(PathPlayback, PathBuf)> {
+    let mut path = None;
+    let mut interval = DEFAULT_CAPTURE_INTERVAL;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--camera-path", Some(file)) => path = Some(PathBuf::from(file)),
+            ("--capture-interval", Some(seconds)) => match seconds.parse::<f32>() {
+                Ok(seconds) if seconds > 0.0 => interval = seconds,
+                _ => {
+                    eprintln!("Invalid capture interval {}", seconds);
+                    process::exit(1);
+                }
+            },
+            _ => {
+                eprintln!("Usage: client [--camera-path <file> [--capture-interval <seconds>]]");
+                process::exit(1);
+            }
+        }
+    }
+
+    let path = path?;
+    let camera_path = match CameraPath::load(&path) {
+        Ok(camera_path) => camera_path,
+        Err(e) => {
+            eprintln!("Could not load camera path {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+    let name = path.file_stem().unwrap_or(path.as_os_str());
+    let dir = Path::new(capture::SCREENSHOT_DIR).join(name);
+    println!(
+        "Capturing camera path {} to {}",
+        path.display(),
+        dir.display()
+    );
+    Some((PathPlayback::new(camera_path, interval), dir))
+}
+
+fn main_loop(
+    tx: mpsc::Sender<UserPacket>,
+    mut rx: mpsc::Receiver<ServerPacket>,
+    mut playback: Option<(PathPlayback, PathBuf)>,
+) {
     let event_loop = EventLoop::new();
     let wb = WindowBuilder::new();
     let cb = ContextBuilder::new().with_depth_buffer(24);
     let display = Display::new(wb, cb, &event_loop).unwrap();
+    let settings = match ClientSettings::load(SETTINGS) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Could not load {}: {}", SETTINGS, e);
+            process::exit(1);
+        }
+    };
     let mut locked;
 
     {
@@ -63,6 +151,13 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
         Program::from_source(&display, CHUNKS_VERTEX_SHADER, CHUNKS_FRAGMENT_SHADER, None).unwrap();
     let entity_program =
         Program::from_source(&display, ENTITY_VERTEX_SHADER, ENTITY_FRAGMENT_SHADER, None).unwrap();
+    let overlay_program = Program::from_source(
+        &display,
+        OVERLAY_VERTEX_SHADER,
+        OVERLAY_FRAGMENT_SHADER,
+        None,
+    )
+    .unwrap();
 
     let mut params = glium::DrawParameters {
         depth: glium::Depth {
@@ -75,33 +170,74 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
     };
 
     let mut camera = Camera::new(10.0, 0.001, 90.0);
-    let lights = Arc::new(RwLock::new(vec![
-        LightSource::new(10, 10, 10, camera.get_pos()),
-        LightSource::new(10, 11, 12, [8.0, 0.0, 0.0]),
-        LightSource::new(12, 11, 10, [0.0, 0.0, 8.0]),
-    ]));
     let mut players = HashMap::new();
-    let chunks = Arc::new(RwLock::new(HashMap::new()));
+    // The last clock received from the server and when it was received.
+    let mut clock = (WorldClock::default(), Instant::now());
+    let chunks = Arc::new(ChunkStore::new());
+    let light = Arc::new(RwLock::new(LightMap::new()));
     let square = Mesh::square(&display);
+    let mut renderer = ChunkRenderer::new(&display);
+    let block_overlay = BlockOverlay::new(&display);
+    // Blocks are broken by holding the right mouse button down on them.
+    let mut breaking = BreakProgress::default();
+    let mut breaking_held = false;
+    // Whether to save the next frame drawn.
+    let mut screenshot = false;
     Block::register_defaults();
-    let block_textures = Block::generate_atlas(&display);
+    let resource_pack = Path::new(RESOURCE_PACK_DIR);
+    let resource_pack = if resource_pack.is_dir() {
+        println!("Using resource pack {}", resource_pack.display());
+        Some(resource_pack)
+    } else {
+        None
+    };
+    let block_textures = match Block::generate_atlas(&display, resource_pack) {
+        Ok(textures) => textures,
+        Err(e) => {
+            eprintln!("Could not load block textures: {}", e);
+            process::exit(1);
+        }
+    };
 
-    for x in -3..=3 {
-        for y in -1..=1 {
-            for z in -3..=3 {
-                chunks
-                    .write()
-                    .unwrap()
-                    .insert((x, y, z), RwLock::new(ChunkWaiter::Timestamp(0)));
+    for x in -FULL_DETAIL_DISTANCE..=FULL_DETAIL_DISTANCE {
+        for y in CHUNK_HEIGHTS {
+            for z in -FULL_DETAIL_DISTANCE..=FULL_DETAIL_DISTANCE {
+                chunks.request((x, y, z));
             }
         }
     }
 
-    let (tx2, mut chunk_data_rx) = mpsc::channel(128);
-    let (chunk_data_tx, rx2) = mpsc::channel(128);
-    let chunks2 = chunks.clone();
-    thread::spawn(|| mesh_loop(chunks2, tx2, rx2));
-
+    // Chunks are meshed by a pool of workers sharing one queue, which snapshot them themselves.
+    let mut mesh_mode = MeshMode::Greedy;
+    let mut scheduler = MeshScheduler::new();
+    let (job_tx, job_rx) = mpsc::channel(128);
+    let (mesh_tx, mut mesh_rx) = mpsc::channel(128);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let mesh_workers = mesh_workers();
+    for _ in 0..mesh_workers {
+        let job_rx = job_rx.clone();
+        let mesh_tx = mesh_tx.clone();
+        let chunks = chunks.clone();
+        let light = light.clone();
+        thread::spawn(|| mesh_loop(job_rx, mesh_tx, chunks, light));
+    }
+    // Chunks whose lights are picked again at the end of the frame, as the emissive blocks around
+    // them changed or they got their first mesh.
+    let mut relight = HashSet::new();
+
+    // Downsampled chunks around the camera with their scale, the scale each chunk should have,
+    // closest first, and the scales requested from the server with when they were requested.
+    // Their meshes are kept by the renderer like those of full chunks.
+    let mut lods: HashMap<(i32, i32, i32), usize> = HashMap::new();
+    let mut lod_rings = HashMap::new();
+    let mut lod_order = vec![];
+    let mut lod_requests: HashMap<(i32, i32, i32), (usize, Instant)> = HashMap::new();
+    let mut lod_centre = None;
+    let (lod_tx, lod_mesh_rx) = mpsc::channel(128);
+    let (lod_mesh_tx, mut lod_rx) = mpsc::channel(128);
+    thread::spawn(|| lod_loop(lod_mesh_tx, lod_mesh_rx));
+
+    let start = Instant::now();
     let mut frame_count = 0;
     let mut last = Instant::now();
     let mut last_frame = last;
@@ -137,7 +273,14 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
                     }
 
                     WindowEvent::KeyboardInput { input, .. }
-                        if locked && camera.move_self(input) => {},
+                        if matches!(input.virtual_keycode, Some(VirtualKeyCode::F2))
+                            && matches!(input.state, ElementState::Released) =>
+                    {
+                        screenshot = true;
+                    }
+
+                    WindowEvent::KeyboardInput { input, .. }
+                        if locked && camera.move_self(input) => {}
 
                     WindowEvent::KeyboardInput { input, .. } if locked => {
                         if let Some(VirtualKeyCode::Semicolon) = input.virtual_keycode {
@@ -148,6 +291,15 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
                                     PolygonMode::Fill => params.polygon_mode = PolygonMode::Point,
                                 }
                             }
+                        } else if let Some(VirtualKeyCode::G) = input.virtual_keycode {
+                            if input.state == ElementState::Released {
+                                mesh_mode = mesh_mode.toggled();
+                                println!("Meshing mode: {:?}", mesh_mode);
+
+                                for (coords, _) in chunks.loaded() {
+                                    scheduler.mark_dirty(coords);
+                                }
+                            }
                         }
                     }
 
@@ -156,47 +308,31 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
                         let window = gl_window.window();
                         let size = window.inner_size();
                         let centre = PhysicalPosition::new(size.width / 2, size.height / 2);
-                        camera.raycast(
-                            &display,
-                            &*chunks.read().unwrap(),
-                            RaycastAction::Unselect,
-                            &chunk_data_tx,
-                        );
                         camera.turn_self(
                             position.x as i32 - centre.x as i32,
                             position.y as i32 - centre.y as i32,
                         );
-                        camera.raycast(
-                            &display,
-                            &*chunks.read().unwrap(),
-                            RaycastAction::Select,
-                            &chunk_data_tx,
-                        );
                         window.set_cursor_position(centre).unwrap();
                     }
 
-                    WindowEvent::MouseInput { button, state, .. } if locked => {
-                        if state == ElementState::Pressed {
-                            match button {
-                                MouseButton::Left => camera.raycast(
-                                    &display,
-                                    &chunks.read().unwrap(),
-                                    RaycastAction::Place(
-                                        Block::get("solid").unwrap_or_else(Block::air),
-                                    ),
-                                    &chunk_data_tx,
-                                ),
-                                MouseButton::Right => {
-                                    camera.raycast(
-                                        &display,
-                                        &*chunks.read().unwrap(),
-                                        RaycastAction::Remove,
-                                        &chunk_data_tx,
-                                    );
-                                }
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Right,
+                        state,
+                        ..
+                    } => {
+                        breaking_held = state == ElementState::Pressed;
+                    }
 
-                                _ => (),
-                            }
+                    WindowEvent::MouseInput {
+                        button: MouseButton::Left,
+                        state: ElementState::Pressed,
+                        ..
+                    } if locked => {
+                        let block = Block::get("solid").unwrap_or_else(Block::air);
+                        let remesh = camera.raycast(&chunks, &light, RaycastAction::Place(block));
+                        for coords in remesh {
+                            scheduler.mark_dirty(coords);
+                            relight_around(&mut relight, coords);
                         }
                     }
 
@@ -233,7 +369,7 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
         let delta = Instant::now() - last;
         last = Instant::now();
 
-        let mut to_send = vec![];
+        let mut new_chunks = vec![];
         while let Ok(packet) = rx.try_recv() {
             match packet {
                 ServerPacket::ConnectionAccepted => (),
@@ -254,6 +390,16 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
                     }
                 }
 
+                ServerPacket::WorldTime { clock: new } => {
+                    clock = (new, Instant::now());
+                }
+
+                ServerPacket::NewLod { lod } => {
+                    if lod_rings.get(&lod.coords()) == Some(&lod.scale()) {
+                        let _ = lod_tx.try_send(lod);
+                    }
+                }
+
                 ServerPacket::NewChunk { chunk } => {
                     let coords = (
                         chunk.get_chunk_x(),
@@ -261,86 +407,109 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
                         chunk.get_chunk_z(),
                     );
 
-                    let mut chunks = chunks.write().unwrap();
-                    chunks.insert(
-                        coords,
-                        RwLock::new(ChunkWaiter::Chunk(Chunk::from_server_chunk(
-                            &display, chunk,
-                        ))),
-                    );
+                    chunks.insert(Chunk::from_server_chunk(chunk));
                     let (x, y, z) = coords;
-                    to_send.push((x, y, z));
-
-                    if let Some(chunk) = chunks.get(&(x - 1, y, z)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x - 1, y, z));
-                        }
-                    }
-                    if let Some(chunk) = chunks.get(&(x + 1, y, z)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x + 1, y, z));
-                        }
-                    }
-                    if let Some(chunk) = chunks.get(&(x, y - 1, z)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x, y - 1, z));
-                        }
-                    }
-                    if let Some(chunk) = chunks.get(&(x, y + 1, z)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x, y + 1, z));
-                        }
-                    }
-                    if let Some(chunk) = chunks.get(&(x, y, z - 1)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x, y, z - 1));
-                        }
-                    }
-                    if let Some(chunk) = chunks.get(&(x, y, z + 1)) {
-                        if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                            to_send.push((x, y, z + 1));
+                    scheduler.mark_dirty(coords);
+                    new_chunks.push(coords);
+
+                    for &direction in FaceDirection::ALL.iter() {
+                        let offset = direction.offset();
+                        let neighbour = (
+                            x + offset[0] as i32,
+                            y + offset[1] as i32,
+                            z + offset[2] as i32,
+                        );
+                        if chunks.is_loaded(neighbour) {
+                            scheduler.mark_dirty(neighbour);
                         }
                     }
                 }
             }
         }
-        if !to_send.is_empty() {
-            chunk_data_tx.blocking_send(to_send).unwrap();
+        for coords in new_chunks {
+            relight_around(&mut relight, coords);
+            let lit = light
+                .write()
+                .unwrap()
+                .add_chunk(coords, |block| chunks.block(block));
+            for coords in lit {
+                scheduler.mark_dirty(coords);
+            }
         }
 
-        while let Ok(v) = chunk_data_rx.try_recv() {
-            for (coords, instance_data) in v {
-                if let Some(chunk) = chunks.read().unwrap().get(&coords) {
-                    if let ChunkWaiter::Chunk(chunk) = &mut *chunk.write().unwrap() {
-                        chunk.set_mesh(&display, instance_data);
-                        chunk.invalidate_selection();
-                    }
+        while let Ok((coords, mesh)) = mesh_rx.try_recv() {
+            scheduler.finish(coords);
+            let mesh = match mesh {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+            let visibility = mesh.visibility;
+            if chunks
+                .update(coords, |chunk| chunk.set_visibility(visibility))
+                .is_some()
+            {
+                // Lights are kept with the mesh, so a chunk drawn for the first time needs them.
+                let first = !renderer.contains(coords);
+                renderer.set_mesh(&display, coords, mesh);
+                if first {
+                    relight.insert(coords);
                 }
             }
         }
+        // Replace downsampled chunks only once their new mesh is ready, so that nothing
+        // disappears while the scale changes.
+        while let Ok((lod, mesh)) = lod_rx.try_recv() {
+            let coords = lod.coords();
+            if lod_rings.get(&coords) == Some(&lod.scale()) {
+                lod_requests.remove(&coords);
+                lods.insert(coords, lod.scale());
+                renderer.set_mesh(&display, coords, mesh);
+            }
+        }
 
-        camera.raycast(
-            &display,
-            &*chunks.read().unwrap(),
-            RaycastAction::Unselect,
-            &chunk_data_tx,
-        );
-        camera.tick(delta);
-        camera.raycast(
-            &display,
-            &*chunks.read().unwrap(),
-            RaycastAction::Select,
-            &chunk_data_tx,
-        );
+        for coords in relight.drain() {
+            if chunks.contains(coords) {
+                let lights = chunk::select_chunk_lights(&chunks, coords);
+                renderer.set_lights(coords, &lights);
+            }
+        }
+
+        camera.tick(delta, &chunks);
+        // While a camera path plays, it moves the camera instead of the player. It only starts or
+        // moves on once every wanted chunk has arrived and been meshed, so that no frame is
+        // captured of a world that is still loading.
+        let mut captured_frame = None;
+        let loaded = scheduler.is_idle() && !chunks.has_requests();
+        if let Some((playback, _)) = playback.as_mut().filter(|_| loaded) {
+            match playback.next_frame() {
+                Some((frame, (position, target))) => {
+                    camera.look_at(position, target);
+                    captured_frame = Some(frame);
+                    let _ = tx.try_send(UserPacket::MoveSelf { pos: position });
+                }
+                None => {
+                    println!("Camera path finished");
+                    *control_flow = ControlFlow::Exit;
+                    tx.blocking_send(UserPacket::Disconnect).unwrap();
+                    return;
+                }
+            }
+        }
+        let selected = camera.target(&chunks);
+        if breaking
+            .update(selected, locked && breaking_held, delta)
+            .is_some()
+        {
+            for coords in camera.raycast(&chunks, &light, RaycastAction::Remove) {
+                scheduler.mark_dirty(coords);
+                relight_around(&mut relight, coords);
+            }
+        }
         if camera.is_moving() {
             let _ = tx.try_send(UserPacket::MoveSelf {
                 pos: camera.get_pos(),
             });
-            if let Some(light) = lights.write().unwrap().get_mut(0) {
-                light.set_location(camera.get_pos());
-            }
-
-            //camera.check_loaded_chunks(&mut *chunks.write().unwrap());
+            //camera.check_loaded_chunks(&chunks);
         }
 
         for (name, player) in players.iter() {
@@ -349,84 +518,294 @@ fn main_loop(tx: mpsc::Sender<UserPacket>, mut rx: mpsc::Receiver<ServerPacket>)
             }
         }
 
+        let camera_chunk = coords::block_to_chunk(coords::world_to_block(camera.get_pos())).0;
+
+        // Only a few chunks per worker are queued at a time, so that chunks marked dirty later
+        // but closer to the camera can still go first.
+        while scheduler.meshing() < mesh_workers * MESH_JOBS_PER_WORKER {
+            let coords = match scheduler.next(camera_chunk) {
+                Some(coords) => coords,
+                None => break,
+            };
+
+            if chunks.is_loaded(coords) {
+                job_tx.try_send((coords, mesh_mode)).unwrap();
+            } else {
+                // The chunk has not arrived yet, it is marked dirty again when it does.
+                scheduler.finish(coords);
+            }
+        }
+        if lod_centre != Some(camera_chunk) {
+            lod_rings = lod::lod_rings(
+                camera_chunk,
+                FULL_DETAIL_DISTANCE,
+                settings.view_distance,
+                CHUNK_HEIGHTS,
+                |coords| chunks.contains(coords),
+            );
+            lod_order = lod_rings.keys().copied().collect();
+            lod_order.sort_by_key(|&coords| lod::chunk_distance(camera_chunk, coords));
+            lods.retain(|&coords, _| {
+                let keep = lod_rings.contains_key(&coords);
+                if !keep && !chunks.contains(coords) {
+                    renderer.remove(coords);
+                }
+                keep
+            });
+            lod_requests.retain(|coords, _| lod_rings.contains_key(coords));
+            lod_centre = Some(camera_chunk);
+        }
+
+        // Ask for the closest downsampled chunks that are missing or have the wrong scale first.
+        let mut requested = 0;
+        for &coords in lod_order.iter() {
+            if requested == LOD_REQUESTS_PER_FRAME {
+                break;
+            }
+
+            let scale = lod_rings[&coords];
+            if lods.get(&coords) == Some(&scale) {
+                continue;
+            }
+            if let Some(&(requested_scale, time)) = lod_requests.get(&coords) {
+                if requested_scale == scale && time.elapsed() < LOD_REQUEST_TIMEOUT {
+                    continue;
+                }
+            }
+
+            let (x, y, z) = coords;
+            if tx
+                .try_send(UserPacket::RequestLod { x, y, z, scale })
+                .is_err()
+            {
+                break;
+            }
+            lod_requests.insert(coords, (scale, Instant::now()));
+            requested += 1;
+        }
+
         // RENDERING
 
         let mut target = display.draw();
-        target.clear_color_and_depth((0.53, 0.80, 0.92, 1.0), 1.0);
-
+        // The server only sends the clock now and then, it keeps running in between.
+        let world_clock = clock.0.after(clock.1.elapsed());
+        let sky_colour = world_clock.sky_colour();
+        let [red, green, blue] = sky_colour;
+        let fog = settings.fog(sky_colour);
+        target.clear_color_and_depth((red, green, blue, 1.0), 1.0);
+
+        let animation_tick = (start.elapsed().as_millis() / ANIMATION_TICK.as_millis()) as u32;
         let perspective = camera.perspective(&target);
         let view = camera.view_matrix();
-        let frustum = camera.frustum(&target);
-
-        for (_, chunk) in chunks.read().unwrap().iter() {
-            if let ChunkWaiter::Chunk(chunk) = &mut *chunk.write().unwrap() {
-                if chunk.loaded {
-                    chunk.select(&display, None);
-
-                    if chunk.aabb().is_in_frustum(&frustum) {
-                        chunk.render(
-                            &mut target,
-                            &chunks_program,
-                            perspective,
-                            view,
-                            &params,
-                            &square,
-                            &block_textures,
-                            &lights.read().unwrap(),
-                        );
+        let frustum = camera.frustum(target.get_dimensions());
+
+        // Only chunks that can be seen through open space from the camera's chunk are drawn,
+        // unless the camera is outside of the loaded chunks.
+        let visible = if chunks.contains(camera_chunk) {
+            Some(visibility::visible_chunks(
+                camera_chunk,
+                |coords| {
+                    if chunks.contains(coords) {
+                        let chunk = chunks.get(coords);
+                        Some(chunk.map_or(Visibility::OPEN, |chunk| chunk.visibility()))
+                    } else {
+                        None
                     }
-                }
+                },
+                |coords| coords::chunk_aabb(coords).is_in_frustum(&frustum),
+            ))
+        } else {
+            None
+        };
+
+        let pos = camera.get_pos();
+        let distance = |coords: (i32, i32, i32)| {
+            let centre = coords::chunk_aabb(coords).centre;
+            (centre[0] - pos[0]).powi(2)
+                + (centre[1] - pos[1]).powi(2)
+                + (centre[2] - pos[2]).powi(2)
+        };
+        let mut drawn = vec![];
+        for (coords, chunk) in chunks.loaded() {
+            let visible = visible
+                .as_ref()
+                .is_none_or(|visible| visible.contains(&coords));
+            if chunk.loaded && visible && chunk.aabb().is_in_frustum(&frustum) {
+                drawn.push(coords);
+            }
+        }
+        // Downsampled chunks are outside of the loaded chunks, so they are only culled by the
+        // frustum.
+        for &coords in lods.keys() {
+            if coords::chunk_aabb(coords).is_in_frustum(&frustum) {
+                drawn.push(coords);
             }
         }
 
+        renderer.render(
+            &mut target,
+            &chunks_program,
+            perspective,
+            view,
+            &params,
+            &square,
+            &block_textures,
+            animation_tick,
+            &world_clock,
+            &fog,
+            &drawn,
+        );
+
+        // Translucent faces are drawn last, from the furthest chunk to the closest, so that
+        // everything behind them is already on screen.
+        let mut translucent_chunks: Vec<_> = drawn
+            .into_iter()
+            .filter(|&coords| renderer.has_translucent_faces(coords))
+            .map(|coords| (coords, distance(coords)))
+            .collect();
+        translucent_chunks.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
+        let translucent_chunks: Vec<_> = translucent_chunks
+            .into_iter()
+            .map(|(coords, _)| coords)
+            .collect();
+        for &coords in translucent_chunks.iter() {
+            renderer.sort_translucent(coords, pos);
+        }
+        let translucent_params = glium::DrawParameters {
+            depth: glium::Depth {
+                write: false,
+                ..params.depth
+            },
+            blend: glium::Blend::alpha_blending(),
+            ..params.clone()
+        };
+        renderer.render_translucent(
+            &mut target,
+            &chunks_program,
+            perspective,
+            view,
+            &translucent_params,
+            &square,
+            &block_textures,
+            animation_tick,
+            &world_clock,
+            &fog,
+            &translucent_chunks,
+        );
+
+        if let Some(block) = selected {
+            let stage = breaking
+                .stage()
+                .filter(|&(broken, _)| broken == block)
+                .map(|(_, stage)| stage);
+            let boxes = chunks
+                .block(block)
+                .map_or_else(Vec::new, |b| b.selection_boxes());
+            block_overlay.render(
+                &mut target,
+                &overlay_program,
+                perspective,
+                view,
+                block,
+                &boxes,
+                stage,
+            );
+        }
+
         let timestamp = SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_nanos();
-        for ((x, y, z), chunk) in chunks.read().unwrap().iter() {
-            let mut chunk = chunk.write().unwrap();
-            if let Some(stamp) = chunk.timestamp() {
-                if timestamp - stamp > 100_000 {
-                    *chunk = ChunkWaiter::Timestamp(timestamp);
-                    let _ = tx.try_send(UserPacket::RequestChunk {
-                        x: *x,
-                        y: *y,
-                        z: *z,
-                    });
-                }
-            }
+        for (x, y, z) in chunks.due_requests(timestamp, 100_000) {
+            let _ = tx.try_send(UserPacket::RequestChunk { x, y, z });
         }
 
         for (_, player) in players.iter() {
-            player.render(&mut target, &entity_program, perspective, view, &params);
+            player.render(
+                &mut target,
+                &entity_program,
+                perspective,
+                view,
+                &params,
+                &fog,
+            );
         }
 
         target.finish().unwrap();
 
+        if screenshot || captured_frame.is_some() {
+            let image = capture::image_from_rows(display.read_front_buffer().unwrap());
+            if screenshot {
+                match capture::save_screenshot(&image) {
+                    Ok(path) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("Could not save screenshot: {}", e),
+                }
+                screenshot = false;
+            }
+            if let (Some(frame), Some((_, dir))) = (captured_frame, playback.as_ref()) {
+                if let Err(e) = capture::save_frame(&image, dir, frame) {
+                    eprintln!("Could not save frame {}: {}", frame, e);
+                }
+            }
+        }
+
         let next_frame_time = Instant::now() + Duration::from_nanos(5);
         *control_flow = ControlFlow::WaitUntil(next_frame_time);
     });
 }
 
-#[allow(clippy::type_complexity)]
-fn mesh_loop(
-    chunks: Arc<RwLock<HashMap<(i32, i32, i32), RwLock<ChunkWaiter>>>>,
-    tx: mpsc::Sender<Vec<((i32, i32, i32), Vec<InstanceData>)>>,
-    mut rx: mpsc::Receiver<Vec<(i32, i32, i32)>>,
-) {
-    while let Some(v) = rx.blocking_recv() {
-        let mut result = vec![];
-        for coords in v {
-            if let Some(chunk) = chunks.read().unwrap().get(&coords) {
-                let chunk = chunk.read().unwrap();
-                if let ChunkWaiter::Chunk(chunk) = &*chunk {
-                    let mesh = chunk.generate_mesh(&*chunks.read().unwrap());
-                    result.push((coords, mesh));
-                }
+/// Returns how many threads mesh chunks, leaving a core for the render thread.
+fn mesh_workers() -> usize {
+    thread::available_parallelism()
+        .map(|cores| cores.get().saturating_sub(1))
+        .unwrap_or(1)
+        .clamp(1, MAX_MESH_WORKERS)
+}
+
+/// Marks the lights of a chunk and the chunks around it to be picked again, as emissive blocks
+/// light the chunks around them too.
+fn relight_around(relight: &mut HashSet<(i32, i32, i32)>, (x, y, z): (i32, i32, i32)) {
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                relight.insert((x + dx, y + dy, z + dz));
             }
         }
+    }
+}
+
+/// The queue of chunks waiting to be meshed, shared between the mesh workers.
+type MeshJobs = Arc<Mutex<mpsc::Receiver<((i32, i32, i32), MeshMode)>>>;
+
+/// Snapshots and meshes chunks, taking turns with the other workers to take them from the queue.
+/// Sends back `None` for chunks that are no longer loaded.
+fn mesh_loop(
+    rx: MeshJobs,
+    tx: mpsc::Sender<((i32, i32, i32), Option<ChunkMesh>)>,
+    chunks: Arc<ChunkStore>,
+    light: Arc<RwLock<LightMap>>,
+) {
+    loop {
+        // The queue is only locked while waiting for a chunk, not while meshing it.
+        let (coords, mode) = match rx.lock().unwrap().blocking_recv() {
+            Some(job) => job,
+            None => return,
+        };
+        // The light map is only read while copying, so that the render thread can update it
+        // while the chunk is being meshed.
+        let snapshot = chunks
+            .get(coords)
+            .map(|chunk| chunk.snapshot(&chunks, &light.read().unwrap(), mode));
+        tx.blocking_send((coords, snapshot.map(|snapshot| snapshot.mesh())))
+            .unwrap();
+    }
+}
 
-        tx.blocking_send(result).unwrap();
+/// Meshes downsampled chunks as they arrive.
+fn lod_loop(tx: mpsc::Sender<(LodChunk, ChunkMesh)>, mut rx: mpsc::Receiver<LodChunk>) {
+    while let Some(lod) = rx.blocking_recv() {
+        let mesh = chunk::lod_mesh(&lod);
+        tx.blocking_send((lod, mesh)).unwrap();
     }
 }
 
@@ -516,12 +895,23 @@ async fn receiving(
                     .unwrap();
             }
 
+            ServerPacket::NewLod { lod } => {
+                recv_tx.send(ServerPacket::NewLod { lod }).await.unwrap();
+            }
+
             ServerPacket::NewChunk { chunk } => {
                 recv_tx
                     .send(ServerPacket::NewChunk { chunk })
                     .await
                     .unwrap();
             }
+
+            ServerPacket::WorldTime { clock } => {
+                recv_tx
+                    .send(ServerPacket::WorldTime { clock })
+                    .await
+                    .unwrap();
+            }
         }
     }
 }
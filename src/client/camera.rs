@@ -1,26 +1,27 @@
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    convert::TryInto,
-    sync::RwLock,
-    time::Duration,
-};
+use std::{sync::RwLock, time::Duration};
 
 use glium::{
     glutin::event::{ElementState, KeyboardInput, VirtualKeyCode},
-    Display, Frame, Surface,
+    Frame, Surface,
 };
-use nalgebra::Vector3;
-use tokio::sync::mpsc;
+use nalgebra::Matrix4;
 
 use crate::{
-    blocks::{Block, CHUNK_SIZE},
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
     collision::Aabb,
+    coords,
+    lighting::LightMap,
 };
 
-use super::{
-    chunk::{Chunk, ChunkWaiter},
-    shapes::frustum::{Frustum, Plane},
-};
+use super::{shapes::frustum::Frustum, store::ChunkStore};
+
+/// Distance the ray cast to find the targeted block moves forward at a time, in world units.
+const REACH_STEP: f32 = 0.25;
+/// Number of steps the ray cast to find the targeted block takes before giving up.
+const REACH_STEPS: u32 = 16;
+/// Half of the size of the box around the camera that blocks stop, in world units. It is smaller
+/// than a block, so that the camera fits through gaps one block wide.
+const BODY_EXTENTS: [f32; 3] = [0.15; 3];
 
 #[derive(Clone, Debug)]
 pub struct Camera {
@@ -56,6 +57,20 @@ impl Camera {
         self.position
     }
 
+    /// Moves the camera to `position`, facing `target`.
+    pub fn look_at(&mut self, position: [f32; 3], target: [f32; 3]) {
+        let direction = [
+            target[0] - position[0],
+            target[1] - position[1],
+            target[2] - position[2],
+        ];
+        let length = direction.iter().map(|d| d * d).sum::<f32>().sqrt();
+        if length > f32::EPSILON {
+            self.direction = direction.map(|d| d / length);
+        }
+        self.position = position;
+    }
+
     pub fn aabb(&self) -> Aabb {
         Aabb {
             centre: [
@@ -73,16 +88,34 @@ impl Camera {
             || self.velocity[2].abs() > f32::EPSILON
     }
 
-    pub fn tick(&mut self, delta: Duration) {
-        self.position[0] += (self.velocity[0] * self.direction[0]
-            + self.velocity[2] * self.direction[2])
-            * delta.as_secs_f32()
-            / (1.0 - self.direction[1] * self.direction[1]).sqrt();
-        self.position[1] += self.velocity[1] * delta.as_secs_f32();
-        self.position[2] += (self.velocity[0] * self.direction[2]
-            - self.velocity[2] * self.direction[0])
-            * delta.as_secs_f32()
-            / (1.0 - self.direction[1] * self.direction[1]).sqrt();
+    /// Returns the box around the camera that the collision boxes of blocks stop.
+    pub fn body(&self) -> Aabb {
+        Aabb {
+            centre: self.position,
+            extents: BODY_EXTENTS,
+        }
+    }
+
+    /// Moves the camera by its velocity, one axis at a time, stopping along any axis where it
+    /// would run into a block. A camera that is already inside of a block moves freely, so that
+    /// it can get out.
+    pub fn tick(&mut self, delta: Duration, chunks: &ChunkStore) {
+        let horizontal = delta.as_secs_f32() / (1.0 - self.direction[1] * self.direction[1]).sqrt();
+        let movement = [
+            (self.velocity[0] * self.direction[0] + self.velocity[2] * self.direction[2])
+                * horizontal,
+            self.velocity[1] * delta.as_secs_f32(),
+            (self.velocity[0] * self.direction[2] - self.velocity[2] * self.direction[0])
+                * horizontal,
+        ];
+
+        for (axis, &distance) in movement.iter().enumerate() {
+            let stuck = chunks.collides(&self.body());
+            self.position[axis] += distance;
+            if !stuck && chunks.collides(&self.body()) {
+                self.position[axis] -= distance;
+            }
+        }
     }
 
     pub fn move_self(&mut self, input: KeyboardInput) -> bool {
@@ -156,7 +189,11 @@ impl Camera {
     }
 
     pub fn perspective(&self, target: &Frame) -> [[f32; 4]; 4] {
-        let (width, height) = target.get_dimensions();
+        self.projection(target.get_dimensions())
+    }
+
+    /// Returns the projection matrix for a target of the given width and height.
+    pub fn projection(&self, (width, height): (u32, u32)) -> [[f32; 4]; 4] {
         let aspect_ratio = height as f32 / width as f32;
         let fov: f32 = self.fov;
         let z_far = self.z_far;
@@ -205,183 +242,116 @@ impl Camera {
         ]
     }
 
-    pub fn frustum(&self, target: &Frame) -> Frustum {
-        // https://learnopengl.com/Guest-Articles/2021/Scene/Frustum-Culling
-        let (width, height) = target.get_dimensions();
-        let half_v_side = self.z_far * (self.fov * 0.5).tan();
-        let half_h_side = half_v_side * height as f32 / width as f32;
-        let front = Vector3::from(self.direction).normalize();
-        let front_mult_far = self.z_far * front;
-        let right = front.cross(&Vector3::from([0.0, 1.0, 0.0]));
-        let up = right.cross(&front);
-
-        let mut frustum = Frustum {
-            top: Plane {
-                normal: right
-                    .cross(&(front_mult_far - up * half_v_side))
-                    .normalize()
-                    .try_into()
-                    .unwrap(),
-                distance: 0.0,
-            },
-            bottom: Plane {
-                normal: (front_mult_far + up * half_v_side)
-                    .cross(&right)
-                    .normalize()
-                    .try_into()
-                    .unwrap(),
-                distance: 0.0,
-            },
-            left: Plane {
-                normal: (front_mult_far - right * half_h_side)
-                    .cross(&up)
-                    .normalize()
-                    .try_into()
-                    .unwrap(),
-                distance: 0.0,
-            },
-            right: Plane {
-                normal: up
-                    .cross(&(front_mult_far + right * half_h_side))
-                    .normalize()
-                    .try_into()
-                    .unwrap(),
-                distance: 0.0,
-            },
-            far: Plane {
-                normal: (-front).try_into().unwrap(),
-                distance: 0.0,
-            },
-            near: Plane {
-                normal: front.try_into().unwrap(),
-                distance: 0.0,
-            },
-        };
+    /// Returns the volume visible on a target of the given width and height.
+    pub fn frustum(&self, dimensions: (u32, u32)) -> Frustum {
+        let projection = Matrix4::from(self.projection(dimensions));
+        let view = Matrix4::from(self.view_matrix());
+        Frustum::from_matrix((projection * view).into())
+    }
 
-        frustum.near.distance = (Vector3::from(self.position) + self.z_near * front).norm();
-        frustum.far.distance = Vector3::from(frustum.far.normal)
-            .dot(&(Vector3::from(self.position) + front_mult_far))
-            .abs();
-        frustum.left.distance = Vector3::from(frustum.left.normal)
-            .dot(&Vector3::from(self.position))
-            .abs();
-        frustum.right.distance = Vector3::from(frustum.right.normal)
-            .dot(&Vector3::from(self.position))
-            .abs();
-        frustum.top.distance = Vector3::from(frustum.top.normal)
-            .dot(&Vector3::from(self.position))
-            .abs();
-        frustum.bottom.distance = Vector3::from(frustum.bottom.normal)
-            .dot(&Vector3::from(self.position))
-            .abs();
-
-        frustum
+    /// Returns the block the camera is looking at, if it is close enough to reach.
+    pub fn target(&self, chunks: &ChunkStore) -> Option<[i32; 3]> {
+        self.hit(chunks).map(|(.., block_coords)| block_coords)
     }
 
-    pub fn raycast(
+    /// Casts a ray from the camera along its direction and returns the chunk, the coordinates in
+    /// that chunk and the block coordinates of the first block it hits.
+    #[allow(clippy::type_complexity)]
+    fn hit(
         &self,
-        display: &Display,
-        chunks: &HashMap<(i32, i32, i32), RwLock<ChunkWaiter>>,
-        action: RaycastAction,
-        tx: &mpsc::Sender<Vec<(i32, i32, i32)>>,
-    ) {
+        chunks: &ChunkStore,
+    ) -> Option<((i32, i32, i32), (usize, usize, usize), [i32; 3])> {
         let mut pos = self.position;
 
-        for _ in 0..16 {
+        for _ in 0..REACH_STEPS {
             pos = [
-                pos[0] + self.direction[0] * 0.25,
-                pos[1] + self.direction[1] * 0.25,
-                pos[2] + self.direction[2] * 0.25,
+                pos[0] + self.direction[0] * REACH_STEP,
+                pos[1] + self.direction[1] * REACH_STEP,
+                pos[2] + self.direction[2] * REACH_STEP,
             ];
             let (chunk_x, chunk_y, chunk_z, x, y, z) =
-                Chunk::world_to_chunk_coords(pos[0], pos[1], pos[2]);
-
-            let chunk = match chunks.get(&(chunk_x, chunk_y, chunk_z)) {
-                Some(chunk) => chunk,
-                _ => continue,
-            };
-
-            if let ChunkWaiter::Chunk(chunk) = &mut *chunk.write().unwrap() {
-                let block = chunk.block_mut(x, y, z);
+                coords::world_to_chunk(pos[0], pos[1], pos[2]);
+
+            if let Some(chunk) = chunks.get((chunk_x, chunk_y, chunk_z)) {
+                let block = chunk.block(x, y, z);
+                let block_coords = coords::chunk_to_block((chunk_x, chunk_y, chunk_z), (x, y, z));
+                // The boxes of a block may be thinner than a step, so the ray is tested against
+                // them rather than the point it stepped to.
+                let reach = REACH_STEP * REACH_STEPS as f32;
+                let hit = block.selection_boxes().iter().any(|b| {
+                    coords::model_box_aabb(block_coords, b)
+                        .ray_distance(self.position, self.direction)
+                        .is_some_and(|distance| distance <= reach)
+                });
+
+                if hit {
+                    return Some(((chunk_x, chunk_y, chunk_z), (x, y, z), block_coords));
+                }
+            }
+        }
 
-                if block.is_solid().unwrap_or(false) {
-                    match action {
-                        RaycastAction::Place(_block) => {
-                            // TODO
-                        }
+        None
+    }
 
-                        RaycastAction::Remove => {
-                            *block = Block::air();
-
-                            let mut to_send = vec![(chunk_x, chunk_y, chunk_z)];
-                            if x == 0 {
-                                if let Some(chunk) = chunks.get(&(chunk_x - 1, chunk_y, chunk_z)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x - 1, chunk_y, chunk_z));
-                                    }
-                                }
-                            } else if x == CHUNK_SIZE - 1 {
-                                if let Some(chunk) = chunks.get(&(chunk_x + 1, chunk_y, chunk_z)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x + 1, chunk_y, chunk_z));
-                                    }
-                                }
-                            }
-
-                            if y == 0 {
-                                if let Some(chunk) = chunks.get(&(chunk_x, chunk_y - 1, chunk_z)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x, chunk_y - 1, chunk_z));
-                                    }
-                                }
-                            } else if y == CHUNK_SIZE - 1 {
-                                if let Some(chunk) = chunks.get(&(chunk_x, chunk_y + 1, chunk_z)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x, chunk_y + 1, chunk_z));
-                                    }
-                                }
-                            }
-
-                            if z == 0 {
-                                if let Some(chunk) = chunks.get(&(chunk_x, chunk_y, chunk_z - 1)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x, chunk_y, chunk_z - 1));
-                                    }
-                                }
-                            } else if z == CHUNK_SIZE - 1 {
-                                if let Some(chunk) = chunks.get(&(chunk_x, chunk_y, chunk_z + 1)) {
-                                    if let ChunkWaiter::Chunk(_) = &mut *chunk.write().unwrap() {
-                                        to_send.push((chunk_x, chunk_y, chunk_z + 1));
-                                    }
-                                }
-                            }
-
-                            tx.blocking_send(to_send).unwrap();
-                        }
+    /// Applies the action to the block the camera is looking at, returning the chunks that have
+    /// to be meshed again.
+    pub fn raycast(
+        &self,
+        chunks: &ChunkStore,
+        light: &RwLock<LightMap>,
+        action: RaycastAction,
+    ) -> Vec<(i32, i32, i32)> {
+        let ((chunk_x, chunk_y, chunk_z), (x, y, z), block_coords) = match self.hit(chunks) {
+            Some(hit) => hit,
+            None => return vec![],
+        };
 
-                        RaycastAction::Unselect => {
-                            chunk.invalidate_selection();
-                            chunk.select(display, None);
-                        }
+        match action {
+            RaycastAction::Place(_block) => {
+                // TODO
+                vec![]
+            }
 
-                        RaycastAction::Select => {
-                            chunk.invalidate_selection();
-                            chunk.select(display, Some((x, y, z)));
-                        }
+            RaycastAction::Remove => {
+                chunks.update((chunk_x, chunk_y, chunk_z), |chunk| {
+                    *chunk.block_mut(x, y, z) = Block::air();
+                    chunk.update_emissive_lights();
+                });
+
+                // Blocks on the border of a chunk are part of the meshes of the chunks next to it.
+                let mut remesh = vec![(chunk_x, chunk_y, chunk_z)];
+                let local = [x, y, z];
+                for &direction in FaceDirection::ALL.iter() {
+                    let offset = direction.offset();
+                    let axis = offset.iter().position(|&d| d != 0).unwrap();
+                    let border = if offset[axis] < 0 { 0 } else { CHUNK_SIZE - 1 };
+                    let neighbour = (
+                        chunk_x + offset[0] as i32,
+                        chunk_y + offset[1] as i32,
+                        chunk_z + offset[2] as i32,
+                    );
+                    if local[axis] == border && chunks.is_loaded(neighbour) {
+                        remesh.push(neighbour);
                     }
+                }
 
-                    break;
+                let lit = light
+                    .write()
+                    .unwrap()
+                    .update_block(block_coords, |block| chunks.block(block));
+                for chunk in lit {
+                    if !remesh.contains(&chunk) {
+                        remesh.push(chunk);
+                    }
                 }
+                remesh
             }
         }
     }
 
-    pub fn check_loaded_chunks(
-        &mut self,
-        chunks: &mut HashMap<(i32, i32, i32), RwLock<ChunkWaiter>>,
-    ) {
+    pub fn check_loaded_chunks(&mut self, chunks: &ChunkStore) {
         let (chunk_x, chunk_y, chunk_z, ..) =
-            Chunk::world_to_chunk_coords(self.position[0], self.position[1], self.position[2]);
+            coords::world_to_chunk(self.position[0], self.position[1], self.position[2]);
         if chunk_x != self.old_chunk_pos[0]
             || chunk_y != self.old_chunk_pos[1]
             || chunk_z != self.old_chunk_pos[2]
@@ -389,15 +359,12 @@ impl Camera {
             for i in -3..=3 {
                 for j in -3..=3 {
                     for k in -3..=3 {
-                        if let Some(chunk) = chunks.get(&(
+                        let coords = (
                             self.old_chunk_pos[0] + i,
                             self.old_chunk_pos[1] + j,
                             self.old_chunk_pos[2] + k,
-                        )) {
-                            if let ChunkWaiter::Chunk(chunk) = &mut *chunk.write().unwrap() {
-                                chunk.loaded = false;
-                            }
-                        }
+                        );
+                        chunks.update(coords, |chunk| chunk.loaded = false);
                     }
                 }
             }
@@ -405,18 +372,9 @@ impl Camera {
             for i in -2..=2 {
                 for j in -2..=2 {
                     for k in -2..=2 {
-                        match chunks.entry((chunk_x + i, chunk_y + j, chunk_z + k)) {
-                            Entry::Occupied(occupied) => {
-                                if let ChunkWaiter::Chunk(chunk) =
-                                    &mut *occupied.get().write().unwrap()
-                                {
-                                    chunk.loaded = true;
-                                }
-                            }
-
-                            Entry::Vacant(vacant) => {
-                                vacant.insert(RwLock::new(ChunkWaiter::Timestamp(0)));
-                            }
+                        let coords = (chunk_x + i, chunk_y + j, chunk_z + k);
+                        if chunks.update(coords, |chunk| chunk.loaded = true).is_none() {
+                            chunks.request(coords);
                         }
                     }
                 }
@@ -430,6 +388,4 @@ impl Camera {
 pub enum RaycastAction {
     Place(Block),
     Remove,
-    Unselect,
-    Select,
 }
@@ -0,0 +1,196 @@
+//! Saving what was drawn: screenshots, and frames captured while the camera follows a scripted
+//! path.
+//!
+//! A camera path is a text file with one keyframe per line: the time in seconds, then the position
+//! of the camera and the point it looks at, all separated by whitespace. Blank lines and lines
+//! starting with `#` are skipped. Between keyframes the camera moves in a straight line.
+//!
+//! ```text
+//! # time  x     y     z     target x  target y  target z
+//! 0       -9    10    4     6         0         -1
+//! 4       9     10    4     -6        0         -1
+//! ```
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::RgbaImage;
+
+/// Directory screenshots and captured frames are saved to.
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// The position of the camera and the point it looks at.
+pub type Pose = ([f32; 3], [f32; 3]);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Keyframe {
+    /// Seconds since the start of the path.
+    pub time: f32,
+    pub position: [f32; 3],
+    /// The point the camera looks at.
+    pub target: [f32; 3],
+}
+
+/// Keyframes for the camera to move through, in order of time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn load(path: &Path) -> io::Result<CameraPath> {
+        CameraPath::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<CameraPath> {
+        let mut keyframes: Vec<Keyframe> = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |message: &str| invalid_data(format!("line {}: {}", number + 1, message));
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(&e.to_string()))?;
+            if values.len() != 7 {
+                return Err(invalid("expected a time, a position and a target"));
+            }
+
+            let keyframe = Keyframe {
+                time: values[0],
+                position: [values[1], values[2], values[3]],
+                target: [values[4], values[5], values[6]],
+            };
+            if keyframes
+                .last()
+                .is_some_and(|last| keyframe.time < last.time)
+            {
+                return Err(invalid("keyframes are out of order"));
+            }
+            keyframes.push(keyframe);
+        }
+
+        if keyframes.is_empty() {
+            return Err(invalid_data("camera path has no keyframes".to_string()));
+        }
+        Ok(CameraPath { keyframes })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Returns the time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().unwrap().time
+    }
+
+    /// Returns where the camera is at the given time. Before the first keyframe and after the
+    /// last, the camera stays where they put it.
+    pub fn pose(&self, time: f32) -> Pose {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            let first = self.keyframes[0];
+            return (first.position, first.target);
+        }
+        let previous = self.keyframes[next - 1];
+        let next = match self.keyframes.get(next) {
+            Some(&next) => next,
+            None => return (previous.position, previous.target),
+        };
+
+        let t = (time - previous.time) / (next.time - previous.time);
+        let lerp = |a: [f32; 3], b: [f32; 3]| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        (
+            lerp(previous.position, next.position),
+            lerp(previous.target, next.target),
+        )
+    }
+}
+
+/// Plays a camera path back one frame at a time, each frame a fixed interval after the last, so
+/// that the same path always gives the same frames however fast they are drawn.
+#[derive(Clone, Debug)]
+pub struct PathPlayback {
+    path: CameraPath,
+    /// Seconds of the path between frames.
+    interval: f32,
+    frame: u32,
+}
+
+impl PathPlayback {
+    pub fn new(path: CameraPath, interval: f32) -> PathPlayback {
+        PathPlayback {
+            path,
+            interval,
+            frame: 0,
+        }
+    }
+
+    /// Returns the number of the next frame and where the camera is in it, or `None` once the
+    /// path is over.
+    pub fn next_frame(&mut self) -> Option<(u32, Pose)> {
+        let time = self.frame as f32 * self.interval;
+        // Allow for rounding, so that a path is not cut a frame short.
+        if time > self.path.duration() + self.interval * 1e-3 {
+            return None;
+        }
+
+        let frame = self.frame;
+        self.frame += 1;
+        Some((frame, self.path.pose(time)))
+    }
+}
+
+/// Turns rows of pixels read from OpenGL, bottom row first, into an image.
+pub fn image_from_rows(rows: Vec<Vec<(u8, u8, u8, u8)>>) -> RgbaImage {
+    let height = rows.len() as u32;
+    let width = rows.first().map_or(0, |row| row.len()) as u32;
+    let pixels = rows
+        .into_iter()
+        .rev()
+        .flatten()
+        .flat_map(|(r, g, b, a)| [r, g, b, a])
+        .collect();
+    RgbaImage::from_raw(width, height, pixels).unwrap()
+}
+
+/// Saves a screenshot under [`SCREENSHOT_DIR`], named after the time it was taken, and returns
+/// where it was saved.
+pub fn save_screenshot(image: &RgbaImage) -> io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = Path::new(SCREENSHOT_DIR).join(format!("screenshot-{}.png", millis));
+    save_png(image, &path)?;
+    Ok(path)
+}
+
+/// Saves a frame captured from a camera path to `dir`, numbered so that frames sort in order.
+pub fn save_frame(image: &RgbaImage, dir: &Path, frame: u32) -> io::Result<PathBuf> {
+    let path = dir.join(format!("frame-{:05}.png", frame));
+    save_png(image, &path)?;
+    Ok(path)
+}
+
+fn save_png(image: &RgbaImage, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    image
+        .save(path)
+        .map_err(|e| io::Error::other(format!("failed to save {}: {}", path.display(), e)))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
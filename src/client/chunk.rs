@@ -1,20 +1,30 @@
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
 
+use glium::backend::Facade;
 use glium::index::PrimitiveType;
-use glium::texture::SrgbTexture3d;
-use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, UniformBuffer};
-use glium::{Display, DrawParameters, Frame, IndexBuffer, Program, Surface, VertexBuffer};
-
-use crate::blocks::FaceDirection;
+use glium::texture::SrgbTexture2dArray;
+use glium::{IndexBuffer, VertexBuffer};
+
+use crate::blocks::{FaceDirection, RenderLayer};
+use crate::collision::Aabb;
+use crate::coords;
+use crate::lighting::{Light, LightMap};
+use crate::lod::LodChunk;
+use crate::mesh::{self, Face, MeshMode};
+use crate::model::{FaceShape, MODEL_SIZE};
+use crate::visibility::Visibility;
 
 use super::super::blocks::{Block, CHUNK_SIZE};
 use super::super::server::chunk::Chunk as ServerChunk;
-use super::light::LightSource;
-use super::shapes::frustum::Aabb;
+use super::light::{self, LightSource};
+use super::mesher::ChunkSnapshot;
 use super::shapes::{Normal, Position, TexCoord};
+use super::store::ChunkStore;
 
-const LIGHT_COUNT: usize = 5;
+/// Most lights a chunk is drawn with. Must match the chunk table in the chunks shader.
+pub(super) const LIGHT_COUNT: usize = 5;
 
 const SQUARE_POSITIONS: [Position; 4] = [
     Position {
@@ -50,13 +60,13 @@ const SQUARE_INDICES: [u32; 6] = [0, 1, 2, 1, 3, 2];
 
 #[derive(Debug)]
 pub struct Mesh {
-    positions: VertexBuffer<Position>,
-    tex_coords: VertexBuffer<TexCoord>,
-    indices: IndexBuffer<u32>,
+    pub(super) positions: VertexBuffer<Position>,
+    pub(super) tex_coords: VertexBuffer<TexCoord>,
+    pub(super) indices: IndexBuffer<u32>,
 }
 
 impl Mesh {
-    pub fn square(display: &Display) -> Mesh {
+    pub fn square<F: Facade>(display: &F) -> Mesh {
         let positions = VertexBuffer::new(display, &SQUARE_POSITIONS).unwrap();
         let tex_coords = VertexBuffer::new(display, &SQUARE_TEX_COORDS).unwrap();
         let indices =
@@ -70,45 +80,72 @@ impl Mesh {
     }
 }
 
+/// Length of the ticks animated textures advance by.
+pub const ANIMATION_TICK: Duration = Duration::from_millis(50);
+
 pub struct BlockTextures {
-    pub textures: SrgbTexture3d,
-    pub texture_count: u32,
+    /// One layer for every block texture, see [`Block::generate_atlas`].
+    pub textures: SrgbTexture2dArray,
 }
 
 #[derive(Debug, Copy, Clone)]
 pub struct InstanceData {
-    /// 0..2   = FaceDirection
-    /// 3..3   = nothing
+    /// 0..3   = FaceDirection, or 6..9 for the sides of a cross model
     /// 4..7   = x
     /// 8..11  = y
     /// 12..15 = z
-    /// 32..63 = texture map
+    /// 16..19 = width - 1
+    /// 20..23 = height - 1
+    /// 24..31 = light, see [`crate::lighting::Light::packed`]
+    /// 32..63 = texture word, see [`crate::textures::pack_texture`]
     data: (u32, u32),
+    /// 0..4   = start of the face along its width axis, in sixteenths of a block
+    /// 5..9   = end of the face along its width axis
+    /// 10..14 = start of the face along its height axis
+    /// 15..19 = end of the face along its height axis
+    /// 20..24 = depth of the face into the block
+    shape: u32,
+    /// 0..7   = ambient occlusion of the four corners, two bits each, see [`Face::ao`]
+    ao: u32,
+    /// Row of the chunk table the face's chunk is in, see [`super::renderer`]
+    chunk: u32,
 }
 
-implement_vertex!(InstanceData, data);
+implement_vertex!(InstanceData, data, shape, ao, chunk);
 
 impl InstanceData {
-    fn new(dir: FaceDirection, block: Block, x: u32, y: u32, z: u32) -> InstanceData {
+    fn new(face: &Face) -> InstanceData {
         let mut data = InstanceData {
-            data: (0, block.get_texture(dir).unwrap_or(0)),
+            data: (0, face.block.get_texture(face.direction).unwrap_or(0)),
+            shape: 0,
+            ao: face
+                .ao
+                .iter()
+                .enumerate()
+                .map(|(corner, &level)| (level as u32) << (corner * 2))
+                .sum(),
+            chunk: 0,
         };
-        data.set_direction(dir);
-        data.set_x(x);
-        data.set_y(y);
-        data.set_z(z);
+        data.set_direction(face.direction);
+        data.set_x(face.x as u32);
+        data.set_y(face.y as u32);
+        data.set_z(face.z as u32);
+        data.set_width(face.width as u32);
+        data.set_height(face.height as u32);
+        data.set_shape(face.shape);
+        data.data.0 |= (face.light.sky() as u32) << 24;
         data
     }
 
-    #[allow(unused)]
-    fn direction(&self) -> FaceDirection {
+    fn direction(&self) -> Option<FaceDirection> {
         match self.data.0 & 0x000f {
-            0 => FaceDirection::Up,
-            1 => FaceDirection::Down,
-            2 => FaceDirection::Front,
-            3 => FaceDirection::Back,
-            4 => FaceDirection::Left,
-            5 => FaceDirection::Right,
+            0 => Some(FaceDirection::Up),
+            1 => Some(FaceDirection::Down),
+            2 => Some(FaceDirection::Front),
+            3 => Some(FaceDirection::Back),
+            4 => Some(FaceDirection::Left),
+            5 => Some(FaceDirection::Right),
+            6..=9 => None,
 
             _ => unreachable!(),
         }
@@ -141,421 +178,229 @@ impl InstanceData {
     fn set_z(&mut self, z: u32) {
         self.data.0 = (self.data.0 & !0xf000) | (z << 12);
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-struct Selection {
-    selected: u32,
+    fn width(&self) -> u32 {
+        ((self.data.0 & 0x000f_0000) >> 16) + 1
+    }
+
+    fn set_width(&mut self, width: u32) {
+        self.data.0 = (self.data.0 & !0x000f_0000) | ((width - 1) << 16);
+    }
+
+    fn height(&self) -> u32 {
+        ((self.data.0 & 0x00f0_0000) >> 20) + 1
+    }
+
+    fn set_height(&mut self, height: u32) {
+        self.data.0 = (self.data.0 & !0x00f0_0000) | ((height - 1) << 20);
+    }
+
+    pub(super) fn set_chunk(&mut self, slot: u32) {
+        self.chunk = slot;
+    }
+
+    fn set_shape(&mut self, shape: FaceShape) {
+        self.shape = match shape {
+            FaceShape::Rect { u, v, depth } => {
+                u[0] as u32
+                    | (u[1] as u32) << 5
+                    | (v[0] as u32) << 10
+                    | (v[1] as u32) << 15
+                    | (depth as u32) << 20
+            }
+            FaceShape::Diagonal(side) => {
+                self.data.0 = (self.data.0 & !0x000f) | (6 + side as u32);
+                (MODEL_SIZE as u32) << 5 | (MODEL_SIZE as u32) << 15 | (MODEL_SIZE as u32 / 2) << 20
+            }
+        };
+    }
+
+    /// Returns the centre of the face in world units, relative to the chunk origin.
+    pub(super) fn centre(&self) -> [f32; 3] {
+        let mut centre = [self.x() as f32, self.y() as f32, self.z() as f32];
+        if let Some(direction) = self.direction() {
+            let (normal, u, v) = mesh::face_axes(direction);
+            let sixteenths = |shift: u32| ((self.shape >> shift) & 0x1f) as f32 / MODEL_SIZE as f32;
+            let depth = sixteenths(20);
+            centre[normal] += direction.offset()[normal] as f32 * (0.5 - depth);
+            centre[u] +=
+                (sixteenths(0) + sixteenths(5)) * 0.5 - 0.5 + (self.width() - 1) as f32 * 0.5;
+            centre[v] +=
+                (sixteenths(10) + sixteenths(15)) * 0.5 - 0.5 + (self.height() - 1) as f32 * 0.5;
+        }
+        [
+            centre[0] * coords::BLOCK_SIZE,
+            centre[1] * coords::BLOCK_SIZE,
+            centre[2] * coords::BLOCK_SIZE,
+        ]
+    }
 }
 
-implement_vertex!(Selection, selected);
+/// The faces of a chunk, split by how they are drawn.
+#[derive(Debug, Default)]
+pub struct ChunkMesh {
+    /// Opaque and cutout faces, drawn in any order.
+    pub solid: Vec<InstanceData>,
+    /// Translucent faces, sorted back to front before drawing.
+    pub translucent: Vec<InstanceData>,
+    /// Which faces of the chunk can see each other.
+    pub visibility: Visibility,
+}
 
-#[derive(Copy, Clone, Debug)]
-#[repr(C)]
-struct Light {
-    colour: u32,
-    reserved: [u32; 3],
-    position: [f32; 3],
-    reserved2: u32,
+impl ChunkMesh {
+    pub(super) fn new(faces: Vec<Face>, visibility: Visibility) -> ChunkMesh {
+        let mut mesh = ChunkMesh {
+            visibility,
+            ..ChunkMesh::default()
+        };
+        for face in faces {
+            match face.block.render_layer() {
+                Some(RenderLayer::Translucent) => mesh.translucent.push(InstanceData::new(&face)),
+                _ => mesh.solid.push(InstanceData::new(&face)),
+            }
+        }
+        mesh
+    }
 }
 
-implement_uniform_block!(Light, colour, position);
+/// Meshes a downsampled chunk, see [`LodChunk::mesh`].
+pub fn lod_mesh(lod: &LodChunk) -> ChunkMesh {
+    ChunkMesh::new(lod.mesh(), Visibility::OPEN)
+}
 
-#[derive(Debug)]
+/// The blocks of a chunk. What is drawn for it is kept by the renderer, see
+/// [`super::renderer::ChunkRenderer`].
+#[derive(Clone, Debug)]
 pub struct Chunk {
     chunk_x: i32,
     chunk_y: i32,
     chunk_z: i32,
     blocks: Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>,
-    mesh_raw: Option<Vec<InstanceData>>,
-    mesh: Option<Box<VertexBuffer<InstanceData>>>,
-    selected: Option<Box<VertexBuffer<Selection>>>,
     aabb: Aabb,
-    light_buffer: Box<UniformBuffer<[Light; LIGHT_COUNT]>>,
+    /// Lights of the blocks in this chunk that give off light.
+    emissive: Vec<LightSource>,
+    /// Which faces of the chunk can see each other, open until the chunk is meshed.
+    visibility: Visibility,
     pub loaded: bool,
 }
 
-unsafe impl Send for Chunk {}
-unsafe impl Sync for Chunk {}
-
 impl Chunk {
-    pub fn from_server_chunk(display: &Display, chunk: ServerChunk) -> Chunk {
+    pub fn from_server_chunk(chunk: ServerChunk) -> Chunk {
+        let coords = (
+            chunk.get_chunk_x(),
+            chunk.get_chunk_y(),
+            chunk.get_chunk_z(),
+        );
+        let blocks = Box::new(*chunk.get_blocks());
         Chunk {
-            chunk_x: chunk.get_chunk_x(),
-            chunk_y: chunk.get_chunk_y(),
-            chunk_z: chunk.get_chunk_z(),
-            blocks: Box::new(*chunk.get_blocks()),
-            mesh_raw: None,
-            mesh: None,
-            selected: None,
-            aabb: Aabb {
-                centre: [
-                    chunk.get_chunk_x() as f32 * CHUNK_SIZE as f32 * 0.5 + CHUNK_SIZE as f32 * 0.25,
-                    chunk.get_chunk_y() as f32 * CHUNK_SIZE as f32 * 0.5 + CHUNK_SIZE as f32 * 0.25,
-                    chunk.get_chunk_z() as f32 * CHUNK_SIZE as f32 * 0.5 + CHUNK_SIZE as f32 * 0.25,
-                ],
-                extents: [CHUNK_SIZE as f32 * 0.25; 3],
-            },
-            light_buffer: Box::new(
-                UniformBuffer::new(
-                    display,
-                    [Light {
-                        colour: 0,
-                        reserved: [0; 3],
-                        position: [f32::INFINITY; 3],
-                        reserved2: 0,
-                    }; 5],
-                )
-                .unwrap(),
-            ),
+            chunk_x: coords.0,
+            chunk_y: coords.1,
+            chunk_z: coords.2,
+            emissive: light::emissive_blocks(coords, &blocks),
+            blocks,
+            aabb: coords::chunk_aabb(coords),
+            visibility: Visibility::OPEN,
             loaded: true,
         }
     }
 
-    pub fn world_to_chunk_coords(x: f32, y: f32, z: f32) -> (i32, i32, i32, usize, usize, usize) {
-        let mut block_coords = [
-            (x * 2.0).round() as i32,
-            (y * 2.0).round() as i32,
-            (z * 2.0).round() as i32,
-        ];
-
-        if block_coords[0] < 0 {
-            block_coords[0] -= CHUNK_SIZE as i32 - 1;
-        }
-        if block_coords[1] < 0 {
-            block_coords[1] -= CHUNK_SIZE as i32 - 1;
-        }
-        if block_coords[2] < 0 {
-            block_coords[2] -= CHUNK_SIZE as i32 - 1;
-        }
-
-        let (chunk_x, chunk_y, chunk_z) = (
-            block_coords[0] / CHUNK_SIZE as i32,
-            block_coords[1] / CHUNK_SIZE as i32,
-            block_coords[2] / CHUNK_SIZE as i32,
-        );
-
-        if block_coords[0] < 0 {
-            block_coords[0] += CHUNK_SIZE as i32 - 1;
-        }
-        if block_coords[1] < 0 {
-            block_coords[1] += CHUNK_SIZE as i32 - 1;
-        }
-        if block_coords[2] < 0 {
-            block_coords[2] += CHUNK_SIZE as i32 - 1;
-        }
+    pub fn coords(&self) -> (i32, i32, i32) {
+        (self.chunk_x, self.chunk_y, self.chunk_z)
+    }
 
-        let (mut x, mut y, mut z) = (
-            block_coords[0] % CHUNK_SIZE as i32,
-            block_coords[1] % CHUNK_SIZE as i32,
-            block_coords[2] % CHUNK_SIZE as i32,
-        );
-        if x < 0 {
-            x += CHUNK_SIZE as i32;
-        }
-        if y < 0 {
-            y += CHUNK_SIZE as i32;
-        }
-        if z < 0 {
-            z += CHUNK_SIZE as i32;
-        }
-        let (x, y, z) = (x as usize, y as usize, z as usize);
+    /// Returns the lights of the blocks in this chunk that give off light.
+    pub fn emissive_lights(&self) -> &[LightSource] {
+        &self.emissive
+    }
 
-        (chunk_x, chunk_y, chunk_z, x, y, z)
+    /// Finds the blocks that give off light again after blocks in the chunk were changed.
+    pub fn update_emissive_lights(&mut self) {
+        self.emissive =
+            light::emissive_blocks((self.chunk_x, self.chunk_y, self.chunk_z), &self.blocks);
     }
 
-    pub fn triangle_count(&self) -> usize {
-        if let Some(v) = &self.mesh_raw {
-            v.len() * 2
-        } else {
-            0
-        }
+    pub fn block(&self, x: usize, y: usize, z: usize) -> Block {
+        self.blocks[x][y][z]
     }
 
     pub fn block_mut(&mut self, x: usize, y: usize, z: usize) -> &mut Block {
         &mut self.blocks[x][y][z]
     }
 
-    fn get_block<'a>(
-        &'a self,
-        chunks: &'a HashMap<(i32, i32, i32), RwLock<ChunkWaiter>>,
+    fn get_block(
+        &self,
+        neighbours: &HashMap<(i32, i32, i32), Arc<Chunk>>,
         x: isize,
         y: isize,
         z: isize,
     ) -> Block {
-        if x < 0 {
-            chunks
-                .get(&(self.chunk_x - 1, self.chunk_y, self.chunk_z))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[CHUNK_SIZE - 1][y as usize][z as usize]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
-        } else if x > CHUNK_SIZE as isize - 1 {
-            chunks
-                .get(&(self.chunk_x + 1, self.chunk_y, self.chunk_z))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[0][y as usize][z as usize]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
-        } else if y < 0 {
-            chunks
-                .get(&(self.chunk_x, self.chunk_y - 1, self.chunk_z))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[x as usize][CHUNK_SIZE - 1][z as usize]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
-        } else if y > CHUNK_SIZE as isize - 1 {
-            chunks
-                .get(&(self.chunk_x, self.chunk_y + 1, self.chunk_z))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[x as usize][0][z as usize]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
-        } else if z < 0 {
-            chunks
-                .get(&(self.chunk_x, self.chunk_y, self.chunk_z - 1))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[x as usize][y as usize][CHUNK_SIZE - 1]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
-        } else if z > CHUNK_SIZE as isize - 1 {
-            chunks
-                .get(&(self.chunk_x, self.chunk_y, self.chunk_z + 1))
-                .and_then(|v| v.read().ok())
-                .and_then(|v| match &*v {
-                    ChunkWaiter::Chunk(v) => Some(v.blocks[x as usize][y as usize][0]),
-                    ChunkWaiter::Timestamp(_) => None,
-                })
-                .unwrap_or_else(Block::air)
+        let origin = coords::chunk_to_block((self.chunk_x, self.chunk_y, self.chunk_z), (0, 0, 0));
+        let block = [
+            origin[0] + x as i32,
+            origin[1] + y as i32,
+            origin[2] + z as i32,
+        ];
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        if chunk == (self.chunk_x, self.chunk_y, self.chunk_z) {
+            self.blocks[x][y][z]
         } else {
-            self.blocks[x as usize][y as usize][z as usize]
+            neighbours
+                .get(&chunk)
+                .map_or_else(Block::air, |chunk| chunk.block(x, y, z))
         }
     }
 
-    pub fn generate_mesh(
-        &self,
-        chunks: &HashMap<(i32, i32, i32), RwLock<ChunkWaiter>>,
-    ) -> Vec<InstanceData> {
-        let mut instance_data = vec![];
-
-        for x in 0..CHUNK_SIZE {
-            for y in 0..CHUNK_SIZE {
-                for z in 0..CHUNK_SIZE {
-                    let x = x as isize;
-                    let y = y as isize;
-                    let z = z as isize;
-                    let block = self.get_block(chunks, x, y, z);
-                    if block.is_solid().unwrap_or(false) {
-                        if self.get_block(chunks, x, y + 1, z) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Up,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-
-                        if self.get_block(chunks, x, y - 1, z) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Down,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-
-                        if self.get_block(chunks, x + 1, y, z) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Front,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-
-                        if self.get_block(chunks, x - 1, y, z) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Back,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-
-                        if self.get_block(chunks, x, y, z + 1) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Left,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-
-                        if self.get_block(chunks, x, y, z - 1) == Block::air() {
-                            instance_data.push(InstanceData::new(
-                                FaceDirection::Right,
-                                block,
-                                x as u32,
-                                y as u32,
-                                z as u32,
-                            ));
-                        }
-                    }
-                }
-            }
-        }
-
-        instance_data
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    pub fn render(
-        &self,
-        target: &mut Frame,
-        program: &Program,
-        perspective: [[f32; 4]; 4],
-        view: [[f32; 4]; 4],
-        params: &DrawParameters,
-        square: &Mesh,
-        textures: &BlockTextures,
-        lights: &[LightSource],
-    ) {
-        if let Some(data) = &self.mesh {
-            if data.len() == 0 {
-                return;
-            }
+    /// Copies the blocks of the chunk and the blocks and light around it, so that it can be
+    /// meshed without holding any lock, see [`ChunkSnapshot`].
+    pub fn snapshot(&self, chunks: &ChunkStore, light: &LightMap, mode: MeshMode) -> ChunkSnapshot {
+        let origin = coords::chunk_to_block((self.chunk_x, self.chunk_y, self.chunk_z), (0, 0, 0));
+        let light = |x: isize, y: isize, z: isize| {
+            light
+                .get([
+                    origin[0] + x as i32,
+                    origin[1] + y as i32,
+                    origin[2] + z as i32,
+                ])
+                .unwrap_or(Light::SKY)
+        };
 
-            if let Some(selected) = &self.selected {
-                let model = [
-                    [1.0, 0.0, 0.0, 0.0],
-                    [0.0, 1.0, 0.0, 0.0],
-                    [0.0, 0.0, 1.0, 0.0],
-                    [
-                        (self.chunk_x * CHUNK_SIZE as i32) as f32 * 0.5,
-                        (self.chunk_y * CHUNK_SIZE as i32) as f32 * 0.5,
-                        (self.chunk_z * CHUNK_SIZE as i32) as f32 * 0.5,
-                        1.0,
-                    ],
-                ];
-
-                let mut new = [Light {
-                    colour: 0,
-                    reserved: [0; 3],
-                    position: [0.0; 3],
-                        reserved2: 0,
-                }; LIGHT_COUNT];
-
-                for (new, light) in new.iter_mut().zip(lights) {
-                    *new = Light {
-                        colour: light.as_uint(),
-                        reserved: [0; 3],
-                        position: light.location(),
-                        reserved2: 0,
-                    };
-                }
-
-                self.light_buffer.write(&new);
-                let uniforms = uniform! {
-                    model: model,
-                    view: view,
-                    perspective: perspective,
-                    Lights: &*self.light_buffer,
-                    light_count: lights.len().min(LIGHT_COUNT) as u32,
-                    textures: Sampler::new(&textures.textures).minify_filter(MinifySamplerFilter::Nearest).magnify_filter(MagnifySamplerFilter::Nearest),
-                    texture_count: textures.texture_count,
-                };
-
-                target
-                    .draw(
-                        (
-                            &square.positions,
-                            &square.tex_coords,
-                            data.per_instance().unwrap(),
-                            selected.per_instance().unwrap(),
-                        ),
-                        &square.indices,
-                        program,
-                        &uniforms,
-                        params,
-                    )
-                    .unwrap();
-            }
-        }
+        let neighbours = chunks.neighbourhood(self.coords());
+        ChunkSnapshot::new(
+            self.coords(),
+            mode,
+            &self.blocks,
+            |x, y, z| self.get_block(&neighbours, x, y, z),
+            light,
+        )
     }
 
     pub fn aabb(&self) -> &Aabb {
         &self.aabb
     }
 
-    pub fn set_mesh(&mut self, display: &Display, mesh_raw: Vec<InstanceData>) {
-        self.mesh = Some(Box::new(VertexBuffer::new(display, &mesh_raw).unwrap()));
-        self.mesh_raw = Some(mesh_raw);
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
     }
 
-    pub fn select(&mut self, display: &Display, coords: Option<(usize, usize, usize)>) {
-        if self.selected.is_some() {
-            return;
-        }
-
-        let coords = if let Some(coords) = coords {
-            coords
-        } else {
-            (CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE)
-        };
-
-        if let Some(mesh) = &self.mesh_raw {
-            let mut select_data = vec![];
-
-            for data in mesh {
-                let x = data.x() as usize;
-                let y = data.y() as usize;
-                let z = data.z() as usize;
-                select_data.push(Selection {
-                    selected: (x == coords.0 && y == coords.1 && z == coords.2) as u32,
-                });
-            }
-
-            self.selected = Some(Box::new(VertexBuffer::new(display, &select_data).unwrap()));
-        }
-    }
-
-    pub fn invalidate_selection(&mut self) {
-        self.selected = None;
+    /// Sets the visibility of the chunk once it has been meshed, see [`ChunkMesh::visibility`].
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
     }
 }
 
-pub enum ChunkWaiter {
-    Timestamp(u128),
-    Chunk(Chunk),
-}
+/// Picks the lights a chunk is drawn with from the emissive blocks in it and the chunks around
+/// it, closest to its centre first.
+pub fn select_chunk_lights(chunks: &ChunkStore, coords: (i32, i32, i32)) -> Vec<LightSource> {
+    // In a fixed order, so that lights as far as each other are always picked the same way.
+    let mut neighbours: Vec<_> = chunks.neighbourhood(coords).into_iter().collect();
+    neighbours.sort_by_key(|&(coords, _)| coords);
 
-impl ChunkWaiter {
-    pub fn chunk(&self) -> Option<&Chunk> {
-        match self {
-            ChunkWaiter::Timestamp(_) => None,
-            ChunkWaiter::Chunk(chunk) => Some(chunk),
-        }
+    let mut lights = vec![];
+    for (_, chunk) in neighbours {
+        lights.extend_from_slice(chunk.emissive_lights());
     }
 
-    pub fn timestamp(&self) -> Option<u128> {
-        match self {
-            ChunkWaiter::Timestamp(ts) => Some(*ts),
-            ChunkWaiter::Chunk(_) => None,
-        }
-    }
+    let aabb = coords::chunk_aabb(coords);
+    let radius = aabb.extents.iter().map(|e| e * e).sum::<f32>().sqrt();
+    light::select_lights(&lights, aabb.centre, radius, LIGHT_COUNT)
 }
@@ -0,0 +1,165 @@
+//! Drawing without a window, for tests and tools running on machines without a display.
+//!
+//! The context comes from Mesa's surfaceless EGL platform, which needs neither a display server
+//! nor a GPU: without a GPU, Mesa draws on the CPU with llvmpipe. A surfaceless context has no
+//! framebuffer of its own, so everything is drawn into an [`Offscreen`] target instead.
+
+use std::ffi::{c_void, CString};
+use std::io::{self, ErrorKind};
+use std::ptr;
+use std::rc::Rc;
+
+use glium::backend::{Backend, Context, Facade};
+use glium::debug::DebugCallbackBehavior;
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, SrgbFormat, SrgbTexture2d};
+use glium::SwapBuffersError;
+use glutin_egl_sys::egl::{self, types::EGLenum, Egl};
+use glutin_egl_sys::{EGLContext, EGLDisplay};
+use image::RgbaImage;
+use libloading::Library;
+
+use super::capture;
+
+const EGL_LIBRARY: &str = "libEGL.so.1";
+/// `EGL_PLATFORM_SURFACELESS_MESA`, from `EGL_MESA_platform_surfaceless`.
+const PLATFORM_SURFACELESS: EGLenum = 0x31DD;
+
+/// Creates an OpenGL context that is not tied to a window, reporting the given dimensions as the
+/// size of its framebuffer.
+pub fn context(dimensions: (u32, u32)) -> io::Result<Rc<Context>> {
+    let backend = EglBackend::new(dimensions)?;
+    unsafe { Context::new(backend, false, DebugCallbackBehavior::Ignore) }
+        .map_err(|e| io::Error::other(format!("unsupported OpenGL version: {}", e)))
+}
+
+struct EglBackend {
+    egl: Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+    dimensions: (u32, u32),
+    // The functions in `egl` point into the library, so it is kept loaded until they are gone.
+    _library: Library,
+}
+
+impl EglBackend {
+    fn new(dimensions: (u32, u32)) -> io::Result<EglBackend> {
+        let library = unsafe { Library::new(EGL_LIBRARY) }.map_err(|e| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!("failed to load {}: {}", EGL_LIBRARY, e),
+            )
+        })?;
+        let egl = Egl::load_with(|symbol| {
+            let symbol = CString::new(symbol).unwrap();
+            unsafe { library.get::<*const c_void>(symbol.as_bytes_with_nul()) }
+                .map(|symbol| *symbol)
+                .unwrap_or(ptr::null())
+        });
+        let error = |call: &str| io::Error::other(format!("{} failed", call));
+
+        unsafe {
+            let attributes = [egl::NONE as egl::types::EGLAttrib];
+            let display = egl.GetPlatformDisplay(
+                PLATFORM_SURFACELESS,
+                egl::DEFAULT_DISPLAY as *mut _,
+                attributes.as_ptr(),
+            );
+            if display == egl::NO_DISPLAY {
+                return Err(error("eglGetPlatformDisplay"));
+            }
+            let (mut major, mut minor) = (0, 0);
+            if egl.Initialize(display, &mut major, &mut minor) == egl::FALSE {
+                return Err(error("eglInitialize"));
+            }
+            if egl.BindAPI(egl::OPENGL_API) == egl::FALSE {
+                return Err(error("eglBindAPI"));
+            }
+
+            // Nothing is drawn to a surface, so no config is needed (EGL_KHR_no_config_context).
+            let attributes = [egl::NONE as egl::types::EGLint];
+            let context =
+                egl.CreateContext(display, ptr::null(), egl::NO_CONTEXT, attributes.as_ptr());
+            if context == egl::NO_CONTEXT {
+                return Err(error("eglCreateContext"));
+            }
+
+            Ok(EglBackend {
+                egl,
+                display,
+                context,
+                dimensions,
+                _library: library,
+            })
+        }
+    }
+}
+
+unsafe impl Backend for EglBackend {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        self.egl.GetProcAddress(symbol.as_ptr()) as *const c_void
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    fn is_current(&self) -> bool {
+        unsafe { self.egl.GetCurrentContext() == self.context }
+    }
+
+    unsafe fn make_current(&self) {
+        self.egl
+            .MakeCurrent(self.display, egl::NO_SURFACE, egl::NO_SURFACE, self.context);
+    }
+}
+
+impl Drop for EglBackend {
+    fn drop(&mut self) {
+        // The display is shared by every context in the process, so it is left initialised.
+        unsafe {
+            self.egl.DestroyContext(self.display, self.context);
+        }
+    }
+}
+
+/// A colour and depth buffer to draw into instead of a window.
+pub struct Offscreen {
+    colour: SrgbTexture2d,
+    depth: DepthRenderBuffer,
+}
+
+impl Offscreen {
+    pub fn new<F: Facade>(facade: &F, (width, height): (u32, u32)) -> Offscreen {
+        Offscreen {
+            colour: SrgbTexture2d::empty_with_format(
+                facade,
+                SrgbFormat::U8U8U8U8,
+                MipmapsOption::NoMipmap,
+                width,
+                height,
+            )
+            .unwrap(),
+            depth: DepthRenderBuffer::new(facade, DepthFormat::I24, width, height).unwrap(),
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.colour.width(), self.colour.height())
+    }
+
+    /// Returns a surface that draws into the buffers.
+    pub fn framebuffer<F: Facade>(&self, facade: &F) -> SimpleFrameBuffer<'_> {
+        SimpleFrameBuffer::with_depth_buffer(facade, &self.colour, &self.depth).unwrap()
+    }
+
+    /// Reads back what has been drawn, top row first.
+    pub fn read(&self) -> RgbaImage {
+        capture::image_from_rows(self.colour.read())
+    }
+}
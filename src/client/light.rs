@@ -1,3 +1,11 @@
+use crate::blocks::{Block, CHUNK_SIZE};
+use crate::coords;
+
+/// Distance in world units at which a light at full brightness fades out completely. The chunks
+/// shader is given it as `light_range`.
+pub const LIGHT_RANGE: f32 = 7.5;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct LightSource {
     red: u8,
     green: u8,
@@ -17,6 +25,13 @@ impl LightSource {
         }
     }
 
+    /// Returns a light at the centre of the block if the block gives off light.
+    pub fn from_block(block: Block, coords: [i32; 3]) -> Option<LightSource> {
+        block.light_colour().map(|[red, green, blue]| {
+            LightSource::new(red, green, blue, coords::block_centre(coords))
+        })
+    }
+
     pub fn as_uint(&self) -> u32 {
         ((self.red as u32 & 0xf) << 12)
             | ((self.green as u32 & 0xf) << 8)
@@ -67,3 +82,52 @@ impl LightSource {
         self.updated = true;
     }
 }
+
+/// Returns a light for every block in the chunk that gives off light.
+pub fn emissive_blocks(
+    chunk: (i32, i32, i32),
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+) -> Vec<LightSource> {
+    let mut lights = vec![];
+    for (x, square) in blocks.iter().enumerate() {
+        for (y, line) in square.iter().enumerate() {
+            for (z, &block) in line.iter().enumerate() {
+                let coords = coords::chunk_to_block(chunk, (x, y, z));
+                lights.extend(LightSource::from_block(block, coords));
+            }
+        }
+    }
+    lights
+}
+
+/// Picks up to `count` of the lights closest to `centre` that reach within `radius` of it,
+/// closest first.
+pub fn select_lights<'a, I>(
+    lights: I,
+    centre: [f32; 3],
+    radius: f32,
+    count: usize,
+) -> Vec<LightSource>
+where
+    I: IntoIterator<Item = &'a LightSource>,
+{
+    let distance = |light: &LightSource| {
+        let location = light.location();
+        (0..3)
+            .map(|i| (location[i] - centre[i]).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    };
+
+    let mut lights: Vec<_> = lights
+        .into_iter()
+        .map(|light| (distance(light), light))
+        .filter(|&(distance, _)| distance < radius + LIGHT_RANGE)
+        .collect();
+    lights.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+    lights
+        .into_iter()
+        .take(count)
+        .map(|(_, light)| light.clone())
+        .collect()
+}
@@ -0,0 +1,161 @@
+//! Remeshing chunks in the background.
+//!
+//! Chunks that need a new mesh are marked dirty in a [`MeshScheduler`], which hands them out
+//! closest to the camera first. Marking a chunk again before it is meshed does nothing, and a
+//! chunk is never meshed by two workers at once, so its meshes cannot arrive out of order.
+//!
+//! Workers copy the blocks of a chunk and the blocks and light around it into a [`ChunkSnapshot`]
+//! before meshing it, so that the render thread does not spend its frames copying and the light
+//! map is only locked while copying, never while meshing.
+
+use std::collections::HashSet;
+
+use crate::blocks::{Block, CHUNK_SIZE};
+use crate::lighting::Light;
+use crate::mesh::MeshMode;
+use crate::visibility::Visibility;
+
+use super::chunk::ChunkMesh;
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+/// Blocks along each side of a snapshot: the chunk and one block around it.
+const PADDED_SIZE: usize = CHUNK_SIZE + 2;
+
+/// Keeps track of the chunks that need a new mesh.
+#[derive(Clone, Debug, Default)]
+pub struct MeshScheduler {
+    dirty: HashSet<(i32, i32, i32)>,
+    /// Chunks handed out that have not been meshed yet.
+    meshing: HashSet<(i32, i32, i32)>,
+}
+
+impl MeshScheduler {
+    pub fn new() -> MeshScheduler {
+        MeshScheduler::default()
+    }
+
+    /// Marks a chunk as needing a new mesh. A chunk being meshed is handed out again once its
+    /// mesh is done, as that mesh is already out of date.
+    pub fn mark_dirty(&mut self, chunk: (i32, i32, i32)) {
+        self.dirty.insert(chunk);
+    }
+
+    pub fn is_dirty(&self, chunk: (i32, i32, i32)) -> bool {
+        self.dirty.contains(&chunk)
+    }
+
+    /// Returns the number of chunks handed out that have not been meshed yet.
+    pub fn meshing(&self) -> usize {
+        self.meshing.len()
+    }
+
+    /// Returns whether no chunk is dirty or being meshed.
+    pub fn is_idle(&self) -> bool {
+        self.dirty.is_empty() && self.meshing.is_empty()
+    }
+
+    /// Hands out the dirty chunk closest to the camera's chunk that is not being meshed already.
+    pub fn next(&mut self, camera: (i32, i32, i32)) -> Option<(i32, i32, i32)> {
+        let distance = |&(x, y, z): &(i32, i32, i32)| {
+            let (dx, dy, dz) = (x - camera.0, y - camera.1, z - camera.2);
+            dx * dx + dy * dy + dz * dz
+        };
+        let chunk = *self
+            .dirty
+            .iter()
+            .filter(|chunk| !self.meshing.contains(chunk))
+            .min_by_key(|&chunk| (distance(chunk), *chunk))?;
+
+        self.dirty.remove(&chunk);
+        self.meshing.insert(chunk);
+        Some(chunk)
+    }
+
+    /// Marks a chunk handed out by [`MeshScheduler::next`] as meshed.
+    pub fn finish(&mut self, chunk: (i32, i32, i32)) {
+        self.meshing.remove(&chunk);
+    }
+}
+
+/// A copy of everything needed to mesh a chunk.
+#[derive(Clone, Debug)]
+pub struct ChunkSnapshot {
+    chunk: (i32, i32, i32),
+    mode: MeshMode,
+    blocks: Box<Blocks>,
+    /// Blocks and light of the chunk and the blocks around it, indexed by
+    /// `((x + 1) * PADDED_SIZE + y + 1) * PADDED_SIZE + z + 1` for coordinates local to the chunk.
+    padded_blocks: Vec<Block>,
+    padded_light: Vec<Light>,
+}
+
+impl ChunkSnapshot {
+    /// Copies the blocks of a chunk, with `block` and `light` returning the block and light at
+    /// coordinates local to the chunk, from `-1` to `CHUNK_SIZE` along each axis.
+    pub fn new<B, L>(
+        chunk: (i32, i32, i32),
+        mode: MeshMode,
+        blocks: &Blocks,
+        block: B,
+        light: L,
+    ) -> ChunkSnapshot
+    where
+        B: Fn(isize, isize, isize) -> Block,
+        L: Fn(isize, isize, isize) -> Light,
+    {
+        let cells = PADDED_SIZE * PADDED_SIZE * PADDED_SIZE;
+        let mut padded_blocks = Vec::with_capacity(cells);
+        let mut padded_light = Vec::with_capacity(cells);
+        let range = -1..=CHUNK_SIZE as isize;
+        for x in range.clone() {
+            for y in range.clone() {
+                for z in range.clone() {
+                    let inside = [x, y, z].iter().all(|&c| c >= 0 && c < CHUNK_SIZE as isize);
+                    padded_blocks.push(if inside {
+                        blocks[x as usize][y as usize][z as usize]
+                    } else {
+                        block(x, y, z)
+                    });
+                    padded_light.push(light(x, y, z));
+                }
+            }
+        }
+
+        ChunkSnapshot {
+            chunk,
+            mode,
+            blocks: Box::new(*blocks),
+            padded_blocks,
+            padded_light,
+        }
+    }
+
+    pub fn coords(&self) -> (i32, i32, i32) {
+        self.chunk
+    }
+
+    /// Returns the block at coordinates local to the chunk, from `-1` to `CHUNK_SIZE`.
+    pub fn block(&self, x: isize, y: isize, z: isize) -> Block {
+        self.padded_blocks[padded_index(x, y, z)]
+    }
+
+    /// Returns the light at coordinates local to the chunk, from `-1` to `CHUNK_SIZE`.
+    pub fn light(&self, x: isize, y: isize, z: isize) -> Light {
+        self.padded_light[padded_index(x, y, z)]
+    }
+
+    pub fn mesh(&self) -> ChunkMesh {
+        let faces = self.mode.mesh(
+            &self.blocks,
+            |x, y, z| self.block(x, y, z),
+            |x, y, z| self.light(x, y, z),
+        );
+        ChunkMesh::new(faces, Visibility::compute(&self.blocks))
+    }
+}
+
+fn padded_index(x: isize, y: isize, z: isize) -> usize {
+    let size = PADDED_SIZE as isize;
+    (((x + 1) * size + y + 1) * size + z + 1) as usize
+}
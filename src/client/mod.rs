@@ -1,5 +1,12 @@
 pub mod camera;
+pub mod capture;
 pub mod chunk;
+pub mod headless;
 pub mod light;
+pub mod mesher;
+pub mod overlay;
 pub mod player;
+pub mod renderer;
+pub mod settings;
 pub mod shapes;
+pub mod store;
@@ -0,0 +1,181 @@
+//! Shapes drawn over the world rather than as part of a chunk's mesh: the outline around the
+//! block the camera is looking at, and the shading that darkens it while it is being broken.
+
+use std::time::Duration;
+
+use glium::{
+    backend::Facade, index::PrimitiveType, Blend, Depth, DrawParameters, IndexBuffer, Program,
+    Surface, VertexBuffer,
+};
+
+use crate::{coords, model::ModelBox};
+
+use super::shapes::Position;
+
+/// How long the mouse button has to be held down to break a block.
+pub const BREAK_TIME: Duration = Duration::from_millis(600);
+/// Number of steps the breaking shading darkens in.
+pub const BREAK_STAGES: u32 = 10;
+
+/// How far the overlay sticks out of the block, in world units, so that it is not hidden by the
+/// block's own faces.
+const MARGIN: f32 = 0.002;
+
+const OUTLINE_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Corners of the unit cube the overlay is drawn from are numbered `4x + 2y + z`, with each of
+/// `x`, `y` and `z` being 0 on the negative side and 1 on the positive side.
+const EDGE_INDICES: [u16; 24] = [
+    0, 1, 2, 3, 4, 5, 6, 7, // along z
+    0, 2, 1, 3, 4, 6, 5, 7, // along y
+    0, 4, 1, 5, 2, 6, 3, 7, // along x
+];
+
+const FACE_INDICES: [u16; 36] = [
+    0, 1, 2, 1, 3, 2, // -x
+    4, 6, 5, 5, 6, 7, // +x
+    0, 4, 1, 1, 4, 5, // -y
+    2, 3, 6, 3, 7, 6, // +y
+    0, 2, 4, 2, 6, 4, // -z
+    1, 5, 3, 3, 5, 7, // +z
+];
+
+/// How far along breaking the targeted block is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BreakProgress {
+    block: Option<[i32; 3]>,
+    elapsed: Duration,
+}
+
+impl BreakProgress {
+    /// Advances breaking the targeted block by `delta` while the button is `held`. Breaking starts
+    /// over when the button is released or the camera looks at another block. Returns the block
+    /// once it breaks.
+    pub fn update(
+        &mut self,
+        target: Option<[i32; 3]>,
+        held: bool,
+        delta: Duration,
+    ) -> Option<[i32; 3]> {
+        if !held || target != self.block {
+            self.block = target.filter(|_| held);
+            self.elapsed = Duration::ZERO;
+        }
+
+        let block = self.block?;
+        self.elapsed += delta;
+        if self.elapsed >= BREAK_TIME {
+            *self = BreakProgress::default();
+            Some(block)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the block being broken and how far along it is, from 0 to `BREAK_STAGES - 1`.
+    pub fn stage(&self) -> Option<([i32; 3], u32)> {
+        let progress = self.elapsed.as_secs_f32() / BREAK_TIME.as_secs_f32();
+        self.block.map(|block| {
+            (
+                block,
+                ((progress * BREAK_STAGES as f32) as u32).min(BREAK_STAGES - 1),
+            )
+        })
+    }
+}
+
+/// Returns the model matrix that stretches the overlay's unit cube over each of the boxes of a
+/// block, with the margin added on every side.
+pub fn box_models(block: [i32; 3], boxes: &[ModelBox]) -> Vec<[[f32; 4]; 4]> {
+    boxes
+        .iter()
+        .map(|model_box| {
+            let aabb = coords::model_box_aabb(block, model_box);
+            let [x, y, z] = aabb.centre;
+            let size = |i: usize| (aabb.extents[i] + MARGIN) * 2.0;
+            [
+                [size(0), 0.0, 0.0, 0.0],
+                [0.0, size(1), 0.0, 0.0],
+                [0.0, 0.0, size(2), 0.0],
+                [x, y, z, 1.0],
+            ]
+        })
+        .collect()
+}
+
+/// Draws the outline and breaking shading of a block.
+pub struct BlockOverlay {
+    positions: VertexBuffer<Position>,
+    edges: IndexBuffer<u16>,
+    faces: IndexBuffer<u16>,
+}
+
+impl BlockOverlay {
+    pub fn new<F: Facade>(display: &F) -> BlockOverlay {
+        let mut positions = vec![];
+        for &x in [-0.5, 0.5].iter() {
+            for &y in [-0.5, 0.5].iter() {
+                for &z in [-0.5, 0.5].iter() {
+                    positions.push(Position {
+                        position: [x, y, z],
+                    });
+                }
+            }
+        }
+
+        BlockOverlay {
+            positions: VertexBuffer::new(display, &positions).unwrap(),
+            edges: IndexBuffer::new(display, PrimitiveType::LinesList, &EDGE_INDICES).unwrap(),
+            faces: IndexBuffer::new(display, PrimitiveType::TrianglesList, &FACE_INDICES).unwrap(),
+        }
+    }
+
+    /// Outlines each of the given boxes of a block, shading them darker the further along breaking
+    /// the block is. This should be called once everything else in the world has been drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<S: Surface>(
+        &self,
+        target: &mut S,
+        program: &Program,
+        perspective: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        block: [i32; 3],
+        boxes: &[ModelBox],
+        stage: Option<u32>,
+    ) {
+        let params = DrawParameters {
+            depth: Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: false,
+                ..Default::default()
+            },
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        for model in box_models(block, boxes) {
+            if let Some(stage) = stage {
+                let darkness = 0.15 + 0.5 * (stage + 1) as f32 / BREAK_STAGES as f32;
+                let uniforms = uniform! {
+                    model: model,
+                    view: view,
+                    perspective: perspective,
+                    colour: [0.0, 0.0, 0.0, darkness],
+                };
+                target
+                    .draw(&self.positions, &self.faces, program, &uniforms, &params)
+                    .unwrap();
+            }
+
+            let uniforms = uniform! {
+                model: model,
+                view: view,
+                perspective: perspective,
+                colour: OUTLINE_COLOUR,
+            };
+            target
+                .draw(&self.positions, &self.edges, program, &uniforms, &params)
+                .unwrap();
+        }
+    }
+}
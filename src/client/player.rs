@@ -1,11 +1,12 @@
 use glium::{
-    index::PrimitiveType, Display, DrawParameters, Frame, IndexBuffer, Program, Surface,
+    backend::Facade, index::PrimitiveType, DrawParameters, IndexBuffer, Program, Surface,
     VertexBuffer,
 };
 use tobj::LoadOptions;
 
 use crate::collision::Aabb;
 
+use super::renderer::Fog;
 use super::shapes::{Normal, Position, TexCoord};
 
 pub struct Player {
@@ -18,7 +19,7 @@ pub struct Player {
 }
 
 impl Player {
-    pub fn new(name: String, position: [f32; 3], display: &Display) -> Player {
+    pub fn new<F: Facade>(name: String, position: [f32; 3], display: &F) -> Player {
         let model = tobj::load_obj(
             "assets/models/player.obj",
             &LoadOptions {
@@ -85,13 +86,14 @@ impl Player {
         }
     }
 
-    pub fn render(
+    pub fn render<S: Surface>(
         &self,
-        target: &mut Frame,
+        target: &mut S,
         program: &Program,
         perspective: [[f32; 4]; 4],
         view: [[f32; 4]; 4],
         params: &DrawParameters,
+        fog: &Fog,
     ) {
         let model = [
             [1.0, 0.0, 0.0, 0.0],
@@ -106,6 +108,9 @@ impl Player {
             perspective: perspective,
             light: [-1.0, 0.4, 0.9f32],
             colour: [0.0, 0.0, 1.0f32],
+            fog_start: fog.start,
+            fog_end: fog.end,
+            fog_colour: fog.colour,
         };
 
         target
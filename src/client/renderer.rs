@@ -0,0 +1,497 @@
+//! Draws chunks from a few large buffers shared by all of them.
+//!
+//! The faces of every chunk live in a range of one of two instance buffers, one for solid faces
+//! and one for translucent faces, so that chunks lying next to each other in a buffer are drawn
+//! with a single call. Every face carries the slot of its chunk, a row of the chunk table: a
+//! texture with the origin and lights of each chunk that the chunks shader reads them from.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use glium::backend::Facade;
+use glium::texture::{MipmapsOption, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::{DrawParameters, Program, Rect, Surface, Texture2d, VertexBuffer};
+
+use crate::clock::WorldClock;
+use crate::coords;
+
+use super::chunk::{BlockTextures, ChunkMesh, InstanceData, Mesh, LIGHT_COUNT};
+use super::light::{LightSource, LIGHT_RANGE};
+
+/// Faces the instance buffers have room for before they first grow.
+const INITIAL_CAPACITY: usize = 1 << 16;
+/// Chunks the chunk table has room for before it first grows.
+const INITIAL_SLOTS: usize = 256;
+/// Texels in a row of the chunk table: the origin of the chunk and its light count, then the
+/// position and colour of each light.
+const TABLE_WIDTH: usize = 1 + 2 * LIGHT_COUNT;
+
+type Texel = (f32, f32, f32, f32);
+
+/// Hands out ranges of a buffer of a given capacity, first fit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeAllocator {
+    capacity: usize,
+    /// Free ranges sorted by their start, never empty or touching each other.
+    free: Vec<Range<usize>>,
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: usize) -> RangeAllocator {
+        let mut allocator = RangeAllocator {
+            capacity: 0,
+            free: vec![],
+        };
+        allocator.grow(capacity);
+        allocator
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the first free range long enough for `len` elements, or `None` if there is none
+    /// and the buffer has to grow.
+    pub fn allocate(&mut self, len: usize) -> Option<Range<usize>> {
+        if len == 0 {
+            return Some(0..0);
+        }
+
+        let index = self.free.iter().position(|range| range.len() >= len)?;
+        let start = self.free[index].start;
+        self.free[index].start += len;
+        if self.free[index].is_empty() {
+            self.free.remove(index);
+        }
+        Some(start..start + len)
+    }
+
+    /// Gives back a range returned by [`RangeAllocator::allocate`], merging it with the free
+    /// ranges around it.
+    pub fn free(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let index = self.free.partition_point(|free| free.start < range.start);
+        self.free.insert(index, range);
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+    }
+
+    /// Adds the elements between the old and the new capacity to the free ranges.
+    pub fn grow(&mut self, capacity: usize) {
+        assert!(capacity >= self.capacity, "buffers never shrink");
+        let old = self.capacity;
+        self.capacity = capacity;
+        self.free(old..capacity);
+    }
+}
+
+/// Joins ranges that follow each other, keeping their order and leaving out empty ones, so that
+/// they can be drawn with fewer calls.
+pub fn merge_ranges<I>(ranges: I) -> Vec<Range<usize>>
+where
+    I: IntoIterator<Item = Range<usize>>,
+{
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        if range.is_empty() {
+            continue;
+        }
+        match merged.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// An instance buffer shared by many chunks, doubling in size when it runs out of room.
+struct InstanceBuffer {
+    buffer: VertexBuffer<InstanceData>,
+    allocator: RangeAllocator,
+}
+
+impl InstanceBuffer {
+    fn new<F: Facade>(display: &F) -> InstanceBuffer {
+        InstanceBuffer {
+            buffer: VertexBuffer::empty_dynamic(display, INITIAL_CAPACITY).unwrap(),
+            allocator: RangeAllocator::new(INITIAL_CAPACITY),
+        }
+    }
+
+    fn insert<F: Facade>(&mut self, display: &F, data: &[InstanceData]) -> Range<usize> {
+        let range = loop {
+            match self.allocator.allocate(data.len()) {
+                Some(range) => break range,
+                None => self.grow(display, data.len()),
+            }
+        };
+        self.write(&range, data);
+        range
+    }
+
+    fn write(&self, range: &Range<usize>, data: &[InstanceData]) {
+        if !range.is_empty() {
+            self.buffer.slice(range.clone()).unwrap().write(data);
+        }
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        self.allocator.free(range);
+    }
+
+    fn grow<F: Facade>(&mut self, display: &F, needed: usize) {
+        let old = self.allocator.capacity();
+        let capacity = (old * 2).max(old + needed);
+        let buffer = VertexBuffer::empty_dynamic(display, capacity).unwrap();
+        self.buffer.copy_to(buffer.slice(0..old).unwrap()).unwrap();
+        self.buffer = buffer;
+        self.allocator.grow(capacity);
+    }
+}
+
+/// Fog that hides distant chunks, fading them into its colour between two distances from the
+/// camera, in world units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fog {
+    pub start: f32,
+    pub end: f32,
+    /// Linear colour, like the colours the shaders output.
+    pub colour: [f32; 3],
+}
+
+impl Fog {
+    /// Fog that ends where it starts, which the shaders leave out.
+    pub fn none() -> Fog {
+        Fog {
+            start: f32::MAX,
+            end: f32::MAX,
+            colour: [0.0; 3],
+        }
+    }
+}
+
+/// Where the faces of a chunk are.
+struct Entry {
+    slot: usize,
+    solid: Range<usize>,
+    translucent: Range<usize>,
+    /// The translucent faces in the order they are in the buffer.
+    translucent_raw: Vec<InstanceData>,
+    /// Block the translucent faces were last sorted from.
+    sorted_from: Option<[i32; 3]>,
+}
+
+/// Keeps the meshes of every chunk on the GPU and draws them.
+pub struct ChunkRenderer {
+    solid: InstanceBuffer,
+    translucent: InstanceBuffer,
+    chunks: HashMap<(i32, i32, i32), Entry>,
+    free_slots: Vec<usize>,
+    /// The chunk table, with a copy of its rows to fill it again when it grows.
+    table: Texture2d,
+    rows: Vec<Vec<Texel>>,
+}
+
+impl ChunkRenderer {
+    pub fn new<F: Facade>(display: &F) -> ChunkRenderer {
+        let rows = vec![vec![(0.0, 0.0, 0.0, 0.0); TABLE_WIDTH]; INITIAL_SLOTS];
+        ChunkRenderer {
+            solid: InstanceBuffer::new(display),
+            translucent: InstanceBuffer::new(display),
+            chunks: HashMap::new(),
+            free_slots: (0..INITIAL_SLOTS).rev().collect(),
+            table: chunk_table(display, &rows),
+            rows,
+        }
+    }
+
+    pub fn contains(&self, coords: (i32, i32, i32)) -> bool {
+        self.chunks.contains_key(&coords)
+    }
+
+    pub fn has_translucent_faces(&self, coords: (i32, i32, i32)) -> bool {
+        self.chunks
+            .get(&coords)
+            .is_some_and(|entry| !entry.translucent.is_empty())
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.chunks
+            .values()
+            .map(|entry| (entry.solid.len() + entry.translucent.len()) * 2)
+            .sum()
+    }
+
+    /// Uploads the mesh of a chunk, replacing its old one.
+    pub fn set_mesh<F: Facade>(&mut self, display: &F, coords: (i32, i32, i32), mesh: ChunkMesh) {
+        let slot = match self.chunks.remove(&coords) {
+            Some(entry) => {
+                self.solid.remove(entry.solid);
+                self.translucent.remove(entry.translucent);
+                entry.slot
+            }
+            None => self.new_slot(display, coords),
+        };
+
+        let with_slot = |faces: Vec<InstanceData>| -> Vec<InstanceData> {
+            faces
+                .into_iter()
+                .map(|mut face| {
+                    face.set_chunk(slot as u32);
+                    face
+                })
+                .collect()
+        };
+        let solid = with_slot(mesh.solid);
+        let translucent_raw = with_slot(mesh.translucent);
+
+        let entry = Entry {
+            slot,
+            solid: self.solid.insert(display, &solid),
+            translucent: self.translucent.insert(display, &translucent_raw),
+            translucent_raw,
+            sorted_from: None,
+        };
+        self.chunks.insert(coords, entry);
+    }
+
+    /// Forgets the mesh of a chunk, leaving its room to other chunks.
+    pub fn remove(&mut self, coords: (i32, i32, i32)) {
+        if let Some(entry) = self.chunks.remove(&coords) {
+            self.solid.remove(entry.solid);
+            self.translucent.remove(entry.translucent);
+            self.free_slots.push(entry.slot);
+        }
+    }
+
+    /// Sets the lights a chunk is drawn with, see [`super::chunk::select_chunk_lights`].
+    pub fn set_lights(&mut self, coords: (i32, i32, i32), lights: &[LightSource]) {
+        let slot = match self.chunks.get(&coords) {
+            Some(entry) => entry.slot,
+            None => return,
+        };
+
+        let row = &mut self.rows[slot];
+        let lights = &lights[..lights.len().min(LIGHT_COUNT)];
+        row[0].3 = lights.len() as f32;
+        for (texels, light) in row[1..].chunks_mut(2).zip(lights) {
+            let [x, y, z] = light.location();
+            let channel = |value: u8| (value & 0xf) as f32 / 15.0;
+            texels[0] = (x, y, z, 0.0);
+            texels[1] = (
+                channel(light.red()),
+                channel(light.green()),
+                channel(light.blue()),
+                0.0,
+            );
+        }
+        self.write_row(slot);
+    }
+
+    /// Sorts the translucent faces of a chunk from the furthest to the closest to the given world
+    /// position, so that they blend correctly. The faces are only sorted again once the position
+    /// has moved to another block.
+    pub fn sort_translucent(&mut self, coords: (i32, i32, i32), eye: [f32; 3]) {
+        let entry = match self.chunks.get_mut(&coords) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let block = coords::world_to_block(eye);
+        if entry.translucent_raw.is_empty() || entry.sorted_from == Some(block) {
+            return;
+        }
+
+        let origin = coords::chunk_origin(coords);
+        let eye = [eye[0] - origin[0], eye[1] - origin[1], eye[2] - origin[2]];
+        let distance = |data: &InstanceData| {
+            let centre = data.centre();
+            (centre[0] - eye[0]).powi(2)
+                + (centre[1] - eye[1]).powi(2)
+                + (centre[2] - eye[2]).powi(2)
+        };
+        entry
+            .translucent_raw
+            .sort_by(|a, b| distance(b).partial_cmp(&distance(a)).unwrap());
+
+        self.translucent
+            .write(&entry.translucent, &entry.translucent_raw);
+        entry.sorted_from = Some(block);
+    }
+
+    /// Draws the opaque and cutout faces of the given chunks.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<S: Surface>(
+        &self,
+        target: &mut S,
+        program: &Program,
+        perspective: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        params: &DrawParameters,
+        square: &Mesh,
+        textures: &BlockTextures,
+        animation_tick: u32,
+        clock: &WorldClock,
+        fog: &Fog,
+        chunks: &[(i32, i32, i32)],
+    ) {
+        let mut ranges: Vec<_> = chunks
+            .iter()
+            .filter_map(|coords| self.chunks.get(coords))
+            .map(|entry| entry.solid.clone())
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        self.draw(
+            target,
+            program,
+            perspective,
+            view,
+            params,
+            square,
+            textures,
+            animation_tick,
+            clock,
+            fog,
+            (&self.solid, merge_ranges(ranges)),
+            false,
+        );
+    }
+
+    /// Draws the translucent faces of the given chunks, which should be ordered from the furthest
+    /// to the closest. This should be called after [`ChunkRenderer::render`], with blending
+    /// enabled and depth writes disabled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_translucent<S: Surface>(
+        &self,
+        target: &mut S,
+        program: &Program,
+        perspective: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        params: &DrawParameters,
+        square: &Mesh,
+        textures: &BlockTextures,
+        animation_tick: u32,
+        clock: &WorldClock,
+        fog: &Fog,
+        chunks: &[(i32, i32, i32)],
+    ) {
+        let ranges = chunks
+            .iter()
+            .filter_map(|coords| self.chunks.get(coords))
+            .map(|entry| entry.translucent.clone());
+
+        self.draw(
+            target,
+            program,
+            perspective,
+            view,
+            params,
+            square,
+            textures,
+            animation_tick,
+            clock,
+            fog,
+            (&self.translucent, merge_ranges(ranges)),
+            true,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw<S: Surface>(
+        &self,
+        target: &mut S,
+        program: &Program,
+        perspective: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        params: &DrawParameters,
+        square: &Mesh,
+        textures: &BlockTextures,
+        animation_tick: u32,
+        clock: &WorldClock,
+        fog: &Fog,
+        (buffer, runs): (&InstanceBuffer, Vec<Range<usize>>),
+        translucent: bool,
+    ) {
+        let uniforms = uniform! {
+            view: view,
+            perspective: perspective,
+            chunk_table: Sampler::new(&self.table)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            textures: Sampler::new(&textures.textures)
+                .wrap_function(SamplerWrapFunction::Repeat)
+                .minify_filter(MinifySamplerFilter::NearestMipmapLinear)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            translucent: translucent,
+            animation_tick: animation_tick,
+            daylight: clock.daylight(),
+            sky_direction: clock.light_direction(),
+            light_range: LIGHT_RANGE,
+            fog_start: fog.start,
+            fog_end: fog.end,
+            fog_colour: fog.colour,
+        };
+
+        for run in runs {
+            let instances = buffer.buffer.slice(run).unwrap();
+            target
+                .draw(
+                    (
+                        &square.positions,
+                        &square.tex_coords,
+                        instances.per_instance().unwrap(),
+                    ),
+                    &square.indices,
+                    program,
+                    &uniforms,
+                    params,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Returns a free row of the chunk table for a chunk, growing the table if it is full.
+    fn new_slot<F: Facade>(&mut self, display: &F, coords: (i32, i32, i32)) -> usize {
+        if self.free_slots.is_empty() {
+            let old = self.rows.len();
+            self.rows
+                .resize(old * 2, vec![(0.0, 0.0, 0.0, 0.0); TABLE_WIDTH]);
+            self.free_slots.extend((old..old * 2).rev());
+            self.table = chunk_table(display, &self.rows);
+        }
+
+        let slot = self.free_slots.pop().unwrap();
+        let [x, y, z] = coords::chunk_origin(coords);
+        self.rows[slot][0] = (x, y, z, 0.0);
+        self.write_row(slot);
+        slot
+    }
+
+    fn write_row(&self, slot: usize) {
+        let rect = Rect {
+            left: 0,
+            bottom: slot as u32,
+            width: TABLE_WIDTH as u32,
+            height: 1,
+        };
+        self.table.write(rect, vec![self.rows[slot].clone()]);
+    }
+}
+
+fn chunk_table<F: Facade>(display: &F, rows: &[Vec<Texel>]) -> Texture2d {
+    Texture2d::with_format(
+        display,
+        rows.to_vec(),
+        UncompressedFloatFormat::F32F32F32F32,
+        MipmapsOption::NoMipmap,
+    )
+    .unwrap()
+}
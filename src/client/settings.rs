@@ -0,0 +1,95 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::blocks::CHUNK_SIZE;
+use crate::coords::BLOCK_SIZE;
+
+use super::renderer::Fog;
+
+/// Client settings, read from `client.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientSettings {
+    /// Chunks are drawn up to this many chunks from the camera, downsampled past the loaded ones.
+    pub view_distance: i32,
+    pub fog: FogSettings,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        ClientSettings {
+            view_distance: 12,
+            fog: FogSettings::default(),
+        }
+    }
+}
+
+impl ClientSettings {
+    /// Loads the settings from the given toml file, falling back to the defaults if it does not
+    /// exist. Settings out of their range are an error.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ClientSettings> {
+        let settings: ClientSettings = match fs::read_to_string(path) {
+            Ok(source) => toml::from_str(&source)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ClientSettings::default()),
+            Err(e) => return Err(e),
+        };
+
+        // Fog starting at the view distance would never be seen.
+        if !(0.0..1.0).contains(&settings.fog.start) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "fog start must be from 0 up to but not including 1, not {}",
+                    settings.fog.start
+                ),
+            ));
+        }
+        Ok(settings)
+    }
+
+    /// Returns the fog for the view distance, thick enough at the edge of the drawn chunks to hide
+    /// them in the given sky colour. The sky colour is the one the frame is cleared with, which is
+    /// written to the framebuffer as it is.
+    pub fn fog(&self, sky_colour: [f32; 3]) -> Fog {
+        if !self.fog.enabled {
+            return Fog::none();
+        }
+
+        let end = self.view_distance.max(1) as f32 * CHUNK_SIZE as f32 * BLOCK_SIZE;
+        Fog {
+            start: end * self.fog.start.clamp(0.0, 1.0),
+            end,
+            // Shaders output linear colours, which are converted to sRGB on the way into the
+            // framebuffer, so the fog colour is converted back first to end up as the sky.
+            colour: sky_colour.map(srgb_to_linear),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FogSettings {
+    pub enabled: bool,
+    /// Where the fog starts, as a share of the view distance from `0.0` up to but not including
+    /// `1.0`. It thickens from there until nothing but fog is left at the view distance.
+    pub start: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        FogSettings {
+            enabled: true,
+            start: 0.6,
+        }
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
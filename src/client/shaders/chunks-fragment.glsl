@@ -3,11 +3,38 @@
 in vec3 normal_out;
 in vec3 tex_coords_out;
 in vec4 light_out;
+in vec3 view_position;
 
-uniform sampler3D textures;
+uniform sampler2DArray textures;
+uniform bool translucent;
+// Distant faces fade into the fog colour between these distances from the camera, see
+// client/renderer.rs.
+uniform float fog_start;
+uniform float fog_end;
+uniform vec3 fog_colour;
 
 out vec4 color;
 
 void main() {
-    color = texture(textures, tex_coords_out) * light_out;
+    // Merged faces repeat the texture once per block. The gradients are taken before wrapping
+    // the coordinates so that the mipmap level does not jump at the edges of each block.
+    vec2 tex_coords = tex_coords_out.xy;
+    color = textureGrad(
+        textures,
+        vec3(fract(tex_coords), tex_coords_out.z),
+        dFdx(tex_coords),
+        dFdy(tex_coords)
+    ) * light_out;
+
+    if (translucent) {
+        color.a *= 0.75;
+    } else if (color.a < 0.5) {
+        // Cutout blocks are either fully opaque or fully transparent.
+        discard;
+    }
+
+    if (fog_end > fog_start) {
+        float fog = clamp((length(view_position) - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+        color.rgb = mix(color.rgb, fog_colour, fog);
+    }
 }
@@ -4,32 +4,45 @@
 in vec3 position;
 in vec2 tex_coords;
 in uvec2 data;
-in uint selected;
+in uint shape;
+in uint ao;
+in uint chunk;
 
-struct Light {
-    uint colour;
-    vec3 position;
-};
-
-uniform mat4 model;
 uniform mat4 view;
 uniform mat4 perspective;
-uniform Lights {
-    Light lights[LIGHT_COUNT];
-};
-uniform uint light_count;
-uniform uint texture_count;
+// One row per chunk, see client/renderer.rs: the chunk's origin and light count, then the
+// position and colour of each of its lights.
+uniform sampler2D chunk_table;
+uniform uint animation_tick;
+// How bright sky light is at this time of day, and the direction towards the sun or moon.
+uniform float daylight;
+uniform vec3 sky_direction;
+// Distance at which a light at full brightness fades out completely, LIGHT_RANGE in
+// client/light.rs.
+uniform float light_range;
 
 out vec3 tex_coords_out;
 out vec3 normal_out;
 out vec4 light_out;
+// Position relative to the camera, for the fog.
+out vec3 view_position;
+
+// Turns a light level from 0 to 15 into a brightness, each level being a fifth dimmer than the
+// one above it.
+float brightness(uint level) {
+    return pow(0.8, float(15u - level));
+}
 
 void main() {
     float x = float((data.x & 0x00f0u) >>  4u) * 0.5;
     float y = float((data.x & 0x0f00u) >>  8u) * 0.5;
     float z = float((data.x & 0xf000u) >> 12u) * 0.5;
+    float width = float(((data.x & 0x0f0000u) >> 16u) + 1u);
+    float height = float(((data.x & 0xf00000u) >> 20u) + 1u);
 
-    mat4 new_model = model;
+    vec4 origin = texelFetch(chunk_table, ivec2(0, int(chunk)), 0);
+    mat4 new_model = mat4(1.0);
+    new_model[3].xyz = origin.xyz;
     new_model[3].x += x;
     new_model[3].y += y;
     new_model[3].z += z;
@@ -117,6 +130,44 @@ void main() {
             );
             break;
 
+        // The two sides of the two planes of a cross model. The square's x axis runs between
+        // opposite corners of the block, its z axis runs up.
+        case 6u:
+            face_rotation = mat4(
+                1.0, 0.0, 1.0, 0.0,
+                0.7071, 0.0, -0.7071, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0
+            );
+            break;
+
+        case 7u:
+            face_rotation = mat4(
+                -1.0, 0.0, -1.0, 0.0,
+                -0.7071, 0.0, 0.7071, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0
+            );
+            break;
+
+        case 8u:
+            face_rotation = mat4(
+                1.0, 0.0, -1.0, 0.0,
+                -0.7071, 0.0, -0.7071, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0
+            );
+            break;
+
+        case 9u:
+            face_rotation = mat4(
+                -1.0, 0.0, 1.0, 0.0,
+                0.7071, 0.0, 0.7071, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0
+            );
+            break;
+
         // identity just in case
         default:
             face_rotation = mat4(
@@ -128,24 +179,74 @@ void main() {
             break;
     }
 
-    vec3 light_colour = vec3(0.0);
+    // Sky light baked into the face, in the top byte. It dims at night and is a little brighter
+    // on faces turned towards the sun or moon.
+    vec3 world_normal = normalize((face_rotation * vec4(0.0, 1.0, 0.0, 0.0)).xyz);
+    float facing = 0.8 + 0.2 * max(dot(world_normal, sky_direction), 0.0);
+    vec3 light_colour = vec3(brightness(data.x >> 24u) * daylight * facing);
+    // Blocks that give off light, which fade out over light_range world units.
+    uint light_count = min(uint(origin.w), LIGHT_COUNT);
     for (uint i = 0u; i < light_count; i++) {
-        if (lights[i].colour != 0u) {
-            float dist = distance(lights[i].position, (new_model * face_rotation)[3].xyz);
-            vec3 colour = vec3(float((lights[i].colour & 0xf000u) >> 12) / 15.0, float((lights[i].colour & 0x0f00u) >> 8) / 15.0, float((lights[i].colour & 0x00f0u) >> 4) / 15.0);
-            light_colour += max(colour - vec3(dist / 7.5), vec3(0.0));
-        }
+        vec3 position = texelFetch(chunk_table, ivec2(1 + 2 * int(i), int(chunk)), 0).xyz;
+        vec3 colour = texelFetch(chunk_table, ivec2(2 + 2 * int(i), int(chunk)), 0).rgb;
+        float dist = distance(position, (new_model * face_rotation)[3].xyz);
+        light_colour += max(colour - vec3(dist / light_range), vec3(0.0));
     }
     const float min_light = 0.05;
     light_colour *= vec3(1.0 - min_light);
     light_colour += vec3(min_light);
     light_out = vec4(min(light_colour, vec3(1.0)), 1.0);
-    if (selected != 0u) {
-        light_out *= vec4(vec3(1.5), 1.0);
+
+    // Faces are placed in block units along their width (u) and height (v) axes, from the
+    // corner of the block in the negative direction of both axes. Merged faces grow from the
+    // corner block towards positive world coordinates. The square's x axis points towards
+    // negative world coordinates for down, back and left faces, and its z axis always points
+    // towards positive world coordinates.
+    uint dir = data.x & 0x000fu;
+    float u_sign = (dir == 1u || dir == 3u || dir == 4u) ? -1.0 : 1.0;
+    float u0 = float(shape & 0x1fu) / 16.0;
+    float u1 = float((shape >> 5u) & 0x1fu) / 16.0 + width - 1.0;
+    float v0 = float((shape >> 10u) & 0x1fu) / 16.0;
+    float v1 = float((shape >> 15u) & 0x1fu) / 16.0 + height - 1.0;
+    float depth = float((shape >> 20u) & 0x1fu) / 16.0;
+
+    // The square is split into two triangles along the diagonal between its (-x, +z) and
+    // (+x, -z) corners, which join the face's 01 and 10 corners, or its 11 and 00 corners when
+    // the x axis is flipped. A quarter turn of the square splits it along the other diagonal,
+    // which is used when that diagonal's corners are less occluded so that the shading of the
+    // two triangles stays symmetric.
+    uint ao_00 = ao & 3u;
+    uint ao_10 = (ao >> 2u) & 3u;
+    uint ao_01 = (ao >> 4u) & 3u;
+    uint ao_11 = (ao >> 6u) & 3u;
+    bool split_00_11 = u_sign < 0.0;
+    vec3 corner = position;
+    if ((split_00_11 && ao_00 + ao_11 < ao_10 + ao_01)
+            || (!split_00_11 && ao_00 + ao_11 > ao_10 + ao_01)) {
+        corner = vec3(position.z, position.y, -position.x);
     }
 
+    uint corner_u = u_sign * corner.x > 0.0 ? 1u : 0u;
+    uint corner_v = corner.z > 0.0 ? 1u : 0u;
+    uint occlusion = (ao >> ((corner_u + 2u * corner_v) * 2u)) & 3u;
+    light_out.rgb *= 0.55 + 0.15 * float(occlusion);
+
+    float u = mix(u0, u1, u_sign * corner.x * 2.0 + 0.5);
+    float v = mix(v0, v1, corner.z * 2.0 + 0.5);
+    vec3 scaled = vec3(u_sign * (u - 0.5) * 0.5, 0.25 - depth * 0.5, (v - 0.5) * 0.5);
+
     mat4 model_view = view * new_model * face_rotation;
     normal_out = transpose(inverse(mat3(model_view))) * (face_rotation * vec4(0.0, 1.0, 0.0, 1.0)).xyz;
-    tex_coords_out = vec3(tex_coords, (float(data.y) + 0.5) / texture_count);
-    gl_Position = perspective * model_view * vec4(position, 1.0);
+
+    // Animated textures have their frames in consecutive layers.
+    uint layer = data.y & 0xffffu;
+    uint frames = ((data.y >> 16u) & 0xffu) + 1u;
+    uint frame_ticks = max(data.y >> 24u, 1u);
+    layer += (animation_tick / frame_ticks) % frames;
+
+    // The texture is repeated once per block, following the square's x and z axes.
+    tex_coords_out = vec3(u_sign > 0.0 ? u : 1.0 - u, v, float(layer));
+    vec4 position_in_view = model_view * vec4(scaled, 1.0);
+    view_position = position_in_view.xyz;
+    gl_Position = perspective * position_in_view;
 }
@@ -1,8 +1,13 @@
 #version 140
 
 in vec3 normal_out;
+in vec3 view_position;
 
 uniform vec3 light;
+// See chunks-fragment.glsl.
+uniform float fog_start;
+uniform float fog_end;
+uniform vec3 fog_colour;
 
 out vec4 color;
 
@@ -11,4 +16,9 @@ void main() {
     vec3 dark_color = vec3(0.6, 0.0, 0.0);
     vec3 regular_color = vec3(1.0, 0.0, 0.0);
     color = vec4(mix(dark_color, regular_color, brightness), 1.0);
+
+    if (fog_end > fog_start) {
+        float fog = clamp((length(view_position) - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+        color.rgb = mix(color.rgb, fog_colour, fog);
+    }
 }
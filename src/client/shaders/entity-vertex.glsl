@@ -9,9 +9,13 @@ uniform mat4 view;
 uniform mat4 perspective;
 
 out vec3 normal_out;
+// Position relative to the camera, for the fog.
+out vec3 view_position;
 
 void main() {
     mat4 model_view = view * model;
     normal_out = transpose(inverse(mat3(model_view))) * normal;
-    gl_Position = perspective * model_view * vec4(position, 1.0);
+    vec4 position_in_view = model_view * vec4(position, 1.0);
+    view_position = position_in_view.xyz;
+    gl_Position = perspective * position_in_view;
 }
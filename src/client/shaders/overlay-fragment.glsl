@@ -0,0 +1,9 @@
+#version 150
+
+uniform vec4 colour;
+
+out vec4 color;
+
+void main() {
+    color = colour;
+}
@@ -1,16 +1,33 @@
-use nalgebra::Vector3;
+use nalgebra::{Matrix4, Vector3};
 
+pub use crate::collision::Aabb;
+
+/// A plane dividing space in two. Points on the side its normal points to have a positive
+/// signed distance.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Plane {
     pub normal: [f32; 3],
     pub distance: f32,
 }
 
 impl Plane {
-    fn get_signed_distance_to_plan(&self, point: [f32; 3]) -> f32 {
+    /// Returns the plane `a*x + b*y + c*z + d = 0`, normalised.
+    fn from_coefficients(coefficients: [f32; 4]) -> Plane {
+        let [a, b, c, d] = coefficients;
+        let length = Vector3::new(a, b, c).norm();
+        Plane {
+            normal: [a / length, b / length, c / length],
+            distance: -d / length,
+        }
+    }
+
+    pub fn signed_distance(&self, point: [f32; 3]) -> f32 {
         Vector3::from(point).dot(&Vector3::from(self.normal)) - self.distance
     }
 }
 
+/// The volume visible to a camera, bounded by six planes whose normals all point inwards.
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Frustum {
     pub top: Plane,
     pub bottom: Plane,
@@ -20,28 +37,65 @@ pub struct Frustum {
     pub near: Plane,
 }
 
-#[derive(Debug)]
-pub struct Aabb {
-    pub centre: [f32; 3],
-    pub extents: [f32; 3],
+impl Frustum {
+    /// Extracts the frustum planes from a column major view-projection matrix that maps the
+    /// visible volume to OpenGL clip space, where `-w <= x, y, z <= w`.
+    pub fn from_matrix(view_projection: [[f32; 4]; 4]) -> Frustum {
+        // Gribb and Hartmann, "Fast Extraction of Viewing Frustum Planes from the
+        // World-View-Projection Matrix".
+        let matrix = Matrix4::from(view_projection);
+        let row = |i: usize| {
+            let row = matrix.row(i);
+            [row[0], row[1], row[2], row[3]]
+        };
+        let plane = |i: usize, sign: f32| {
+            let (w, r) = (row(3), row(i));
+            Plane::from_coefficients([
+                w[0] + sign * r[0],
+                w[1] + sign * r[1],
+                w[2] + sign * r[2],
+                w[3] + sign * r[3],
+            ])
+        };
+
+        Frustum {
+            left: plane(0, 1.0),
+            right: plane(0, -1.0),
+            bottom: plane(1, 1.0),
+            top: plane(1, -1.0),
+            near: plane(2, 1.0),
+            far: plane(2, -1.0),
+        }
+    }
+
+    pub fn planes(&self) -> [&Plane; 6] {
+        [
+            &self.near,
+            &self.far,
+            &self.left,
+            &self.right,
+            &self.top,
+            &self.bottom,
+        ]
+    }
 }
 
 impl Aabb {
-    fn is_on_or_forward_plane(&self, plane: &Plane) -> bool {
+    /// Returns whether any part of the box is on the side of the plane its normal points to.
+    pub fn is_on_or_forward_plane(&self, plane: &Plane) -> bool {
         let r = self.extents[0] * plane.normal[0].abs()
             + self.extents[1] * plane.normal[1].abs()
             + self.extents[2] * plane.normal[2].abs();
 
-        -r <= plane.get_signed_distance_to_plan(self.centre)
+        -r <= plane.signed_distance(self.centre)
     }
 
+    /// Returns whether the box may be visible. Boxes that are outside the frustum but not
+    /// entirely behind any one plane, near its corners, are also counted as visible.
     pub fn is_in_frustum(&self, frustum: &Frustum) -> bool {
-        true
-        //self.is_on_or_forward_plane(&frustum.near)
-        //&& self.is_on_or_forward_plane(&frustum.far)
-        //&& self.is_on_or_forward_plane(&frustum.left)
-        //&& self.is_on_or_forward_plane(&frustum.right)
-        //&& self.is_on_or_forward_plane(&frustum.top)
-        //&& self.is_on_or_forward_plane(&frustum.bottom)
+        frustum
+            .planes()
+            .iter()
+            .all(|plane| self.is_on_or_forward_plane(plane))
     }
 }
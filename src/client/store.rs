@@ -0,0 +1,180 @@
+//! The blocks of the chunks around the player, shared between threads.
+//!
+//! Chunks are kept behind [`Arc`]s in a single map, which is only locked long enough to look a
+//! chunk up or swap one in. Readers clone the chunks they need and work with them without holding
+//! any lock, and changing a chunk copies it first if anyone is still reading it. What is drawn for
+//! each chunk lives in the [`super::renderer::ChunkRenderer`], on the render thread alone.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::blocks::Block;
+use crate::collision::{Aabb, DetectCollision};
+use crate::coords;
+
+use super::chunk::Chunk;
+
+#[derive(Clone, Debug)]
+enum Slot {
+    /// Requested from the server at the given time, in nanoseconds since the Unix epoch, or `0`
+    /// if it has not been requested yet.
+    Requested(u128),
+    Loaded(Arc<Chunk>),
+}
+
+/// The chunks the client wants, whether they have arrived or not.
+#[derive(Debug, Default)]
+pub struct ChunkStore {
+    chunks: RwLock<HashMap<(i32, i32, i32), Slot>>,
+}
+
+impl ChunkStore {
+    pub fn new() -> ChunkStore {
+        ChunkStore::default()
+    }
+
+    /// Marks a chunk as wanted, so that it is returned by [`ChunkStore::due_requests`] until it
+    /// arrives. Chunks that are already wanted are left as they are.
+    pub fn request(&self, coords: (i32, i32, i32)) {
+        self.chunks
+            .write()
+            .unwrap()
+            .entry(coords)
+            .or_insert(Slot::Requested(0));
+    }
+
+    /// Stores a chunk that has arrived, replacing the one at its coordinates.
+    pub fn insert(&self, chunk: Chunk) {
+        let coords = chunk.coords();
+        self.chunks
+            .write()
+            .unwrap()
+            .insert(coords, Slot::Loaded(Arc::new(chunk)));
+    }
+
+    /// Returns whether the chunk is wanted, even if it has not arrived yet.
+    pub fn contains(&self, coords: (i32, i32, i32)) -> bool {
+        self.chunks.read().unwrap().contains_key(&coords)
+    }
+
+    pub fn is_loaded(&self, coords: (i32, i32, i32)) -> bool {
+        self.get(coords).is_some()
+    }
+
+    /// Returns the chunk if it has arrived.
+    pub fn get(&self, coords: (i32, i32, i32)) -> Option<Arc<Chunk>> {
+        match self.chunks.read().unwrap().get(&coords) {
+            Some(Slot::Loaded(chunk)) => Some(chunk.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns every chunk that has arrived.
+    pub fn loaded(&self) -> Vec<((i32, i32, i32), Arc<Chunk>)> {
+        self.chunks
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(&coords, slot)| match slot {
+                Slot::Loaded(chunk) => Some((coords, chunk.clone())),
+                Slot::Requested(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the chunk and the chunks around it that have arrived.
+    pub fn neighbourhood(
+        &self,
+        (x, y, z): (i32, i32, i32),
+    ) -> HashMap<(i32, i32, i32), Arc<Chunk>> {
+        let chunks = self.chunks.read().unwrap();
+        let mut neighbourhood = HashMap::new();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let coords = (x + dx, y + dy, z + dz);
+                    if let Some(Slot::Loaded(chunk)) = chunks.get(&coords) {
+                        neighbourhood.insert(coords, chunk.clone());
+                    }
+                }
+            }
+        }
+        neighbourhood
+    }
+
+    /// Changes a chunk that has arrived, returning what `change` returns, or `None` if the chunk
+    /// has not arrived.
+    pub fn update<F, R>(&self, coords: (i32, i32, i32), change: F) -> Option<R>
+    where
+        F: FnOnce(&mut Chunk) -> R,
+    {
+        match self.chunks.write().unwrap().get_mut(&coords) {
+            Some(Slot::Loaded(chunk)) => Some(change(Arc::make_mut(chunk))),
+            _ => None,
+        }
+    }
+
+    /// Returns the block at the given block coordinates, or `None` if its chunk has not arrived.
+    pub fn block(&self, block: [i32; 3]) -> Option<Block> {
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        match self.chunks.read().unwrap().get(&chunk) {
+            Some(Slot::Loaded(chunk)) => Some(chunk.block(x, y, z)),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the box overlaps the collision boxes of any block in it. Blocks in chunks
+    /// that have not arrived do not collide.
+    pub fn collides(&self, aabb: &Aabb) -> bool {
+        let corner = |sign: f32| {
+            coords::world_to_block([
+                aabb.centre[0] + sign * aabb.extents[0],
+                aabb.centre[1] + sign * aabb.extents[1],
+                aabb.centre[2] + sign * aabb.extents[2],
+            ])
+        };
+        let (min, max) = (corner(-1.0), corner(1.0));
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    let block = match self.block([x, y, z]) {
+                        Some(block) => block,
+                        None => continue,
+                    };
+                    if block
+                        .collision_boxes()
+                        .iter()
+                        .any(|b| coords::model_box_aabb([x, y, z], b).is_colliding(aabb))
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns whether any wanted chunk has not arrived yet.
+    pub fn has_requests(&self) -> bool {
+        self.chunks
+            .read()
+            .unwrap()
+            .values()
+            .any(|slot| matches!(slot, Slot::Requested(_)))
+    }
+
+    /// Returns the chunks that have not arrived and were last requested at least `interval`
+    /// nanoseconds before `now`, marking them as requested `now`.
+    pub fn due_requests(&self, now: u128, interval: u128) -> Vec<(i32, i32, i32)> {
+        let mut due = vec![];
+        for (&coords, slot) in self.chunks.write().unwrap().iter_mut() {
+            if let Slot::Requested(requested) = slot {
+                if now.saturating_sub(*requested) >= interval {
+                    *requested = now;
+                    due.push(coords);
+                }
+            }
+        }
+        due
+    }
+}
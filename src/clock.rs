@@ -0,0 +1,119 @@
+//! The world clock behind the day/night cycle.
+//!
+//! The server owns the clock and broadcasts it to clients, which keep it running on their own
+//! between updates. A day starts at sunrise, the sun is highest a quarter of the way through it
+//! and sets half way through.
+
+use std::{f32::consts::PI, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Length of a world clock tick.
+pub const TICK: Duration = Duration::from_millis(50);
+
+/// Ticks in a day unless the server is configured otherwise, twenty minutes.
+pub const DEFAULT_DAY_LENGTH: u64 = 24000;
+
+const DAY_SKY: [f32; 3] = [0.53, 0.80, 0.92];
+const NIGHT_SKY: [f32; 3] = [0.02, 0.03, 0.08];
+const SUNSET_SKY: [f32; 3] = [0.92, 0.55, 0.32];
+
+/// How bright sky light is in the middle of the night, compared to the middle of the day.
+const NIGHT_LIGHT: f32 = 0.2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldClock {
+    /// Ticks since the world began.
+    pub ticks: u64,
+    /// Ticks in a full day and night.
+    pub day_length: u64,
+    /// Whether time is stopped.
+    pub paused: bool,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        WorldClock::new(DEFAULT_DAY_LENGTH)
+    }
+}
+
+impl WorldClock {
+    /// Returns a running clock at noon of the first day.
+    pub fn new(day_length: u64) -> WorldClock {
+        let day_length = day_length.max(1);
+        WorldClock {
+            ticks: day_length / 4,
+            day_length,
+            paused: false,
+        }
+    }
+
+    /// Moves the clock forward unless it is paused.
+    pub fn advance(&mut self, ticks: u64) {
+        if !self.paused {
+            self.ticks = self.ticks.wrapping_add(ticks);
+        }
+    }
+
+    /// Returns the clock as it will be after the given time has passed.
+    pub fn after(&self, elapsed: Duration) -> WorldClock {
+        let mut clock = *self;
+        clock.advance((elapsed.as_millis() / TICK.as_millis()) as u64);
+        clock
+    }
+
+    /// Returns the number of days that have passed.
+    pub fn day(&self) -> u64 {
+        self.ticks / self.day_length
+    }
+
+    /// Returns how far through the current day the clock is, from `0` at sunrise up to `1`.
+    pub fn time_of_day(&self) -> f32 {
+        (self.ticks % self.day_length) as f32 / self.day_length as f32
+    }
+
+    /// Returns the direction towards the sun. The sun rises towards positive x, sets towards
+    /// negative x and is tilted slightly towards positive z.
+    pub fn sun_direction(&self) -> [f32; 3] {
+        let angle = self.time_of_day() * 2.0 * PI;
+        let tilt: f32 = 0.3;
+        let length = (1.0 + tilt * tilt).sqrt();
+        [angle.cos() / length, angle.sin() / length, tilt / length]
+    }
+
+    /// Returns the direction light from the sky comes from, towards the sun during the day and
+    /// towards the moon, opposite the sun, at night.
+    pub fn light_direction(&self) -> [f32; 3] {
+        let [x, y, z] = self.sun_direction();
+        if y >= 0.0 {
+            [x, y, z]
+        } else {
+            [-x, -y, -z]
+        }
+    }
+
+    /// Returns how bright sky light is, from `1` while the sun is up down to a dim moonlight.
+    pub fn daylight(&self) -> f32 {
+        let height = self.sun_direction()[1];
+        NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * smoothstep(-0.15, 0.25, height)
+    }
+
+    /// Returns the colour of the sky, reddening around sunrise and sunset.
+    pub fn sky_colour(&self) -> [f32; 3] {
+        let height = self.sun_direction()[1];
+        let day = smoothstep(-0.15, 0.25, height);
+        let sunset = (1.0 - (height / 0.3).abs()).max(0.0) * 0.6;
+
+        let mut colour = [0.0; 3];
+        for (i, channel) in colour.iter_mut().enumerate() {
+            let sky = NIGHT_SKY[i] + (DAY_SKY[i] - NIGHT_SKY[i]) * day;
+            *channel = sky + (SUNSET_SKY[i] - sky) * sunset;
+        }
+        colour
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
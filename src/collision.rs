@@ -6,6 +6,36 @@ pub struct Aabb {
     pub extents: [f32; 3],
 }
 
+impl Aabb {
+    /// Returns how far along the ray from `origin` in `direction` it enters the box, or `None` if
+    /// it misses the box. The distance is in lengths of `direction`, and is zero if `origin` is
+    /// inside of the box.
+    pub fn ray_distance(&self, origin: [f32; 3], direction: [f32; 3]) -> Option<f32> {
+        let (mut near, mut far) = (0.0f32, f32::INFINITY);
+        for i in 0..3 {
+            let min = self.centre[i] - self.extents[i];
+            let max = self.centre[i] + self.extents[i];
+            if direction[i] == 0.0 {
+                if origin[i] < min || origin[i] > max {
+                    return None;
+                }
+                continue;
+            }
+
+            let (a, b) = (
+                (min - origin[i]) / direction[i],
+                (max - origin[i]) / direction[i],
+            );
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
 pub trait Chunk {
     fn get_block(&self, x: usize, y: usize, z: usize) -> Block;
 }
@@ -0,0 +1,115 @@
+//! Conversions between the coordinate spaces of the world.
+//!
+//! - World positions are `f32` positions in world units.
+//! - Block coordinates are the integer coordinates of a block in the whole world. Blocks are
+//!   `BLOCK_SIZE` world units wide and block `[0, 0, 0]` is centred on the world origin.
+//! - Chunk coordinates are the integer coordinates of a chunk, and local coordinates index a
+//!   block inside of its chunk.
+
+use crate::{
+    blocks::CHUNK_SIZE,
+    collision::Aabb,
+    model::{ModelBox, MODEL_SIZE},
+};
+
+/// Width of a block in world units.
+pub const BLOCK_SIZE: f32 = 0.5;
+
+/// Returns the coordinates of the block containing the given world position. Positions exactly
+/// on the boundary between two blocks belong to the block in the positive direction.
+pub fn world_to_block(pos: [f32; 3]) -> [i32; 3] {
+    [
+        (pos[0] / BLOCK_SIZE + 0.5).floor() as i32,
+        (pos[1] / BLOCK_SIZE + 0.5).floor() as i32,
+        (pos[2] / BLOCK_SIZE + 0.5).floor() as i32,
+    ]
+}
+
+/// Splits block coordinates into the coordinates of the chunk containing the block and the
+/// local coordinates of the block in that chunk.
+pub fn block_to_chunk(block: [i32; 3]) -> ((i32, i32, i32), (usize, usize, usize)) {
+    let size = CHUNK_SIZE as i32;
+    (
+        (
+            block[0].div_euclid(size),
+            block[1].div_euclid(size),
+            block[2].div_euclid(size),
+        ),
+        (
+            block[0].rem_euclid(size) as usize,
+            block[1].rem_euclid(size) as usize,
+            block[2].rem_euclid(size) as usize,
+        ),
+    )
+}
+
+/// Returns the block coordinates of the block at the given local coordinates in a chunk.
+pub fn chunk_to_block(chunk: (i32, i32, i32), local: (usize, usize, usize)) -> [i32; 3] {
+    let size = CHUNK_SIZE as i32;
+    [
+        chunk.0 * size + local.0 as i32,
+        chunk.1 * size + local.1 as i32,
+        chunk.2 * size + local.2 as i32,
+    ]
+}
+
+/// Returns the chunk and local coordinates of the block containing the given world position.
+pub fn world_to_chunk(x: f32, y: f32, z: f32) -> (i32, i32, i32, usize, usize, usize) {
+    let ((chunk_x, chunk_y, chunk_z), (x, y, z)) = block_to_chunk(world_to_block([x, y, z]));
+    (chunk_x, chunk_y, chunk_z, x, y, z)
+}
+
+/// Returns the world position of the centre of a block.
+pub fn block_centre(block: [i32; 3]) -> [f32; 3] {
+    [
+        block[0] as f32 * BLOCK_SIZE,
+        block[1] as f32 * BLOCK_SIZE,
+        block[2] as f32 * BLOCK_SIZE,
+    ]
+}
+
+/// Returns the world bounds of a block.
+pub fn block_aabb(block: [i32; 3]) -> Aabb {
+    Aabb {
+        centre: block_centre(block),
+        extents: [BLOCK_SIZE * 0.5; 3],
+    }
+}
+
+/// Returns the world bounds of a box of a block model placed at the given block.
+pub fn model_box_aabb(block: [i32; 3], model_box: &ModelBox) -> Aabb {
+    let centre = block_centre(block);
+    let scale = BLOCK_SIZE / MODEL_SIZE as f32;
+    let axis = |i: usize| {
+        let (min, max) = (model_box.min[i] as f32, model_box.max[i] as f32);
+        (
+            centre[i] - BLOCK_SIZE * 0.5 + (min + max) * 0.5 * scale,
+            (max - min) * 0.5 * scale,
+        )
+    };
+    let (x, y, z) = (axis(0), axis(1), axis(2));
+    Aabb {
+        centre: [x.0, y.0, z.0],
+        extents: [x.1, y.1, z.1],
+    }
+}
+
+/// Returns the world position of the centre of the block at local coordinates `(0, 0, 0)` in a
+/// chunk. Chunk meshes are drawn relative to this point.
+pub fn chunk_origin(chunk: (i32, i32, i32)) -> [f32; 3] {
+    block_centre(chunk_to_block(chunk, (0, 0, 0)))
+}
+
+/// Returns the world bounds of a chunk.
+pub fn chunk_aabb(chunk: (i32, i32, i32)) -> Aabb {
+    let half = CHUNK_SIZE as f32 * BLOCK_SIZE * 0.5;
+    let min = chunk_origin(chunk);
+    Aabb {
+        centre: [
+            min[0] - BLOCK_SIZE * 0.5 + half,
+            min[1] - BLOCK_SIZE * 0.5 + half,
+            min[2] - BLOCK_SIZE * 0.5 + half,
+        ],
+        extents: [half; 3],
+    }
+}
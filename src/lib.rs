@@ -12,5 +12,14 @@ pub mod client;
 pub mod server;
 
 pub mod blocks;
+pub mod clock;
 pub mod collision;
+pub mod coords;
+pub mod lighting;
+pub mod lod;
+pub mod mesh;
+pub mod model;
 pub mod packet;
+#[cfg(feature = "image")]
+pub mod textures;
+pub mod visibility;
@@ -0,0 +1,299 @@
+//! Per block sky light levels.
+//!
+//! Sky light enters the top of the highest loaded chunk of each column at full strength and
+//! travels straight down without getting dimmer until it hits a block that does not let light
+//! through. It spreads to neighbouring blocks that let light through, getting one level dimmer
+//! with every block. Levels from `0` to [`MAX_LIGHT`] are propagated with a breadth first search
+//! across chunk borders and kept up to date incrementally as chunks are added and blocks are
+//! changed.
+//!
+//! Blocks that give off light are not part of this; the client draws them as dynamic lights, see
+//! `client::light`.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
+    coords,
+};
+
+/// The brightest light level.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The sky light level of a block.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Light(u8);
+
+impl Light {
+    /// Full sky light, used for blocks that are not loaded.
+    pub const SKY: Light = Light(MAX_LIGHT);
+
+    pub fn new(sky: u8) -> Light {
+        Light(sky.min(MAX_LIGHT))
+    }
+
+    pub fn sky(&self) -> u8 {
+        self.0
+    }
+}
+
+type ChunkLight = [[[Light; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+/// Light levels of every loaded chunk.
+///
+/// Functions that change light levels take a `blocks` function returning the block at the given
+/// block coordinates, or `None` if it is not loaded. They return the chunks whose meshes need to
+/// be rebuilt, which includes the neighbours of chunks whose border blocks changed.
+#[derive(Default)]
+pub struct LightMap {
+    chunks: HashMap<(i32, i32, i32), Box<ChunkLight>>,
+}
+
+impl LightMap {
+    pub fn new() -> LightMap {
+        LightMap::default()
+    }
+
+    pub fn contains_chunk(&self, chunk: (i32, i32, i32)) -> bool {
+        self.chunks.contains_key(&chunk)
+    }
+
+    /// Returns the light at the given block coordinates, or `None` if its chunk is not loaded.
+    pub fn get(&self, block: [i32; 3]) -> Option<Light> {
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        self.chunks.get(&chunk).map(|light| light[x][y][z])
+    }
+
+    fn level(&self, block: [i32; 3]) -> Option<u8> {
+        self.get(block).map(|light| light.sky())
+    }
+
+    fn set_level(&mut self, block: [i32; 3], level: u8, changed: &mut HashSet<[i32; 3]>) {
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        if let Some(light) = self.chunks.get_mut(&chunk) {
+            let old = light[x][y][z];
+            light[x][y][z] = Light::new(level);
+            if old != light[x][y][z] {
+                changed.insert(block);
+            }
+        }
+    }
+
+    /// Lights a newly loaded chunk, spreading light into and out of it.
+    pub fn add_chunk<F>(&mut self, chunk: (i32, i32, i32), blocks: F) -> HashSet<(i32, i32, i32)>
+    where
+        F: Fn([i32; 3]) -> Option<Block>,
+    {
+        let mut changed = HashSet::new();
+        self.chunks.insert(
+            chunk,
+            Box::new([[[Light::default(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]),
+        );
+
+        let mut queue = VecDeque::new();
+
+        // The chunk below used to be at the top of its column, but is now in the shade of this one.
+        let below = (chunk.0, chunk.1 - 1, chunk.2);
+        if self.contains_chunk(below) {
+            let top = CHUNK_SIZE - 1;
+            let sources = (0..CHUNK_SIZE)
+                .flat_map(|x| (0..CHUNK_SIZE).map(move |z| (x, z)))
+                .map(|(x, z)| coords::chunk_to_block(below, (x, top, z)))
+                .collect();
+            self.unpropagate(sources, &blocks, &mut queue, &mut changed);
+        }
+
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let block = coords::chunk_to_block(chunk, (x, y, z));
+                    let level = self.intrinsic(block, &blocks);
+                    if level > 0 {
+                        self.set_level(block, level, &mut changed);
+                        queue.push_back(block);
+                    }
+                }
+            }
+        }
+
+        // Let light from the neighbouring chunks in.
+        for x in -1..=CHUNK_SIZE as i32 {
+            for y in -1..=CHUNK_SIZE as i32 {
+                for z in -1..=CHUNK_SIZE as i32 {
+                    let outside = [x, y, z]
+                        .iter()
+                        .filter(|&&v| v < 0 || v >= CHUNK_SIZE as i32)
+                        .count();
+                    if outside != 1 {
+                        continue;
+                    }
+
+                    let origin = coords::chunk_to_block(chunk, (0, 0, 0));
+                    let block = [origin[0] + x, origin[1] + y, origin[2] + z];
+                    if self.level(block).unwrap_or(0) > 0 {
+                        queue.push_back(block);
+                    }
+                }
+            }
+        }
+
+        self.propagate(queue, &blocks, &mut changed);
+        self.affected_chunks(&changed)
+    }
+
+    /// Updates the light around a block after it has been changed.
+    pub fn update_block<F>(&mut self, block: [i32; 3], blocks: F) -> HashSet<(i32, i32, i32)>
+    where
+        F: Fn([i32; 3]) -> Option<Block>,
+    {
+        if self.get(block).is_none() {
+            return HashSet::new();
+        }
+
+        let mut changed = HashSet::new();
+        let mut queue = VecDeque::new();
+        self.unpropagate(vec![block], &blocks, &mut queue, &mut changed);
+        self.propagate(queue, &blocks, &mut changed);
+        self.affected_chunks(&changed)
+    }
+
+    /// Returns the light a block gets on its own, without any from its neighbours.
+    fn intrinsic<F>(&self, block: [i32; 3], blocks: &F) -> u8
+    where
+        F: Fn([i32; 3]) -> Option<Block>,
+    {
+        let this = match blocks(block) {
+            Some(this) => this,
+            None => return 0,
+        };
+
+        let (above, _) = coords::block_to_chunk([block[0], block[1] + 1, block[2]]);
+        if this.transmits_light() && !self.contains_chunk(above) {
+            MAX_LIGHT
+        } else {
+            0
+        }
+    }
+
+    /// Spreads light outwards from every block in the queue.
+    fn propagate<F>(
+        &mut self,
+        mut queue: VecDeque<[i32; 3]>,
+        blocks: &F,
+        changed: &mut HashSet<[i32; 3]>,
+    ) where
+        F: Fn([i32; 3]) -> Option<Block>,
+    {
+        while let Some(block) = queue.pop_front() {
+            let level = self.level(block).unwrap_or(0);
+            if level == 0 {
+                continue;
+            }
+
+            for &direction in FaceDirection::ALL.iter() {
+                let next = offset(block, direction);
+                let current = match self.level(next) {
+                    Some(current) => current,
+                    None => continue,
+                };
+                if !blocks(next).map(|b| b.transmits_light()).unwrap_or(false) {
+                    continue;
+                }
+
+                let spread = spread(direction, level);
+                if spread > current {
+                    self.set_level(next, spread, changed);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Removes the light of the given blocks and all light that came from them, then gives the
+    /// darkened blocks back their own light. Blocks that are still lit from elsewhere are added
+    /// to `queue` so that [`LightMap::propagate`] can fill the darkened area back in.
+    fn unpropagate<F>(
+        &mut self,
+        sources: Vec<[i32; 3]>,
+        blocks: &F,
+        queue: &mut VecDeque<[i32; 3]>,
+        changed: &mut HashSet<[i32; 3]>,
+    ) where
+        F: Fn([i32; 3]) -> Option<Block>,
+    {
+        let mut darkened = vec![];
+        let mut removals = VecDeque::new();
+        for block in sources {
+            if let Some(level) = self.level(block) {
+                self.set_level(block, 0, changed);
+                darkened.push(block);
+                removals.push_back((block, level));
+            }
+        }
+
+        while let Some((block, level)) = removals.pop_front() {
+            for &direction in FaceDirection::ALL.iter() {
+                let next = offset(block, direction);
+                let current = match self.level(next) {
+                    Some(current) if current > 0 => current,
+                    _ => continue,
+                };
+
+                let dependent = if spread(direction, level) == level {
+                    current == level
+                } else {
+                    current < level
+                };
+                if dependent {
+                    self.set_level(next, 0, changed);
+                    darkened.push(next);
+                    removals.push_back((next, current));
+                } else {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        for block in darkened {
+            let level = self.intrinsic(block, blocks);
+            if level > 0 {
+                self.set_level(block, level, changed);
+                queue.push_back(block);
+            }
+        }
+    }
+
+    /// Returns the chunks containing the changed blocks and the loaded chunks next to changed
+    /// border blocks, whose faces may be lit by them.
+    fn affected_chunks(&self, changed: &HashSet<[i32; 3]>) -> HashSet<(i32, i32, i32)> {
+        let mut chunks = HashSet::new();
+        for &block in changed {
+            chunks.insert(coords::block_to_chunk(block).0);
+            for &direction in FaceDirection::ALL.iter() {
+                let (chunk, _) = coords::block_to_chunk(offset(block, direction));
+                if self.contains_chunk(chunk) {
+                    chunks.insert(chunk);
+                }
+            }
+        }
+        chunks
+    }
+}
+
+/// Returns the level light has after spreading in the given direction.
+fn spread(direction: FaceDirection, level: u8) -> u8 {
+    if direction == FaceDirection::Down && level == MAX_LIGHT {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+fn offset(block: [i32; 3], direction: FaceDirection) -> [i32; 3] {
+    let [dx, dy, dz] = direction.offset();
+    [
+        block[0] + dx as i32,
+        block[1] + dy as i32,
+        block[2] + dz as i32,
+    ]
+}
@@ -0,0 +1,170 @@
+//! Downsampled chunks for drawing distant terrain.
+//!
+//! Chunks far from the camera are sent as [`LodChunk`]s, where every cell stands for a cube of
+//! `scale` blocks on each side. The further a ring of chunks is from the camera, the coarser its
+//! chunks are, see [`lod_scale`].
+//!
+//! The faces on the borders of a downsampled chunk are always drawn, as the chunks next to it may
+//! have a different scale and not line up with it. This keeps gaps from opening between rings.
+
+use std::{cmp::Reverse, collections::HashMap, ops::RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blocks::{Block, CHUNK_SIZE},
+    lighting::Light,
+    mesh::{self, Face},
+};
+
+/// The scales chunks can be downsampled by, from the finest to the coarsest.
+pub const LOD_SCALES: [usize; 3] = [2, 4, 8];
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+/// A chunk with its blocks downsampled by a factor of `scale` along each axis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LodChunk {
+    chunk: (i32, i32, i32),
+    scale: usize,
+    /// Cells indexed by `(x * size + y) * size + z`.
+    cells: Vec<Block>,
+}
+
+impl LodChunk {
+    /// Downsamples the blocks of a chunk. Every cell is filled with the most common cube among
+    /// the blocks it covers if at least half of them are cubes, and is air otherwise. Blocks that
+    /// are not cubes, like tall grass, are too small to see from far away.
+    ///
+    /// Panics if `scale` is not in [`LOD_SCALES`].
+    pub fn new(chunk: (i32, i32, i32), blocks: &Blocks, scale: usize) -> LodChunk {
+        assert!(LOD_SCALES.contains(&scale), "invalid LOD scale {}", scale);
+
+        let size = CHUNK_SIZE / scale;
+        let mut cells = Vec::with_capacity(size * size * size);
+        let mut counts = HashMap::new();
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    counts.clear();
+                    for plane in &blocks[x * scale..(x + 1) * scale] {
+                        for column in &plane[y * scale..(y + 1) * scale] {
+                            for &block in &column[z * scale..(z + 1) * scale] {
+                                if block != Block::air() && block.is_cube() {
+                                    *counts.entry(block).or_insert(0) += 1;
+                                }
+                            }
+                        }
+                    }
+
+                    let cubes: usize = counts.values().sum();
+                    let cell = if 2 * cubes >= scale * scale * scale {
+                        // Ties go to the first registered block, so that the result does not
+                        // depend on the order of the map.
+                        counts
+                            .iter()
+                            .max_by_key(|&(&block, &count)| (count, Reverse(block)))
+                            .map(|(&block, _)| block)
+                            .unwrap()
+                    } else {
+                        Block::air()
+                    };
+                    cells.push(cell);
+                }
+            }
+        }
+
+        LodChunk {
+            chunk,
+            scale,
+            cells,
+        }
+    }
+
+    pub fn coords(&self) -> (i32, i32, i32) {
+        self.chunk
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// Returns the number of cells along each axis.
+    pub fn size(&self) -> usize {
+        CHUNK_SIZE / self.scale
+    }
+
+    /// Returns the cell at the given cell coordinates.
+    pub fn cell(&self, x: usize, y: usize, z: usize) -> Block {
+        let size = self.size();
+        self.cells[(x * size + y) * size + z]
+    }
+
+    /// Returns the blocks of the chunk, with every cell filling the blocks it covers.
+    pub fn expand(&self) -> Box<Blocks> {
+        let mut blocks = Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+        for (x, plane) in blocks.iter_mut().enumerate() {
+            for (y, column) in plane.iter_mut().enumerate() {
+                for (z, block) in column.iter_mut().enumerate() {
+                    *block = self.cell(x / self.scale, y / self.scale, z / self.scale);
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Returns the faces of the downsampled chunk, merged like [`mesh::greedy_mesh_chunk`]. The
+    /// chunk is lit by the sky alone and its border faces are never hidden.
+    pub fn mesh(&self) -> Vec<Face> {
+        mesh::greedy_mesh_chunk(&self.expand(), |_, _, _| Block::air(), |_, _, _| Light::SKY)
+    }
+}
+
+/// Returns the scale chunks at the given distance in chunks from the camera are drawn at: `1`
+/// for full detail up to `full_detail` chunks away, then doubling every time the distance does,
+/// up to the coarsest of [`LOD_SCALES`].
+pub fn lod_scale(distance: i32, full_detail: i32) -> usize {
+    let mut scale = 1;
+    let mut limit = full_detail.max(1);
+    while distance > limit && scale < LOD_SCALES[LOD_SCALES.len() - 1] {
+        scale *= 2;
+        limit *= 2;
+    }
+    scale
+}
+
+/// Returns the distance between two chunks along the ground, the larger of the distances along
+/// the x and z axes, so that rings of chunks with the same distance are squares.
+pub fn chunk_distance(a: (i32, i32, i32), b: (i32, i32, i32)) -> i32 {
+    (a.0 - b.0).abs().max((a.2 - b.2).abs())
+}
+
+/// Returns the scale of every downsampled chunk within `distance` chunks of the camera's chunk,
+/// in the given range of chunk heights. Chunks for which `full` returns `true` are drawn in full
+/// detail and left out, while chunks close enough for full detail that are not get the finest
+/// downsampled scale.
+pub fn lod_rings<F>(
+    camera: (i32, i32, i32),
+    full_detail: i32,
+    distance: i32,
+    heights: RangeInclusive<i32>,
+    full: F,
+) -> HashMap<(i32, i32, i32), usize>
+where
+    F: Fn((i32, i32, i32)) -> bool,
+{
+    let mut rings = HashMap::new();
+    for x in camera.0 - distance..=camera.0 + distance {
+        for z in camera.2 - distance..=camera.2 + distance {
+            for y in heights.clone() {
+                let chunk = (x, y, z);
+                if full(chunk) {
+                    continue;
+                }
+                let scale = lod_scale(chunk_distance(camera, chunk), full_detail);
+                rings.insert(chunk, scale.max(LOD_SCALES[0]));
+            }
+        }
+    }
+    rings
+}
@@ -0,0 +1,339 @@
+use crate::blocks::{Block, FaceDirection, CHUNK_SIZE};
+use crate::lighting::Light;
+use crate::model::FaceShape;
+
+/// A visible face of a block in a chunk.
+///
+/// A face may cover a `width` by `height` rectangle of blocks, extending from the block at
+/// `(x, y, z)` in the positive direction of the face's width and height axes (see
+/// [`face_axes`]). Only full, unoccluded faces of cubes with the same light are merged, faces
+/// produced by [`mesh_chunk`] and faces with any other shape always cover a single block.
+///
+/// `light` is the light of the block the face looks out into, or of the block itself for faces
+/// that are not on its side.
+///
+/// `ao` is the ambient occlusion at the corners of the face, from `0` for a corner tucked into
+/// two neighbouring blocks to `3` for an open corner. The corners are indexed by `u + 2 * v`,
+/// where `u` and `v` are `0` for the corner on the negative side of the face's width and height
+/// axes and `1` for the corner on the positive side. Merged faces are never occluded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Face {
+    pub direction: FaceDirection,
+    pub block: Block,
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub width: usize,
+    pub height: usize,
+    pub shape: FaceShape,
+    pub light: Light,
+    pub ao: [u8; 4],
+}
+
+/// Ambient occlusion of a face with no blocks around it.
+pub const UNOCCLUDED: [u8; 4] = [3; 4];
+
+impl Face {
+    /// Splits the face into the single block faces it covers.
+    pub fn unit_faces(&self) -> impl Iterator<Item = Face> + '_ {
+        let (_, u, v) = face_axes(self.direction);
+        (0..self.height).flat_map(move |j| {
+            (0..self.width).map(move |i| {
+                let mut pos = [self.x, self.y, self.z];
+                pos[u] += i;
+                pos[v] += j;
+                Face {
+                    x: pos[0],
+                    y: pos[1],
+                    z: pos[2],
+                    width: 1,
+                    height: 1,
+                    ..*self
+                }
+            })
+        })
+    }
+}
+
+/// How chunk meshes are built.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MeshMode {
+    /// One face per visible block side.
+    Naive,
+    /// Adjacent faces of the same block are merged into larger rectangles.
+    Greedy,
+}
+
+impl MeshMode {
+    pub fn mesh<F, L>(
+        &self,
+        blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+        neighbour: F,
+        light: L,
+    ) -> Vec<Face>
+    where
+        F: Fn(isize, isize, isize) -> Block,
+        L: Fn(isize, isize, isize) -> Light,
+    {
+        match self {
+            MeshMode::Naive => mesh_chunk(blocks, neighbour, light),
+            MeshMode::Greedy => greedy_mesh_chunk(blocks, neighbour, light),
+        }
+    }
+
+    pub fn toggled(&self) -> MeshMode {
+        match self {
+            MeshMode::Naive => MeshMode::Greedy,
+            MeshMode::Greedy => MeshMode::Naive,
+        }
+    }
+}
+
+/// Returns the axes of a face as indices into `[x, y, z]`: the axis the face looks along, then
+/// the axes its width and height extend along.
+///
+/// The width and height axes match the x and z axes of the unit square the chunks shader
+/// rotates into place for each face.
+pub fn face_axes(direction: FaceDirection) -> (usize, usize, usize) {
+    match direction {
+        FaceDirection::Up | FaceDirection::Down => (1, 0, 2),
+        FaceDirection::Front | FaceDirection::Back => (0, 2, 1),
+        FaceDirection::Left | FaceDirection::Right => (2, 0, 1),
+    }
+}
+
+/// Returns every face in the chunk that is not hidden by the block next to it, in every render
+/// layer.
+///
+/// `neighbour` is called for positions just outside of the chunk, in the chunk's local
+/// coordinates (so `x == -1` is the last column of the chunk in the negative x direction), and
+/// should return air for chunks that are not loaded. Positions may be outside of the chunk on
+/// more than one axis, for the corners used for ambient occlusion. `light` is called for
+/// positions inside of the chunk and just outside of it, in the same coordinates.
+pub fn mesh_chunk<F, L>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: F,
+    light: L,
+) -> Vec<Face>
+where
+    F: Fn(isize, isize, isize) -> Block,
+    L: Fn(isize, isize, isize) -> Light,
+{
+    let mut faces = vec![];
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                visible_faces(blocks, &neighbour, &light, [x, y, z], |face| {
+                    faces.push(face)
+                });
+            }
+        }
+    }
+
+    faces
+}
+
+/// Like [`mesh_chunk`], but merges adjacent full faces of the same block and light into
+/// rectangles. The merged faces cover exactly the same surface as the faces from [`mesh_chunk`].
+pub fn greedy_mesh_chunk<F, L>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: F,
+    light: L,
+) -> Vec<Face>
+where
+    F: Fn(isize, isize, isize) -> Block,
+    L: Fn(isize, isize, isize) -> Light,
+{
+    let mut faces = vec![];
+
+    // One mask of full, unoccluded faces for every layer of blocks in every direction, indexed by
+    // the position along the face's width and height axes. Occluded faces are kept separate, as
+    // their shading only fits a single block.
+    let mut masks = vec![[[[None; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]; 6];
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                visible_faces(blocks, &neighbour, &light, [x, y, z], |face| {
+                    if face.shape == FaceShape::FULL && face.ao == UNOCCLUDED {
+                        let (normal, u, v) = face_axes(face.direction);
+                        let pos = [x, y, z];
+                        masks[face.direction as usize][pos[normal]][pos[u]][pos[v]] =
+                            Some((face.block, face.light));
+                    } else {
+                        faces.push(face);
+                    }
+                });
+            }
+        }
+    }
+
+    for &direction in FaceDirection::ALL.iter() {
+        let (normal, u, v) = face_axes(direction);
+
+        for (layer, mask) in masks[direction as usize].iter_mut().enumerate() {
+            for j in 0..CHUNK_SIZE {
+                let mut i = 0;
+                while i < CHUNK_SIZE {
+                    let (block, light) = match mask[i][j] {
+                        Some(face) => face,
+                        None => {
+                            i += 1;
+                            continue;
+                        }
+                    };
+
+                    let mut width = 1;
+                    while i + width < CHUNK_SIZE && mask[i + width][j] == Some((block, light)) {
+                        width += 1;
+                    }
+
+                    let mut height = 1;
+                    while j + height < CHUNK_SIZE
+                        && (i..i + width).all(|k| mask[k][j + height] == Some((block, light)))
+                    {
+                        height += 1;
+                    }
+
+                    for column in mask[i..i + width].iter_mut() {
+                        for cell in column[j..j + height].iter_mut() {
+                            *cell = None;
+                        }
+                    }
+
+                    let mut pos = [0; 3];
+                    pos[normal] = layer;
+                    pos[u] = i;
+                    pos[v] = j;
+                    faces.push(Face {
+                        direction,
+                        block,
+                        x: pos[0],
+                        y: pos[1],
+                        z: pos[2],
+                        width,
+                        height,
+                        shape: FaceShape::FULL,
+                        light,
+                        ao: UNOCCLUDED,
+                    });
+
+                    i += width;
+                }
+            }
+        }
+    }
+
+    faces
+}
+
+/// Calls `f` with every face of the block's model that is visible. Faces on the sides of the
+/// block are hidden by the block next to them according to [`Block::hides_face_of`], any other
+/// face is always visible.
+fn visible_faces<F, L, G>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: &F,
+    light: &L,
+    [x, y, z]: [usize; 3],
+    mut f: G,
+) where
+    F: Fn(isize, isize, isize) -> Block,
+    L: Fn(isize, isize, isize) -> Light,
+    G: FnMut(Face),
+{
+    let block = blocks[x][y][z];
+    if block == Block::air() || block.render_layer().is_none() {
+        return;
+    }
+
+    let model_faces = if block.is_cube() {
+        FaceDirection::ALL
+            .iter()
+            .map(|&direction| (direction, FaceShape::FULL))
+            .collect()
+    } else {
+        block.model().map(|model| model.faces()).unwrap_or_default()
+    };
+
+    for (direction, shape) in model_faces {
+        let (x, y, z) = (x as isize, y as isize, z as isize);
+        let (face_light, ao) = if shape.is_on_side() {
+            let [dx, dy, dz] = direction.offset();
+            let front = [x + dx, y + dy, z + dz];
+            if block_at(blocks, neighbour, front).hides_face_of(block, direction) {
+                continue;
+            }
+
+            (
+                light(front[0], front[1], front[2]),
+                ambient_occlusion(blocks, neighbour, front, direction),
+            )
+        } else {
+            (light(x, y, z), UNOCCLUDED)
+        };
+
+        f(Face {
+            direction,
+            block,
+            x: x as usize,
+            y: y as usize,
+            z: z as usize,
+            width: 1,
+            height: 1,
+            shape,
+            light: face_light,
+            ao,
+        });
+    }
+}
+
+/// Returns the ambient occlusion at each corner of a face looking out into the block at `front`,
+/// from the two blocks next to the corner and the block diagonally across it, all in the layer
+/// of blocks the face looks out into.
+fn ambient_occlusion<F>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: &F,
+    front: [isize; 3],
+    direction: FaceDirection,
+) -> [u8; 4]
+where
+    F: Fn(isize, isize, isize) -> Block,
+{
+    let (_, u, v) = face_axes(direction);
+    let occludes = |du: isize, dv: isize| {
+        let mut pos = front;
+        pos[u] += du;
+        pos[v] += dv;
+        !block_at(blocks, neighbour, pos).transmits_light() as u8
+    };
+
+    let mut ao = [0; 4];
+    for (corner, level) in ao.iter_mut().enumerate() {
+        let du = if corner & 1 == 0 { -1 } else { 1 };
+        let dv = if corner & 2 == 0 { -1 } else { 1 };
+        let (side_u, side_v) = (occludes(du, 0), occludes(0, dv));
+        *level = if side_u + side_v == 2 {
+            0
+        } else {
+            3 - side_u - side_v - occludes(du, dv)
+        };
+    }
+    ao
+}
+
+/// Returns the block at a position in the chunk's local coordinates, asking `neighbour` for
+/// positions outside of the chunk.
+fn block_at<F>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: &F,
+    [x, y, z]: [isize; 3],
+) -> Block
+where
+    F: Fn(isize, isize, isize) -> Block,
+{
+    let size = CHUNK_SIZE as isize;
+    if (0..size).contains(&x) && (0..size).contains(&y) && (0..size).contains(&z) {
+        blocks[x as usize][y as usize][z as usize]
+    } else {
+        neighbour(x, y, z)
+    }
+}
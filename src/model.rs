@@ -0,0 +1,142 @@
+//! Block models, describing the shape a block is drawn and collided with.
+//!
+//! Models are measured in sixteenths of a block, from `0` on the negative side of the block to
+//! `16` on the positive side.
+
+use crate::blocks::FaceDirection;
+use crate::mesh::face_axes;
+
+/// Number of model units in a block.
+pub const MODEL_SIZE: u8 = 16;
+
+/// An axis aligned box inside of a block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ModelBox {
+    pub min: [u8; 3],
+    pub max: [u8; 3],
+}
+
+impl ModelBox {
+    pub const fn new(min: [u8; 3], max: [u8; 3]) -> ModelBox {
+        ModelBox { min, max }
+    }
+
+    /// Returns the face of the box looking in the given direction.
+    pub fn face(&self, direction: FaceDirection) -> FaceShape {
+        let (normal, u, v) = face_axes(direction);
+        let depth = if direction.offset()[normal] > 0 {
+            MODEL_SIZE - self.max[normal]
+        } else {
+            self.min[normal]
+        };
+
+        FaceShape::Rect {
+            u: [self.min[u], self.max[u]],
+            v: [self.min[v], self.max[v]],
+            depth,
+        }
+    }
+}
+
+/// The shape of a single face of a model.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaceShape {
+    /// A rectangle facing along one of the block axes. `u` and `v` are the ranges the face covers
+    /// along the width and height axes of its direction (see [`face_axes`]), and `depth` is how
+    /// far the face is sunk into the block from the side it looks out of.
+    Rect { u: [u8; 2], v: [u8; 2], depth: u8 },
+    /// One side of the two planes of a cross model, which run between opposite vertical edges of
+    /// the block. `0` and `1` are the two sides of the plane from `(0, 0)` to `(16, 16)` on the
+    /// x and z axes, `2` and `3` the two sides of the plane from `(0, 16)` to `(16, 0)`.
+    Diagonal(u8),
+}
+
+impl FaceShape {
+    /// A face covering the whole side of the block.
+    pub const FULL: FaceShape = FaceShape::Rect {
+        u: [0, MODEL_SIZE],
+        v: [0, MODEL_SIZE],
+        depth: 0,
+    };
+
+    /// Returns whether the face lies on the side of the block, where the neighbouring block can
+    /// hide it.
+    pub fn is_on_side(&self) -> bool {
+        matches!(self, FaceShape::Rect { depth: 0, .. })
+    }
+}
+
+/// The shape of a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockModel {
+    /// A full block.
+    Cube,
+    /// Two crossed planes, for plants.
+    Cross,
+    /// The bottom half of a block.
+    Slab,
+    /// A thin layer lying on the floor of the block, for tracks and carpets.
+    Flat,
+    /// Any number of boxes.
+    Boxes(Vec<ModelBox>),
+}
+
+impl BlockModel {
+    /// Returns the boxes the model is made of. Cross models have no boxes.
+    pub fn boxes(&self) -> Vec<ModelBox> {
+        match self {
+            BlockModel::Cube => vec![ModelBox::new([0; 3], [MODEL_SIZE; 3])],
+            BlockModel::Cross => vec![],
+            BlockModel::Slab => vec![ModelBox::new(
+                [0; 3],
+                [MODEL_SIZE, MODEL_SIZE / 2, MODEL_SIZE],
+            )],
+            BlockModel::Flat => vec![ModelBox::new([0; 3], [MODEL_SIZE, 1, MODEL_SIZE])],
+            BlockModel::Boxes(boxes) => boxes.clone(),
+        }
+    }
+
+    /// Returns every face of the model along with the direction it looks in. Diagonal faces of
+    /// cross models are given the front direction, which selects their texture.
+    pub fn faces(&self) -> Vec<(FaceDirection, FaceShape)> {
+        if let BlockModel::Cross = self {
+            return (0..4)
+                .map(|side| (FaceDirection::Front, FaceShape::Diagonal(side)))
+                .collect();
+        }
+
+        let mut faces = vec![];
+        for b in self.boxes() {
+            for &direction in FaceDirection::ALL.iter() {
+                faces.push((direction, b.face(direction)));
+            }
+        }
+        faces
+    }
+
+    /// Returns whether the model completely covers the side of the block in the given direction,
+    /// hiding the face of the neighbouring block behind it.
+    pub fn covers(&self, direction: FaceDirection) -> bool {
+        match self {
+            BlockModel::Cube => true,
+            _ => self
+                .boxes()
+                .iter()
+                .any(|b| b.face(direction) == FaceShape::FULL),
+        }
+    }
+
+    /// Returns the boxes solid blocks with this model collide with.
+    pub fn collision_boxes(&self) -> Vec<ModelBox> {
+        self.boxes()
+    }
+
+    /// Returns the boxes a block with this model can be targeted by and is outlined with. Cross
+    /// models are targeted by the whole block their planes stand in.
+    pub fn selection_boxes(&self) -> Vec<ModelBox> {
+        match self {
+            BlockModel::Cross => BlockModel::Cube.boxes(),
+            _ => self.boxes(),
+        }
+    }
+}
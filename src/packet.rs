@@ -1,15 +1,34 @@
 use serde::{Deserialize, Serialize};
 
+use crate::clock::WorldClock;
+use crate::lod::LodChunk;
 use crate::server::chunk::Chunk;
 
 /// Packet from user to server
 #[derive(Serialize, Deserialize, Debug)]
 pub enum UserPacket {
-    ConnectionRequest { name: String },
+    ConnectionRequest {
+        name: String,
+    },
     Disconnect,
-    Ping { timestamp: u128 },
-    MoveSelf { pos: [f32; 3] },
-    RequestChunk { x: i32, y: i32, z: i32 },
+    Ping {
+        timestamp: u128,
+    },
+    MoveSelf {
+        pos: [f32; 3],
+    },
+    RequestChunk {
+        x: i32,
+        y: i32,
+        z: i32,
+    },
+    /// Asks for a chunk downsampled by one of [`crate::lod::LOD_SCALES`].
+    RequestLod {
+        x: i32,
+        y: i32,
+        z: i32,
+        scale: usize,
+    },
 }
 
 /// Packet from server to user
@@ -22,4 +41,6 @@ pub enum ServerPacket {
     UserLeave { name: String },
     MoveUser { name: String, pos: [f32; 3] },
     NewChunk { chunk: Chunk },
+    NewLod { lod: LodChunk },
+    WorldTime { clock: WorldClock },
 }
@@ -0,0 +1,224 @@
+use std::{collections::HashMap, env, io, ops::RangeInclusive, process, str::FromStr};
+
+use image::{Rgb, RgbImage};
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
+    server::{
+        chunk::{Chunk, ChunkGenerator},
+        config::ServerConfig,
+        generator,
+        save::WorldSave,
+    },
+};
+
+const CONFIG: &str = "server.toml";
+/// Rows of chunks generated or previewed when `--chunk-y` is not given, which covers the terrain
+/// of the default generator.
+const DEFAULT_CHUNK_Y: RangeInclusive<i32> = -2..=1;
+
+const USAGE: &str = "Usage:
+    worldgen generate <min x> <min z> <max x> <max z> [--chunk-y <min y> <max y>]
+        Generates the given region of chunks into the world save from server.toml.
+    worldgen preview <min x> <min z> <max x> <max z> <output.png> [seed] [--chunk-y ...]
+        Renders a top down map of the given region of chunks to a png.
+
+Coordinates are in chunks and bounds are inclusive. The generator and seed are read from
+server.toml; preview takes an optional seed that overrides it. --chunk-y sets the rows of chunks
+that are generated or looked at for the map, -2 to 1 by default, and should cover the terrain of
+the configured generator.";
+
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let chunk_y = match args.iter().position(|arg| arg == "--chunk-y") {
+        Some(i) if i + 2 < args.len() => {
+            let range = parse(&args[i + 1])..=parse(&args[i + 2]);
+            args.drain(i..i + 3);
+            range
+        }
+        Some(_) => usage(),
+        None => DEFAULT_CHUNK_Y,
+    };
+    if args.len() < 5 || chunk_y.is_empty() {
+        usage();
+    }
+
+    let min_x = parse(&args[1]);
+    let min_z = parse(&args[2]);
+    let max_x = parse(&args[3]);
+    let max_z = parse(&args[4]);
+    if min_x > max_x || min_z > max_z {
+        usage();
+    }
+
+    Block::register_defaults();
+    generator::register_defaults();
+    let mut config = ServerConfig::load(CONFIG)?;
+
+    match args[0].as_str() {
+        "generate" if args.len() == 5 => {
+            let mut gen = create_generator(&config)?;
+            let save = WorldSave::open(&config.world)?;
+            pregenerate(&mut *gen, &save, (min_x, min_z), (max_x, max_z), chunk_y)
+        }
+
+        "preview" if args.len() == 6 || args.len() == 7 => {
+            if let Some(seed) = args.get(6) {
+                config.seed = parse(seed);
+            }
+
+            let mut gen = create_generator(&config)?;
+            let image = preview(&mut *gen, (min_x, min_z), (max_x, max_z), chunk_y);
+            image.save(&args[5]).map_err(io::Error::other)?;
+            println!("Saved preview to {}", args[5]);
+            Ok(())
+        }
+
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse<T: FromStr>(arg: &str) -> T {
+    arg.parse().unwrap_or_else(|_| {
+        eprintln!("Invalid number {}", arg);
+        usage()
+    })
+}
+
+fn create_generator(config: &ServerConfig) -> io::Result<Box<dyn ChunkGenerator>> {
+    generator::create(
+        &config.generator.name,
+        config.seed,
+        &config.generator.options,
+    )
+}
+
+fn pregenerate(
+    gen: &mut dyn ChunkGenerator,
+    save: &WorldSave,
+    (min_x, min_z): (i32, i32),
+    (max_x, max_z): (i32, i32),
+    chunk_y: RangeInclusive<i32>,
+) -> io::Result<()> {
+    let total = (max_x - min_x + 1) * (max_z - min_z + 1) * (chunk_y.end() - chunk_y.start() + 1);
+    let mut generated = 0;
+    let mut skipped = 0;
+
+    for x in min_x..=max_x {
+        for z in min_z..=max_z {
+            for y in chunk_y.clone() {
+                if save.contains(x, y, z) {
+                    skipped += 1;
+                    continue;
+                }
+
+                save.store(&Chunk::new(x, y, z, gen))?;
+                generated += 1;
+            }
+        }
+
+        println!("{}/{} chunks done", generated + skipped, total);
+    }
+
+    println!(
+        "Generated {} chunks, skipped {} already saved chunks",
+        generated, skipped
+    );
+    Ok(())
+}
+
+fn preview(
+    gen: &mut dyn ChunkGenerator,
+    (min_x, min_z): (i32, i32),
+    (max_x, max_z): (i32, i32),
+    chunk_y: RangeInclusive<i32>,
+) -> RgbImage {
+    let width = (max_x - min_x + 1) as u32 * CHUNK_SIZE as u32;
+    let height = (max_z - min_z + 1) as u32 * CHUNK_SIZE as u32;
+    let mut image = RgbImage::new(width, height);
+    let mut colours = HashMap::new();
+
+    for chunk_x in min_x..=max_x {
+        for chunk_z in min_z..=max_z {
+            let column: Vec<_> = chunk_y
+                .clone()
+                .rev()
+                .map(|y| (y, gen.generate(chunk_x, y, chunk_z)))
+                .collect();
+
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let top = column.iter().find_map(|(chunk_y, blocks)| {
+                        (0..CHUNK_SIZE).rev().find_map(|y| {
+                            let block = blocks[x][y][z];
+                            if block == Block::air() {
+                                None
+                            } else {
+                                Some((block, chunk_y * CHUNK_SIZE as i32 + y as i32))
+                            }
+                        })
+                    });
+
+                    let pixel = match top {
+                        Some((block, y)) => {
+                            let colour =
+                                *colours.entry(block).or_insert_with(|| block_colour(block));
+                            shade(colour, y)
+                        }
+                        None => Rgb([0, 0, 0]),
+                    };
+
+                    image.put_pixel(
+                        (chunk_x - min_x) as u32 * CHUNK_SIZE as u32 + x as u32,
+                        (chunk_z - min_z) as u32 * CHUNK_SIZE as u32 + z as u32,
+                        pixel,
+                    );
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/// Average colour of a block's top texture.
+fn block_colour(block: Block) -> [f32; 3] {
+    let texture = block
+        .get_texture_asset(FaceDirection::Up)
+        .and_then(|asset| image::open(asset).ok());
+    let texture = match texture {
+        Some(texture) => texture.into_rgba8(),
+        None => return [1.0, 0.0, 1.0],
+    };
+
+    let mut sum = [0.0; 3];
+    let mut count = 0.0;
+    for pixel in texture.pixels() {
+        let alpha = pixel[3] as f32 / 255.0;
+        for (sum, &channel) in sum.iter_mut().zip(pixel.0.iter()) {
+            *sum += channel as f32 / 255.0 * alpha;
+        }
+        count += alpha;
+    }
+
+    if count > 0.0 {
+        [sum[0] / count, sum[1] / count, sum[2] / count]
+    } else {
+        [0.0; 3]
+    }
+}
+
+/// Darkens low blocks and lightens high blocks so that height is visible on the map.
+fn shade(colour: [f32; 3], y: i32) -> Rgb<u8> {
+    let factor = (1.0 + y as f32 * 0.03).clamp(0.3, 1.5);
+    Rgb([
+        (colour[0] * factor * 255.0).min(255.0) as u8,
+        (colour[1] * factor * 255.0).min(255.0) as u8,
+        (colour[2] * factor * 255.0).min(255.0) as u8,
+    ])
+}
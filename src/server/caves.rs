@@ -0,0 +1,79 @@
+use noise::{Fbm, NoiseFn};
+
+use super::super::blocks::Block;
+use super::chunk::perlin;
+
+/// World y level (in blocks) at and below which carved out space is flooded with lava.
+pub const LAVA_LEVEL: i32 = -12;
+
+const TUNNEL_SCALE: f64 = 1.0 / 24.0;
+const TUNNEL_WIDTH: f64 = 0.08;
+const RAVINE_SCALE: f64 = 1.0 / 96.0;
+const RAVINE_WIDTH: f64 = 0.025;
+const RAVINE_DEPTH_SCALE: f64 = 1.0 / 48.0;
+
+/// Carves caves and ravines out of terrain.
+///
+/// Every decision is made from world coordinates only, so carving is deterministic per seed and
+/// lines up across chunk borders regardless of which chunk is generated first.
+pub struct CaveCarver {
+    tunnels_a: Fbm,
+    tunnels_b: Fbm,
+    ravines: Fbm,
+    ravine_depth: Fbm,
+}
+
+impl CaveCarver {
+    pub fn new(seed: u32) -> CaveCarver {
+        CaveCarver {
+            tunnels_a: perlin(seed.wrapping_add(1)),
+            tunnels_b: perlin(seed.wrapping_add(2)),
+            ravines: perlin(seed.wrapping_add(3)),
+            ravine_depth: perlin(seed.wrapping_add(4)),
+        }
+    }
+
+    /// Returns whether the block at the given world coordinates is carved out. `surface` is the
+    /// world y level of the terrain surface in this column.
+    pub fn is_carved(&self, x: i32, y: i32, z: i32, surface: i32) -> bool {
+        self.is_tunnel(x, y, z, surface) || self.is_ravine(x, y, z, surface)
+    }
+
+    /// Returns the block that fills carved out space at the given world y level.
+    pub fn fill(&self, y: i32) -> Block {
+        if y <= LAVA_LEVEL {
+            Block::get("lava").unwrap_or_else(Block::air)
+        } else {
+            Block::air()
+        }
+    }
+
+    // Tunnels are where the zero surfaces of two independent noise fields intersect, which gives
+    // long winding worms that branch into each other instead of isolated blobs.
+    fn is_tunnel(&self, x: i32, y: i32, z: i32, surface: i32) -> bool {
+        if y > surface - 3 {
+            return false;
+        }
+
+        let coords = [
+            x as f64 * TUNNEL_SCALE,
+            y as f64 * TUNNEL_SCALE * 2.0,
+            z as f64 * TUNNEL_SCALE,
+        ];
+        self.tunnels_a.get(coords).abs() < TUNNEL_WIDTH
+            && self.tunnels_b.get(coords).abs() < TUNNEL_WIDTH
+    }
+
+    // Ravines follow the zero line of a 2D noise field and cut from the surface down to a depth
+    // that varies along their length.
+    fn is_ravine(&self, x: i32, y: i32, z: i32, surface: i32) -> bool {
+        let coords = [x as f64 * RAVINE_SCALE, z as f64 * RAVINE_SCALE];
+        if self.ravines.get(coords).abs() >= RAVINE_WIDTH {
+            return false;
+        }
+
+        let depth_coords = [x as f64 * RAVINE_DEPTH_SCALE, z as f64 * RAVINE_DEPTH_SCALE];
+        let depth = 12.0 + (self.ravine_depth.get(depth_coords) + 1.0) * 8.0;
+        y <= surface && y as f64 > surface as f64 - depth
+    }
+}
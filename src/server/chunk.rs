@@ -1,10 +1,15 @@
-use noise::{NoiseFn, Perlin, Seedable};
+use noise::{Fbm, MultiFractal, NoiseFn, Seedable};
 use serde::{Deserialize, Serialize};
 
 use super::super::blocks::{Block, CHUNK_SIZE};
+use super::super::coords::chunk_to_block;
+use super::caves::CaveCarver;
+use super::ores::OreTable;
 
-pub trait ChunkGenerator: Default {
-    fn from_seed(seed: u32) -> Self;
+pub trait ChunkGenerator: Send {
+    fn from_seed(seed: u32) -> Self
+    where
+        Self: Sized;
 
     fn generate(
         &mut self,
@@ -14,12 +19,42 @@ pub trait ChunkGenerator: Default {
     ) -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>;
 }
 
-#[derive(Default)]
-pub struct PerlinChunkGenerator(Perlin);
+/// Returns Perlin noise with the given seed.
+///
+/// noise 0.7 exports two generators named `Perlin` through glob re-exports, so that name is
+/// ambiguous. A single octave of [`Fbm`] is built from the same generator and returns exactly its
+/// values, so it stands in for it.
+pub(crate) fn perlin(seed: u32) -> Fbm {
+    Fbm::new().set_octaves(1).set_seed(seed)
+}
+
+pub struct PerlinChunkGenerator {
+    seed: u32,
+    noise: Fbm,
+    carver: CaveCarver,
+    ores: OreTable,
+}
+
+impl PerlinChunkGenerator {
+    pub fn with_ores(seed: u32, ores: OreTable) -> Self {
+        PerlinChunkGenerator {
+            seed,
+            noise: perlin(seed),
+            carver: CaveCarver::new(seed),
+            ores,
+        }
+    }
+}
+
+impl Default for PerlinChunkGenerator {
+    fn default() -> Self {
+        PerlinChunkGenerator::from_seed(Fbm::DEFAULT_SEED)
+    }
+}
 
 impl ChunkGenerator for PerlinChunkGenerator {
     fn from_seed(seed: u32) -> Self {
-        PerlinChunkGenerator(Perlin::new().set_seed(seed))
+        PerlinChunkGenerator::with_ores(seed, OreTable::default())
     }
 
     fn generate(
@@ -30,23 +65,25 @@ impl ChunkGenerator for PerlinChunkGenerator {
     ) -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]> {
         let mut blocks = Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
 
-        let chunk_x = chunk_x as f64;
-        let chunk_y = chunk_y as f64;
-        let chunk_z = chunk_z as f64;
         for (x, square) in blocks.iter_mut().enumerate() {
-            let x = x as f64;
             for (y, line) in square.iter_mut().enumerate() {
-                let y = y as f64;
                 for (z, block) in line.iter_mut().enumerate() {
-                    let z = z as f64;
+                    let [world_x, world_y, world_z] =
+                        chunk_to_block((chunk_x, chunk_y, chunk_z), (x, y, z));
                     let coords = [
-                        (chunk_x * 16.0 + x) / 20.0,
-                        (chunk_y * 16.0 + y) / 20.0,
-                        (chunk_z * 16.0 + z) / 20.0,
+                        world_x as f64 / 20.0,
+                        world_y as f64 / 20.0,
+                        world_z as f64 / 20.0,
                     ];
-                    let height = self.0.get([coords[0], coords[2]]) / 3.0;
+                    let height = self.noise.get([coords[0], coords[2]]) / 3.0;
 
                     if coords[1] <= height {
+                        let surface = (height * 20.0).floor() as i32;
+                        if self.carver.is_carved(world_x, world_y, world_z, surface) {
+                            *block = self.carver.fill(world_y);
+                            continue;
+                        }
+
                         if coords[1] + 1.0 / 20.0 > height {
                             *block = Block::get("grass").unwrap_or_else(Block::air);
                         } else if coords[1] >= height - 4.0 / 20.0 {
@@ -54,17 +91,13 @@ impl ChunkGenerator for PerlinChunkGenerator {
                         } else {
                             *block = Block::get("stone").unwrap_or_else(Block::air);
                         }
-
-                        if self.0.get(coords) > 0.65
-                            && *block == Block::get("stone").unwrap_or_else(Block::invalid)
-                        {
-                            *block = Block::get("coal_ore").unwrap_or_else(Block::air);
-                        }
                     }
                 }
             }
         }
 
+        self.ores
+            .populate(self.seed, chunk_x, chunk_y, chunk_z, &mut blocks);
         blocks
     }
 }
@@ -80,7 +113,7 @@ pub struct Chunk {
 impl Chunk {
     pub fn new<G>(chunk_x: i32, chunk_y: i32, chunk_z: i32, gen: &mut G) -> Chunk
     where
-        G: ChunkGenerator,
+        G: ChunkGenerator + ?Sized,
     {
         let blocks = gen.generate(chunk_x, chunk_y, chunk_z);
 
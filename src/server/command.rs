@@ -0,0 +1,74 @@
+//! Commands typed into the server console.
+
+use crate::clock::WorldClock;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// `time`: prints the world time.
+    QueryTime,
+    /// `time set <ticks|sunrise|noon|sunset|midnight>`: moves the clock to a time of the current
+    /// day, or to an absolute tick.
+    SetTime(TimeOfDay),
+    /// `time pause`: stops the clock.
+    PauseTime,
+    /// `time resume`: starts the clock again.
+    ResumeTime,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Ticks(u64),
+    /// A fraction of the current day, in quarters from sunrise.
+    Quarter(u64),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["time"] => Ok(Command::QueryTime),
+            ["time", "pause"] => Ok(Command::PauseTime),
+            ["time", "resume"] => Ok(Command::ResumeTime),
+            ["time", "set", time] => {
+                let time = match *time {
+                    "sunrise" => TimeOfDay::Quarter(0),
+                    "noon" => TimeOfDay::Quarter(1),
+                    "sunset" => TimeOfDay::Quarter(2),
+                    "midnight" => TimeOfDay::Quarter(3),
+                    ticks => TimeOfDay::Ticks(
+                        ticks
+                            .parse()
+                            .map_err(|_| format!("Invalid time: {}", ticks))?,
+                    ),
+                };
+                Ok(Command::SetTime(time))
+            }
+            ["time", ..] => Err(String::from(
+                "Usage: time [pause|resume|set <ticks|sunrise|noon|sunset|midnight>]",
+            )),
+            [] => Err(String::from("Empty command")),
+            [command, ..] => Err(format!("Unknown command: {}", command)),
+        }
+    }
+
+    /// Runs the command on the world clock, returning the message to print.
+    pub fn apply(&self, clock: &mut WorldClock) -> String {
+        match *self {
+            Command::QueryTime => (),
+            Command::SetTime(TimeOfDay::Ticks(ticks)) => clock.ticks = ticks,
+            Command::SetTime(TimeOfDay::Quarter(quarter)) => {
+                clock.ticks = clock.day() * clock.day_length + clock.day_length * quarter / 4;
+            }
+            Command::PauseTime => clock.paused = true,
+            Command::ResumeTime => clock.paused = false,
+        }
+
+        format!(
+            "Day {}, tick {} of {}{}",
+            clock.day(),
+            clock.ticks % clock.day_length,
+            clock.day_length,
+            if clock.paused { " (paused)" } else { "" }
+        )
+    }
+}
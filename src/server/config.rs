@@ -0,0 +1,65 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use toml::value::Table;
+
+use crate::clock::DEFAULT_DAY_LENGTH;
+
+/// Server settings, read from `server.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Address the server listens on.
+    pub bind: String,
+    /// World seed passed to the generator.
+    pub seed: u32,
+    /// Directory generated chunks are saved to.
+    pub world: String,
+    /// Ticks in a full day and night, see [`crate::clock`].
+    pub day_length: u64,
+    pub generator: GeneratorConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: String::from("127.0.0.1:6429"),
+            seed: 0,
+            world: String::from("world"),
+            day_length: DEFAULT_DAY_LENGTH,
+            generator: GeneratorConfig::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Loads the config from the given toml file, falling back to the defaults if it does not
+    /// exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ServerConfig> {
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                toml::from_str(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ServerConfig::default()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Selects the world generator by its registered name. Every other key in the `[generator]`
+/// table is handed to the generator as its options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub options: Table,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            name: String::from("perlin"),
+            options: Table::new(),
+        }
+    }
+}
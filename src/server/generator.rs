@@ -0,0 +1,252 @@
+use std::{collections::HashMap, io, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+use toml::value::Table;
+
+use super::super::blocks::{Block, CHUNK_SIZE};
+use super::super::coords::chunk_to_block;
+use super::chunk::{ChunkGenerator, PerlinChunkGenerator};
+use super::ores::OreTable;
+
+/// Builds a chunk generator from a seed and the generator specific options of the server config.
+pub type GeneratorFactory =
+    Box<dyn Fn(u32, &Table) -> io::Result<Box<dyn ChunkGenerator>> + Send + Sync>;
+
+lazy_static! {
+    static ref GENERATORS: RwLock<HashMap<String, GeneratorFactory>> = RwLock::new(HashMap::new());
+}
+
+/// Registers a generator under the given name, replacing any generator already registered with
+/// that name. Registered generators can be selected by name from the server config.
+pub fn register<F>(name: &str, factory: F)
+where
+    F: Fn(u32, &Table) -> io::Result<Box<dyn ChunkGenerator>> + Send + Sync + 'static,
+{
+    GENERATORS
+        .write()
+        .unwrap()
+        .insert(String::from(name), Box::new(factory));
+}
+
+/// Registers the built in generators: `perlin`, `flat`, `void` and `debug`.
+pub fn register_defaults() {
+    register("perlin", |seed, options| {
+        let ores = match options.get("ores").and_then(|v| v.as_str()) {
+            Some(path) => OreTable::load(path)?,
+            None => OreTable::default(),
+        };
+        Ok(Box::new(PerlinChunkGenerator::with_ores(seed, ores)))
+    });
+    register("flat", |_, options| {
+        Ok(Box::new(FlatChunkGenerator::from_options(options)?))
+    });
+    register("void", |_, _| Ok(Box::new(VoidChunkGenerator)));
+    register("debug", |_, _| Ok(Box::new(DebugChunkGenerator::new())));
+}
+
+/// Creates the generator registered under the given name.
+pub fn create(name: &str, seed: u32, options: &Table) -> io::Result<Box<dyn ChunkGenerator>> {
+    match GENERATORS.read().unwrap().get(name) {
+        Some(factory) => factory(seed, options),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no world generator named {}", name),
+        )),
+    }
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// A layer of a superflat world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlatLayer {
+    /// Registered name of the block the layer is made of.
+    pub block: String,
+    /// Thickness of the layer in blocks.
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct FlatOptions {
+    bottom: i32,
+    layers: Vec<FlatLayer>,
+}
+
+impl Default for FlatOptions {
+    fn default() -> Self {
+        FlatOptions {
+            bottom: -6,
+            layers: vec![
+                FlatLayer {
+                    block: String::from("stone"),
+                    height: 3,
+                },
+                FlatLayer {
+                    block: String::from("dirt"),
+                    height: 2,
+                },
+                FlatLayer {
+                    block: String::from("grass"),
+                    height: 1,
+                },
+            ],
+        }
+    }
+}
+
+/// Generates a superflat world: a stack of layers starting at a fixed y level, with air above and
+/// below.
+pub struct FlatChunkGenerator {
+    bottom: i32,
+    column: Vec<Block>,
+}
+
+impl FlatChunkGenerator {
+    /// Creates a generator whose lowest layer starts at world y level `bottom`. Layers are listed
+    /// from the bottom up.
+    pub fn new(bottom: i32, layers: &[FlatLayer]) -> io::Result<FlatChunkGenerator> {
+        let mut column = vec![];
+        for layer in layers {
+            let block = Block::get(&layer.block)
+                .ok_or_else(|| invalid_data(format!("unknown block {}", layer.block)))?;
+            column.extend((0..layer.height).map(|_| block));
+        }
+
+        Ok(FlatChunkGenerator { bottom, column })
+    }
+
+    /// Creates a generator from the `bottom` and `layers` keys of a generator config.
+    pub fn from_options(options: &Table) -> io::Result<FlatChunkGenerator> {
+        let options: FlatOptions = toml::Value::Table(options.clone())
+            .try_into()
+            .map_err(invalid_data)?;
+        FlatChunkGenerator::new(options.bottom, &options.layers)
+    }
+}
+
+impl ChunkGenerator for FlatChunkGenerator {
+    fn from_seed(_: u32) -> Self {
+        // The default layers are built in, so like the Perlin generator, blocks that have not
+        // been registered are left out as air rather than failing.
+        let options = FlatOptions::default();
+        let column = options
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                let block = Block::get(&layer.block).unwrap_or_else(Block::air);
+                (0..layer.height).map(move |_| block)
+            })
+            .collect();
+        FlatChunkGenerator {
+            bottom: options.bottom,
+            column,
+        }
+    }
+
+    fn generate(
+        &mut self,
+        _chunk_x: i32,
+        chunk_y: i32,
+        _chunk_z: i32,
+    ) -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]> {
+        let mut blocks = Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+
+        for square in blocks.iter_mut() {
+            for (y, line) in square.iter_mut().enumerate() {
+                let layer = chunk_y * CHUNK_SIZE as i32 + y as i32 - self.bottom;
+                if layer < 0 {
+                    continue;
+                }
+
+                if let Some(&block) = self.column.get(layer as usize) {
+                    for block_ref in line.iter_mut() {
+                        *block_ref = block;
+                    }
+                }
+            }
+        }
+
+        blocks
+    }
+}
+
+/// Generates nothing but air.
+pub struct VoidChunkGenerator;
+
+impl ChunkGenerator for VoidChunkGenerator {
+    fn from_seed(_: u32) -> Self {
+        VoidChunkGenerator
+    }
+
+    fn generate(
+        &mut self,
+        _chunk_x: i32,
+        _chunk_y: i32,
+        _chunk_z: i32,
+    ) -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]> {
+        Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE])
+    }
+}
+
+/// Lays out every registered block in a grid on world y level 0, one block apart, so that each
+/// block's textures can be inspected.
+pub struct DebugChunkGenerator {
+    blocks: Vec<Block>,
+    width: usize,
+}
+
+impl DebugChunkGenerator {
+    pub fn new() -> DebugChunkGenerator {
+        let blocks = Block::registered();
+        let width = (blocks.len() as f64).sqrt().ceil().max(1.0) as usize;
+        DebugChunkGenerator { blocks, width }
+    }
+}
+
+impl Default for DebugChunkGenerator {
+    fn default() -> Self {
+        DebugChunkGenerator::new()
+    }
+}
+
+impl ChunkGenerator for DebugChunkGenerator {
+    fn from_seed(_: u32) -> Self {
+        DebugChunkGenerator::new()
+    }
+
+    fn generate(
+        &mut self,
+        chunk_x: i32,
+        chunk_y: i32,
+        chunk_z: i32,
+    ) -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]> {
+        let mut blocks = Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+        if chunk_y != 0 {
+            return blocks;
+        }
+
+        for (x, square) in blocks.iter_mut().enumerate() {
+            for (z, block) in square[0].iter_mut().enumerate() {
+                let [world_x, _, world_z] = chunk_to_block((chunk_x, 0, chunk_z), (x, 0, z));
+                if world_x < 0 || world_z < 0 || world_x % 2 != 0 || world_z % 2 != 0 {
+                    continue;
+                }
+
+                let (column, row) = (world_x as usize / 2, world_z as usize / 2);
+                if column < self.width {
+                    if let Some(&debug) = self.blocks.get(row * self.width + column) {
+                        *block = debug;
+                    }
+                }
+            }
+        }
+
+        blocks
+    }
+}
@@ -1,2 +1,9 @@
+pub mod caves;
 pub mod chunk;
+pub mod command;
+pub mod config;
+pub mod generator;
+pub mod net;
+pub mod ores;
 pub mod player;
+pub mod save;
@@ -0,0 +1,426 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io::{self, BufRead},
+    net::SocketAddr,
+    sync::Arc,
+    thread,
+};
+
+use tokio::{
+    net::UdpSocket,
+    sync::{mpsc, Mutex},
+    time,
+};
+
+use super::{
+    chunk::{Chunk, ChunkGenerator},
+    command::Command,
+    config::ServerConfig,
+    generator,
+    player::Player,
+    save::WorldSave,
+};
+use crate::{
+    clock::{WorldClock, TICK},
+    lod::{LodChunk, LOD_SCALES},
+    packet::{ServerPacket, UserPacket},
+};
+
+/// Ticks between broadcasts of the world clock. Clients keep the clock running in between.
+const CLOCK_BROADCAST_TICKS: u64 = 20;
+
+/// Runs the server until it is interrupted with ctrl-c.
+///
+/// Blocks and generators must be registered before calling this; the generator named in the
+/// config is looked up in the generator registry.
+pub async fn run(config: ServerConfig) -> io::Result<()> {
+    let gen = generator::create(
+        &config.generator.name,
+        config.seed,
+        &config.generator.options,
+    )?;
+    let save = WorldSave::open(&config.world)?;
+
+    let run = Arc::new(Mutex::new(true));
+    let run2 = run.clone();
+    ctrlc::set_handler(move || {
+        *run2.blocking_lock() = false;
+    })
+    .unwrap();
+
+    let sock = Arc::new(UdpSocket::bind(&config.bind).await.unwrap());
+    let players = Arc::new(Mutex::new(HashMap::new()));
+    let player_names = HashSet::new();
+    let chunks = Arc::new(Mutex::new(HashMap::new()));
+    let clock = Arc::new(Mutex::new(WorldClock::new(config.day_length)));
+    let (packet_tx, packet_rx) = mpsc::channel(128);
+    let (chunk_tx, chunk_rx) = mpsc::channel(128);
+    let (command_tx, command_rx) = mpsc::channel(16);
+
+    println!("Server started");
+    tokio::spawn(transmitting(packet_rx, sock.clone()));
+    tokio::spawn(receiving(
+        packet_tx.clone(),
+        sock.clone(),
+        players.clone(),
+        player_names,
+        chunk_tx,
+        clock.clone(),
+    ));
+    tokio::spawn(chunk_generator(
+        packet_tx.clone(),
+        chunk_rx,
+        chunks.clone(),
+        gen,
+        save,
+    ));
+    tokio::spawn(ticking(
+        packet_tx.clone(),
+        players.clone(),
+        clock.clone(),
+        command_rx,
+    ));
+    thread::spawn(move || console(command_tx));
+
+    while *run.lock().await {}
+
+    println!("Closing server");
+    for (_, player) in players.lock().await.iter() {
+        packet_tx
+            .send((
+                player.addr,
+                ServerPacket::Disconnected {
+                    reason: String::from("Server closed"),
+                },
+            ))
+            .await
+            .unwrap();
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::type_complexity)]
+async fn chunk_generator(
+    tx: mpsc::Sender<(SocketAddr, ServerPacket)>,
+    mut rx: mpsc::Receiver<(SocketAddr, UserPacket)>,
+    chunks: Arc<Mutex<HashMap<(i32, i32, i32), Chunk>>>,
+    mut gen: Box<dyn ChunkGenerator>,
+    save: WorldSave,
+) {
+    while let Some((addr, packet)) = rx.recv().await {
+        match packet {
+            UserPacket::ConnectionRequest { .. } => (),
+            UserPacket::Disconnect => (),
+            UserPacket::Ping { .. } => (),
+            UserPacket::MoveSelf { .. } => (),
+
+            UserPacket::RequestChunk { x, y, z } => {
+                let chunk = loaded_chunk(&chunks, &mut *gen, &save, (x, y, z)).await;
+                tx.send((addr, ServerPacket::NewChunk { chunk }))
+                    .await
+                    .unwrap();
+            }
+
+            UserPacket::RequestLod { x, y, z, scale } => {
+                if !LOD_SCALES.contains(&scale) {
+                    continue;
+                }
+                let lod = lod_chunk(&chunks, &mut *gen, &save, (x, y, z), scale).await;
+                tx.send((addr, ServerPacket::NewLod { lod })).await.unwrap();
+            }
+        }
+    }
+}
+
+/// Returns the chunk at the given coordinates, loading it from the save or generating it if it
+/// is not in memory yet.
+async fn loaded_chunk(
+    chunks: &Mutex<HashMap<(i32, i32, i32), Chunk>>,
+    gen: &mut dyn ChunkGenerator,
+    save: &WorldSave,
+    coords: (i32, i32, i32),
+) -> Chunk {
+    let (x, y, z) = coords;
+    match chunks.lock().await.entry(coords) {
+        Entry::Occupied(e) => e.get().clone(),
+        Entry::Vacant(e) => {
+            let chunk = match save.load(x, y, z) {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => {
+                    let chunk = Chunk::new(x, y, z, gen);
+                    if let Err(err) = save.store(&chunk) {
+                        println!("Failed to save chunk {:?}: {}", coords, err);
+                    }
+                    chunk
+                }
+                Err(err) => {
+                    println!("Failed to load chunk {:?}: {}", coords, err);
+                    Chunk::new(x, y, z, gen)
+                }
+            };
+            e.insert(chunk).clone()
+        }
+    }
+}
+
+/// Returns the downsampled version of the chunk at the given coordinates.
+///
+/// Chunks in memory or in the save are used as they are. Any other chunk is generated only to be
+/// downsampled, and is neither kept nor saved, so distant rings of chunks a player may never
+/// visit do not fill up memory or the save.
+pub async fn lod_chunk(
+    chunks: &Mutex<HashMap<(i32, i32, i32), Chunk>>,
+    gen: &mut dyn ChunkGenerator,
+    save: &WorldSave,
+    coords: (i32, i32, i32),
+    scale: usize,
+) -> LodChunk {
+    if let Some(chunk) = chunks.lock().await.get(&coords) {
+        return LodChunk::new(coords, chunk.get_blocks(), scale);
+    }
+
+    let (x, y, z) = coords;
+    let blocks = match save.load(x, y, z) {
+        Ok(Some(chunk)) => return LodChunk::new(coords, chunk.get_blocks(), scale),
+        Ok(None) => gen.generate(x, y, z),
+        Err(err) => {
+            println!("Failed to load chunk {:?}: {}", coords, err);
+            gen.generate(x, y, z)
+        }
+    };
+    LodChunk::new(coords, &blocks, scale)
+}
+
+/// Reads commands from the console, one per line.
+fn console(tx: mpsc::Sender<Command>) {
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Command::parse(&line) {
+            Ok(command) => {
+                if tx.blocking_send(command).is_err() {
+                    return;
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+/// Runs the world clock and console commands, sending the clock to every player whenever it is
+/// changed by a command and every [`CLOCK_BROADCAST_TICKS`].
+async fn ticking(
+    tx: mpsc::Sender<(SocketAddr, ServerPacket)>,
+    players: Arc<Mutex<HashMap<SocketAddr, Player>>>,
+    clock: Arc<Mutex<WorldClock>>,
+    mut commands: mpsc::Receiver<Command>,
+) {
+    let mut interval = time::interval(TICK);
+    let mut ticks = 0u64;
+
+    loop {
+        let broadcast = tokio::select! {
+            _ = interval.tick() => {
+                clock.lock().await.advance(1);
+                ticks += 1;
+                ticks.is_multiple_of(CLOCK_BROADCAST_TICKS)
+            }
+
+            Some(command) = commands.recv() => {
+                println!("{}", command.apply(&mut *clock.lock().await));
+                true
+            }
+        };
+
+        if broadcast {
+            let clock = *clock.lock().await;
+            for player in players.lock().await.values() {
+                tx.send((player.addr, ServerPacket::WorldTime { clock }))
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+}
+
+async fn transmitting(
+    mut rx: mpsc::Receiver<(SocketAddr, ServerPacket)>,
+    sock: Arc<UdpSocket>,
+) -> io::Result<()> {
+    while let Some((addr, packet)) = rx.recv().await {
+        let buf = bincode::serialize(&packet).unwrap();
+        let send = sock.send_to(&buf, addr).await;
+        if let Err(err) = send {
+            println!("{:?} {}", err, buf.len());
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+async fn receiving(
+    packet_tx: mpsc::Sender<(SocketAddr, ServerPacket)>,
+    sock: Arc<UdpSocket>,
+    players: Arc<Mutex<HashMap<SocketAddr, Player>>>,
+    mut player_names: HashSet<String>,
+    chunk_tx: mpsc::Sender<(SocketAddr, UserPacket)>,
+    clock: Arc<Mutex<WorldClock>>,
+) -> io::Result<()> {
+    let mut buf = Box::new([0; 4096]);
+
+    loop {
+        let (len, addr) = sock.recv_from(&mut *buf).await?;
+        let packet: UserPacket = bincode::deserialize(&buf[..len]).unwrap();
+
+        match packet {
+            UserPacket::ConnectionRequest { name } => {
+                let mut players = players.lock().await;
+
+                if player_names.contains(&name) {
+                    println!("Duplicate connection for {} at address {}", name, addr);
+                    packet_tx
+                        .send((
+                            addr,
+                            ServerPacket::Disconnected {
+                                reason: format!("Player {} is already on the server!", name),
+                            },
+                        ))
+                        .await
+                        .unwrap();
+                } else if let Entry::Vacant(e) = players.entry(addr) {
+                    println!("Connection requested from {} at address {}", name, addr);
+
+                    packet_tx
+                        .send((addr, ServerPacket::ConnectionAccepted))
+                        .await
+                        .unwrap();
+                    packet_tx
+                        .send((
+                            addr,
+                            ServerPacket::WorldTime {
+                                clock: *clock.lock().await,
+                            },
+                        ))
+                        .await
+                        .unwrap();
+
+                    let position = [0.0, 0.0, 0.0];
+                    e.insert(Player {
+                        name: name.clone(),
+                        addr,
+                        position,
+                    });
+
+                    for (_, player) in players.iter() {
+                        if player.addr != addr {
+                            packet_tx
+                                .send((
+                                    player.addr,
+                                    ServerPacket::UserJoin {
+                                        name: name.clone(),
+                                        pos: position,
+                                    },
+                                ))
+                                .await
+                                .unwrap();
+                            packet_tx
+                                .send((
+                                    addr,
+                                    ServerPacket::UserJoin {
+                                        name: player.name.clone(),
+                                        pos: player.position,
+                                    },
+                                ))
+                                .await
+                                .unwrap();
+                        }
+                    }
+
+                    player_names.insert(name);
+                } else {
+                    println!("Duplicate connection for address {} by {}", addr, name);
+                    packet_tx
+                        .send((
+                            addr,
+                            ServerPacket::Disconnected {
+                                reason: String::from("Address already in use"),
+                            },
+                        ))
+                        .await
+                        .unwrap();
+                }
+            }
+
+            UserPacket::Disconnect => {
+                let mut players = players.lock().await;
+
+                if let Some(player) = players.remove(&addr) {
+                    println!(
+                        "Player {} at address {} disconnected from the server",
+                        player.name, addr
+                    );
+                    player_names.remove(&player.name);
+
+                    for (_, player2) in players.iter() {
+                        packet_tx
+                            .send((
+                                player2.addr,
+                                ServerPacket::UserLeave {
+                                    name: player.name.clone(),
+                                },
+                            ))
+                            .await
+                            .unwrap();
+                    }
+                }
+            }
+
+            UserPacket::Ping { timestamp } => {
+                if players.lock().await.contains_key(&addr) {
+                    packet_tx
+                        .send((addr, ServerPacket::Pong { timestamp }))
+                        .await
+                        .unwrap();
+                    println!("Ping! ({})", addr);
+                }
+            }
+
+            UserPacket::MoveSelf { pos } => {
+                let mut players = players.lock().await;
+                if let Some(mut player) = players.remove(&addr) {
+                    println!(
+                        "Player {} moved from {:?} to {:?}",
+                        player.name, player.position, pos
+                    );
+                    player.position = pos;
+
+                    for (_, player2) in players.iter() {
+                        packet_tx
+                            .send((
+                                player2.addr,
+                                ServerPacket::MoveUser {
+                                    name: player.name.clone(),
+                                    pos: player.position,
+                                },
+                            ))
+                            .await
+                            .unwrap();
+                    }
+
+                    players.insert(addr, player);
+                }
+            }
+            UserPacket::RequestChunk { .. } | UserPacket::RequestLod { .. } => {
+                chunk_tx.send((addr, packet)).await.unwrap()
+            }
+        }
+    }
+}
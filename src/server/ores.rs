@@ -0,0 +1,123 @@
+use std::{fs, io, path::Path};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::super::blocks::{Block, CHUNK_SIZE};
+use super::super::coords::chunk_to_block;
+
+const DEFAULT_ORES: &str = include_str!("../../assets/ores.toml");
+
+/// A single kind of ore and how it is distributed through the world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OreVein {
+    /// Registered name of the ore block.
+    pub block: String,
+    /// Lowest world y level a vein may start at.
+    pub min_y: i32,
+    /// Highest world y level a vein may start at.
+    pub max_y: i32,
+    /// Maximum number of blocks in a single vein.
+    pub vein_size: u32,
+    /// Number of veins attempted in each chunk.
+    pub veins_per_chunk: u32,
+}
+
+/// The table of ores placed by the world generator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OreTable {
+    pub ores: Vec<OreVein>,
+}
+
+impl Default for OreTable {
+    fn default() -> Self {
+        toml::from_str(DEFAULT_ORES).unwrap()
+    }
+}
+
+impl OreTable {
+    /// Loads an ore table from a toml file laid out like `assets/ores.toml`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<OreTable> {
+        let source = fs::read_to_string(path)?;
+        toml::from_str(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Places ore veins into the given chunk, replacing stone.
+    ///
+    /// Veins are seeded per chunk, and the veins of neighbouring chunks are replayed as well so
+    /// that a vein crossing a chunk border is placed on both sides of it.
+    pub fn populate(
+        &self,
+        seed: u32,
+        chunk_x: i32,
+        chunk_y: i32,
+        chunk_z: i32,
+        blocks: &mut [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    ) {
+        let stone = Block::get("stone").unwrap_or_else(Block::invalid);
+        let origin = chunk_to_block((chunk_x, chunk_y, chunk_z), (0, 0, 0));
+
+        for (index, ore) in self.ores.iter().enumerate() {
+            let block = match Block::get(&ore.block) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            for i in -1..=1 {
+                for j in -1..=1 {
+                    for k in -1..=1 {
+                        let source = (chunk_x + i, chunk_y + j, chunk_z + k);
+                        let mut rng = StdRng::seed_from_u64(vein_seed(seed, source, index));
+
+                        for _ in 0..ore.veins_per_chunk {
+                            let start = (
+                                rng.gen_range(0..CHUNK_SIZE),
+                                rng.gen_range(0..CHUNK_SIZE),
+                                rng.gen_range(0..CHUNK_SIZE),
+                            );
+                            let mut pos = chunk_to_block(source, start);
+                            let size = rng.gen_range(1..=ore.vein_size.max(1));
+                            if pos[1] < ore.min_y || pos[1] > ore.max_y {
+                                continue;
+                            }
+
+                            for _ in 0..size {
+                                let x = pos[0] - origin[0];
+                                let y = pos[1] - origin[1];
+                                let z = pos[2] - origin[2];
+                                if (0..CHUNK_SIZE as i32).contains(&x)
+                                    && (0..CHUNK_SIZE as i32).contains(&y)
+                                    && (0..CHUNK_SIZE as i32).contains(&z)
+                                {
+                                    let target = &mut blocks[x as usize][y as usize][z as usize];
+                                    if *target == stone {
+                                        *target = block;
+                                    }
+                                }
+
+                                let axis = rng.gen_range(0..3);
+                                pos[axis] += if rng.gen() { 1 } else { -1 };
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn vein_seed(seed: u32, (x, y, z): (i32, i32, i32), ore: usize) -> u64 {
+    let mut hash = seed as u64 ^ 0x9e37_79b9_7f4a_7c15;
+    for v in [
+        x as u32 as u64,
+        y as u32 as u64,
+        z as u32 as u64,
+        ore as u64,
+    ]
+    .iter()
+    {
+        hash = (hash ^ v).wrapping_mul(0x0100_0000_01b3);
+        hash ^= hash >> 29;
+    }
+    hash
+}
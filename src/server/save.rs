@@ -0,0 +1,56 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use super::chunk::Chunk;
+
+/// On disk storage for generated chunks, one bincode file per chunk.
+pub struct WorldSave {
+    dir: PathBuf,
+}
+
+impl WorldSave {
+    /// Opens the save in the given directory, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<WorldSave> {
+        fs::create_dir_all(&dir)?;
+        Ok(WorldSave {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn chunk_path(&self, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}_{}.chunk", chunk_x, chunk_y, chunk_z))
+    }
+
+    /// Returns whether the chunk has been saved.
+    pub fn contains(&self, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> bool {
+        self.chunk_path(chunk_x, chunk_y, chunk_z).is_file()
+    }
+
+    /// Loads a chunk, returning `None` if it has not been saved.
+    pub fn load(&self, chunk_x: i32, chunk_y: i32, chunk_z: i32) -> io::Result<Option<Chunk>> {
+        match fs::read(self.chunk_path(chunk_x, chunk_y, chunk_z)) {
+            Ok(buf) => bincode::deserialize(&buf)
+                .map(Some)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Saves a chunk, replacing any previously saved version of it.
+    pub fn store(&self, chunk: &Chunk) -> io::Result<()> {
+        let buf = bincode::serialize(chunk).map_err(io::Error::other)?;
+        fs::write(
+            self.chunk_path(
+                chunk.get_chunk_x(),
+                chunk.get_chunk_y(),
+                chunk.get_chunk_z(),
+            ),
+            buf,
+        )
+    }
+}
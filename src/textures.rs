@@ -0,0 +1,244 @@
+//! Loading block textures into the layers of a texture array.
+//!
+//! Textures are named by their asset path, like `assets/textures/PNG/Tiles/dirt.png`. If a
+//! resource pack directory is set, a file at the same path relative to `assets` inside of it
+//! (`<pack>/textures/PNG/Tiles/dirt.png`) replaces the default texture.
+//!
+//! Animated textures are vertical strips of square frames, which are loaded into consecutive
+//! layers.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use image::{Rgba, RgbaImage};
+
+/// Directory the default assets are loaded from.
+pub const ASSETS_DIR: &str = "assets";
+
+/// Every layer of a texture array, all with the same square, power of two size.
+pub struct TextureLayers {
+    pub size: u32,
+    /// Layer `0` is a placeholder for faces without a texture.
+    pub layers: Vec<RgbaImage>,
+}
+
+/// Builds the layers of a texture array, loading each texture once.
+pub struct TextureArrayBuilder {
+    resource_pack: Option<PathBuf>,
+    size: Option<(u32, PathBuf)>,
+    layers: Vec<RgbaImage>,
+    indices: HashMap<String, (u32, u32)>,
+}
+
+impl Default for TextureArrayBuilder {
+    fn default() -> Self {
+        TextureArrayBuilder::new()
+    }
+}
+
+impl TextureArrayBuilder {
+    pub fn new() -> TextureArrayBuilder {
+        TextureArrayBuilder {
+            resource_pack: None,
+            size: None,
+            layers: vec![],
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Loads textures from the given resource pack directory where it has them.
+    pub fn with_resource_pack<P: AsRef<Path>>(mut self, dir: P) -> TextureArrayBuilder {
+        self.resource_pack = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Returns the file the asset is loaded from, taking the resource pack into account.
+    pub fn resolve(&self, asset: &str) -> PathBuf {
+        if let Some(pack) = &self.resource_pack {
+            if let Ok(relative) = Path::new(asset).strip_prefix(ASSETS_DIR) {
+                let path = pack.join(relative);
+                if path.is_file() {
+                    return path;
+                }
+            }
+        }
+
+        PathBuf::from(asset)
+    }
+
+    /// Adds a texture, returning the layer it is in. Adding the same asset again returns the
+    /// layer it was first loaded into.
+    pub fn add(&mut self, asset: &str) -> io::Result<u32> {
+        self.load(asset, false).map(|(layer, _)| layer)
+    }
+
+    /// Adds an animated texture, returning the layer of its first frame and the number of frames.
+    /// The frames are stacked from top to bottom in the image.
+    pub fn add_animated(&mut self, asset: &str) -> io::Result<(u32, u32)> {
+        self.load(asset, true)
+    }
+
+    fn load(&mut self, asset: &str, animated: bool) -> io::Result<(u32, u32)> {
+        if let Some(&layers) = self.indices.get(asset) {
+            return Ok(layers);
+        }
+
+        let path = self.resolve(asset);
+        let texture = image::open(&path)
+            .map_err(|e| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("failed to load texture {}: {}", path.display(), e),
+                )
+            })?
+            .into_rgba8();
+
+        let (width, height) = texture.dimensions();
+        if animated {
+            if !width.is_power_of_two() || height == 0 || height % width != 0 {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "animated texture {} is {}x{}, animated textures must be a power of two \
+                         wide and a whole number of square frames high",
+                        path.display(),
+                        width,
+                        height
+                    ),
+                ));
+            }
+        } else if width != height || !width.is_power_of_two() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "texture {} is {}x{}, textures must be square with a power of two size",
+                    path.display(),
+                    width,
+                    height
+                ),
+            ));
+        }
+
+        match &self.size {
+            Some((size, first)) if *size != width => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "texture {} is {} pixels wide, but {} is {} pixels wide and every \
+                         texture must be the same size",
+                        path.display(),
+                        width,
+                        first.display(),
+                        size
+                    ),
+                ));
+            }
+            Some(_) => (),
+            None => self.size = Some((width, path)),
+        }
+
+        let first = self.layers.len() as u32 + 1;
+        let frames = height / width;
+        for frame in 0..frames {
+            let frame = image::imageops::crop_imm(&texture, 0, frame * width, width, width);
+            self.layers.push(frame.to_image());
+        }
+
+        self.indices.insert(asset.to_string(), (first, frames));
+        Ok((first, frames))
+    }
+
+    /// Returns the layers, with the colour of transparent pixels padded out from the opaque ones
+    /// so that filtering and mipmaps do not bleed black into the edges of cutout textures.
+    pub fn build(self) -> TextureLayers {
+        let size = self.size.map(|(size, _)| size).unwrap_or(16);
+        let mut layers = vec![missing_texture(size)];
+        layers.extend(self.layers.into_iter().map(|mut layer| {
+            pad_transparent(&mut layer);
+            layer
+        }));
+
+        TextureLayers { size, layers }
+    }
+}
+
+/// Packs a texture layer and its animation into the texture word of a chunk instance.
+///
+/// 0..15  = layer of the first frame
+/// 16..23 = number of frames - 1
+/// 24..31 = ticks each frame is shown for
+pub fn pack_texture(layer: u32, frames: u32, frame_ticks: u32) -> io::Result<u32> {
+    if layer > 0xffff || frames == 0 || frames > 0x100 || frame_ticks > 0xff {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "texture layer {} with {} frames of {} ticks does not fit in a texture word",
+                layer, frames, frame_ticks
+            ),
+        ));
+    }
+
+    Ok(layer | (frames - 1) << 16 | frame_ticks << 24)
+}
+
+/// A magenta and black checkerboard for faces without a texture.
+fn missing_texture(size: u32) -> RgbaImage {
+    let half = (size / 2).max(1);
+    RgbaImage::from_fn(size, size, |x, y| {
+        if (x / half + y / half) & 1 == 0 {
+            Rgba([255, 0, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+/// Gives every fully transparent pixel the average colour of its non transparent neighbours,
+/// growing outwards from the opaque pixels until the whole texture is covered. The texture is
+/// treated as wrapping around, like it does when it is drawn.
+pub fn pad_transparent(texture: &mut RgbaImage) {
+    let (width, height) = texture.dimensions();
+    let mut filled: Vec<bool> = texture.pixels().map(|p| p[3] != 0).collect();
+    if !filled.iter().any(|&f| f) {
+        return;
+    }
+
+    loop {
+        let mut next = filled.clone();
+        let mut changed = false;
+
+        for y in 0..height {
+            for x in 0..width {
+                if filled[(y * width + x) as usize] {
+                    continue;
+                }
+
+                let neighbours: Vec<Rgba<u8>> = [(1, 0), (width - 1, 0), (0, 1), (0, height - 1)]
+                    .iter()
+                    .map(|&(dx, dy)| ((x + dx) % width, (y + dy) % height))
+                    .filter(|&(nx, ny)| filled[(ny * width + nx) as usize])
+                    .map(|(nx, ny)| *texture.get_pixel(nx, ny))
+                    .collect();
+                if neighbours.is_empty() {
+                    continue;
+                }
+
+                let pixel = texture.get_pixel_mut(x, y);
+                for (i, channel) in pixel.0.iter_mut().take(3).enumerate() {
+                    let sum: u32 = neighbours.iter().map(|n| n[i] as u32).sum();
+                    *channel = (sum / neighbours.len() as u32) as u8;
+                }
+                next[(y * width + x) as usize] = true;
+                changed = true;
+            }
+        }
+
+        filled = next;
+        if !changed {
+            break;
+        }
+    }
+}
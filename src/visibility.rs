@@ -0,0 +1,169 @@
+//! Occlusion culling with a chunk visibility graph.
+//!
+//! When a chunk is meshed, [`Visibility::compute`] finds which of its six faces can see each
+//! other through blocks that let light through. Each frame [`visible_chunks`] then flood fills
+//! from the camera's chunk, only leaving a chunk through a face that is connected to the face it
+//! was entered through, so that caves and chunks behind solid stone are not drawn.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::blocks::{Block, FaceDirection, CHUNK_SIZE};
+
+/// Which pairs of a chunk's faces are connected through open space.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Visibility(u64);
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility::OPEN
+    }
+}
+
+impl Visibility {
+    /// Every face sees every other face, like in a chunk of air.
+    pub const OPEN: Visibility = Visibility((1 << 36) - 1);
+    /// No face sees any other face, like in a chunk of stone.
+    pub const CLOSED: Visibility = Visibility(0);
+
+    /// Finds the faces of a chunk connected through blocks that let light through.
+    pub fn compute(blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]) -> Visibility {
+        let mut visibility = Visibility::CLOSED;
+        let mut visited = [[[false; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    if visited[x][y][z] || !blocks[x][y][z].transmits_light() {
+                        continue;
+                    }
+
+                    // Find every face touched by this pocket of open space.
+                    let mut faces = 0u8;
+                    let mut stack = vec![(x, y, z)];
+                    visited[x][y][z] = true;
+                    while let Some(block) = stack.pop() {
+                        for &direction in FaceDirection::ALL.iter() {
+                            match neighbour(block, direction) {
+                                Some((x, y, z)) => {
+                                    if !visited[x][y][z] && blocks[x][y][z].transmits_light() {
+                                        visited[x][y][z] = true;
+                                        stack.push((x, y, z));
+                                    }
+                                }
+                                None => faces |= 1 << direction as u8,
+                            }
+                        }
+                    }
+
+                    for &a in FaceDirection::ALL.iter() {
+                        for &b in FaceDirection::ALL.iter() {
+                            if faces & (1 << a as u8) != 0 && faces & (1 << b as u8) != 0 {
+                                visibility.connect(a, b);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        visibility
+    }
+
+    pub fn connects(&self, a: FaceDirection, b: FaceDirection) -> bool {
+        self.0 & bit(a, b) != 0
+    }
+
+    pub fn connect(&mut self, a: FaceDirection, b: FaceDirection) {
+        self.0 |= bit(a, b) | bit(b, a);
+    }
+}
+
+fn bit(a: FaceDirection, b: FaceDirection) -> u64 {
+    1 << (a as u64 * 6 + b as u64)
+}
+
+/// Returns the local coordinates of the neighbouring block in the given direction, or `None` if
+/// it is outside of the chunk.
+fn neighbour(
+    (x, y, z): (usize, usize, usize),
+    direction: FaceDirection,
+) -> Option<(usize, usize, usize)> {
+    let [dx, dy, dz] = direction.offset();
+    let coords = [x as isize + dx, y as isize + dy, z as isize + dz];
+    if coords.iter().all(|&c| c >= 0 && c < CHUNK_SIZE as isize) {
+        Some((coords[0] as usize, coords[1] as usize, coords[2] as usize))
+    } else {
+        None
+    }
+}
+
+/// Returns the chunks that can be seen from the chunk the camera is in.
+///
+/// `visibility` returns the visibility of a chunk, or `None` for chunks outside of the world,
+/// which are never entered. Chunks that have not been meshed yet should be [`Visibility::OPEN`].
+/// Only chunks for which `in_view` returns `true`, such as those in the camera's frustum, are
+/// entered.
+///
+/// The search never turns back in a direction opposite to one it has already gone in, so a
+/// chunk is only reached along paths moving away from the camera.
+pub fn visible_chunks<V, C>(
+    camera: (i32, i32, i32),
+    visibility: V,
+    in_view: C,
+) -> HashSet<(i32, i32, i32)>
+where
+    V: Fn((i32, i32, i32)) -> Option<Visibility>,
+    C: Fn((i32, i32, i32)) -> bool,
+{
+    let mut visible = HashSet::new();
+    let camera_visibility = match visibility(camera) {
+        Some(camera_visibility) => camera_visibility,
+        None => return visible,
+    };
+    visible.insert(camera);
+
+    // Chunks to leave, with their visibility, the face they were entered through and the
+    // directions taken to reach them.
+    let mut queue = VecDeque::new();
+    queue.push_back((camera, camera_visibility, None, 0u8));
+
+    while let Some((chunk, chunk_visibility, entered, travelled)) = queue.pop_front() {
+        for &direction in FaceDirection::ALL.iter() {
+            if travelled & (1 << direction.opposite() as u8) != 0 {
+                continue;
+            }
+            if let Some(entered) = entered {
+                if !chunk_visibility.connects(entered, direction) {
+                    continue;
+                }
+            }
+
+            let [dx, dy, dz] = direction.offset();
+            let next = (
+                chunk.0 + dx as i32,
+                chunk.1 + dy as i32,
+                chunk.2 + dz as i32,
+            );
+            if visible.contains(&next) {
+                continue;
+            }
+            let next_visibility = match visibility(next) {
+                Some(next_visibility) => next_visibility,
+                None => continue,
+            };
+            if !in_view(next) {
+                continue;
+            }
+
+            visible.insert(next);
+            queue.push_back((
+                next,
+                next_visibility,
+                Some(direction.opposite()),
+                travelled | 1 << direction as u8,
+            ));
+        }
+    }
+
+    visible
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use minecraft_rust::{
+    blocks::Block,
+    client::{camera::Camera, chunk::Chunk, store::ChunkStore},
+    collision::Aabb,
+    coords,
+    server::{
+        chunk::{Chunk as ServerChunk, ChunkGenerator},
+        generator::VoidChunkGenerator,
+    },
+};
+
+/// Returns a store holding one empty chunk at the origin with the given blocks in it.
+fn store_with(blocks: &[([usize; 3], &str)]) -> ChunkStore {
+    let mut gen = VoidChunkGenerator::from_seed(0);
+    let store = ChunkStore::new();
+    store.insert(Chunk::from_server_chunk(ServerChunk::new(
+        0, 0, 0, &mut gen,
+    )));
+    store.update((0, 0, 0), |chunk| {
+        for &([x, y, z], name) in blocks {
+            *chunk.block_mut(x, y, z) = Block::get(name).unwrap();
+        }
+    });
+    store
+}
+
+fn camera_looking_at(position: [f32; 3], target: [f32; 3]) -> Camera {
+    let mut camera = Camera::new(10.0, 0.001, 90.0);
+    camera.look_at(position, target);
+    camera
+}
+
+#[test]
+fn plants_can_be_targeted() {
+    common::register_blocks();
+    let store = store_with(&[([4, 1, 4], "tall_grass_1"), ([7, 1, 4], "stone")]);
+    let camera = camera_looking_at(
+        coords::block_centre([2, 1, 4]),
+        coords::block_centre([7, 1, 4]),
+    );
+    assert_eq!(camera.target(&store), Some([4, 1, 4]));
+}
+
+#[test]
+fn flat_blocks_are_targeted_by_their_shape() {
+    common::register_blocks();
+    let store = store_with(&[([4, 1, 4], "track"), ([6, 1, 4], "stone")]);
+    let track = coords::block_centre([4, 1, 4]);
+    let floor = [track[0], track[1] - coords::BLOCK_SIZE * 0.5, track[2]];
+
+    // Looking down at the track hits it, even though it is much thinner than the steps the ray
+    // takes.
+    let above = [track[0] - 0.6, track[1] + 0.6, track[2]];
+    assert_eq!(
+        camera_looking_at(above, floor).target(&store),
+        Some([4, 1, 4])
+    );
+
+    // Looking through the empty space above the track hits the block behind it.
+    let level = [track[0] - 1.0, track[1], track[2]];
+    let behind = coords::block_centre([6, 1, 4]);
+    assert_eq!(
+        camera_looking_at(level, behind).target(&store),
+        Some([6, 1, 4])
+    );
+}
+
+#[test]
+fn rays_enter_boxes_along_their_direction() {
+    let block = Aabb {
+        centre: [2.0, 0.0, 0.0],
+        extents: [0.5; 3],
+    };
+    assert_eq!(block.ray_distance([0.0; 3], [1.0, 0.0, 0.0]), Some(1.5));
+    assert_eq!(
+        block.ray_distance([2.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+        Some(0.0)
+    );
+    assert_eq!(block.ray_distance([0.0; 3], [-1.0, 0.0, 0.0]), None);
+    assert_eq!(block.ray_distance([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]), None);
+}
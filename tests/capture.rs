@@ -0,0 +1,74 @@
+use std::io::ErrorKind;
+
+use minecraft_rust::client::capture::{self, CameraPath, PathPlayback};
+
+const PATH: &str = "
+# time  x  y  z  target x  target y  target z
+0       0  0  0  1         0         0
+
+2       4  2  0  1         0         0
+3       4  2  4  1         0         4
+";
+
+#[test]
+fn camera_paths_skip_comments_and_blank_lines() {
+    let path = CameraPath::parse(PATH).unwrap();
+    assert_eq!(path.keyframes().len(), 3);
+    assert_eq!(path.keyframes()[1].position, [4.0, 2.0, 0.0]);
+    assert_eq!(path.keyframes()[2].target, [1.0, 0.0, 4.0]);
+    assert_eq!(path.duration(), 3.0);
+}
+
+#[test]
+fn invalid_camera_paths_are_rejected() {
+    for text in [
+        "",
+        "# only a comment",
+        "0 1 2 3 4 5",
+        "0 1 2 3 4 5 six",
+        "1 0 0 0 1 0 0\n0 0 0 0 1 0 0",
+    ] {
+        let error = CameraPath::parse(text).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData, "{:?}", text);
+    }
+}
+
+#[test]
+fn camera_moves_in_straight_lines_between_keyframes() {
+    let path = CameraPath::parse(PATH).unwrap();
+    assert_eq!(path.pose(0.0), ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]));
+    assert_eq!(path.pose(1.0), ([2.0, 1.0, 0.0], [1.0, 0.0, 0.0]));
+    assert_eq!(path.pose(2.5), ([4.0, 2.0, 2.0], [1.0, 0.0, 2.0]));
+    // The camera stays put before the first keyframe and after the last.
+    assert_eq!(path.pose(-1.0), path.pose(0.0));
+    assert_eq!(path.pose(10.0), path.pose(3.0));
+}
+
+#[test]
+fn playback_captures_frames_at_fixed_intervals() {
+    let path = CameraPath::parse(PATH).unwrap();
+    let mut playback = PathPlayback::new(path.clone(), 0.5);
+    let mut frames = vec![];
+    while let Some(frame) = playback.next_frame() {
+        frames.push(frame);
+    }
+
+    // Frames at 0, 0.5, ... 3 seconds, the last one on the last keyframe.
+    assert_eq!(frames.len(), 7);
+    for (i, &(frame, pose)) in frames.iter().enumerate() {
+        assert_eq!(frame, i as u32);
+        assert_eq!(pose, path.pose(i as f32 * 0.5));
+    }
+}
+
+#[test]
+fn images_are_read_top_row_first() {
+    let rows = vec![
+        vec![(1, 0, 0, 255), (2, 0, 0, 255)],
+        vec![(3, 0, 0, 255), (4, 0, 0, 255)],
+    ];
+    let image = capture::image_from_rows(rows);
+    assert_eq!(image.dimensions(), (2, 2));
+    assert_eq!(image.get_pixel(0, 0).0, [3, 0, 0, 255]);
+    assert_eq!(image.get_pixel(1, 1).0, [2, 0, 0, 255]);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    server::chunk::{ChunkGenerator, PerlinChunkGenerator},
+};
+
+const SEED: u32 = 1337;
+/// Chunk y level well below the surface anywhere, so that only carving leaves empty space.
+const DEEP: i32 = -2;
+
+type Blocks = Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>;
+
+fn is_carved(block: Block) -> bool {
+    block == Block::air() || Some(block) == Block::get("lava")
+}
+
+/// Generates a 4x1x4 row of deep chunks, keyed by chunk coordinates.
+fn generate_region(gen: &mut PerlinChunkGenerator) -> Vec<((i32, i32, i32), Blocks)> {
+    let mut chunks = vec![];
+    for x in 0..4 {
+        for z in 0..4 {
+            chunks.push(((x, DEEP, z), gen.generate(x, DEEP, z)));
+        }
+    }
+    chunks
+}
+
+fn carved_mask(chunks: &[((i32, i32, i32), Blocks)]) -> Vec<bool> {
+    chunks
+        .iter()
+        .flat_map(|(_, blocks)| blocks.iter().flatten().flatten())
+        .map(|&block| is_carved(block))
+        .collect()
+}
+
+#[test]
+fn carving_is_deterministic() {
+    common::register_blocks();
+    let first = carved_mask(&generate_region(&mut PerlinChunkGenerator::from_seed(SEED)));
+    let second = carved_mask(&generate_region(&mut PerlinChunkGenerator::from_seed(SEED)));
+    let other = carved_mask(&generate_region(&mut PerlinChunkGenerator::from_seed(
+        SEED + 1,
+    )));
+
+    assert!(first.iter().any(|&carved| carved), "nothing carved");
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn carving_does_not_depend_on_generation_order() {
+    common::register_blocks();
+    let mut forwards = PerlinChunkGenerator::from_seed(SEED);
+    let mut backwards = PerlinChunkGenerator::from_seed(SEED);
+
+    let a = forwards.generate(0, DEEP, 0);
+    let b = forwards.generate(1, DEEP, 0);
+    let b_first = backwards.generate(1, DEEP, 0);
+    let a_second = backwards.generate(0, DEEP, 0);
+
+    assert!(a == a_second);
+    assert!(b == b_first);
+}
+
+#[test]
+fn caves_continue_across_chunk_borders() {
+    common::register_blocks();
+    let mut gen = PerlinChunkGenerator::from_seed(SEED);
+    let chunks = generate_region(&mut gen);
+    let get = |x: i32, z: i32| {
+        &chunks
+            .iter()
+            .find(|(coords, _)| *coords == (x, DEEP, z))
+            .unwrap()
+            .1
+    };
+
+    // Of the carved blocks on the last layer of a chunk, count how many have a carved block next
+    // to them in the next chunk over, and compare with the same count one layer inside the chunk.
+    let last = CHUNK_SIZE - 1;
+    let (mut across, mut within, mut carved) = (0, 0, 0);
+    for x in 0..3 {
+        for z in 0..4 {
+            let (here, next) = (get(x, z), get(x + 1, z));
+            for y in 0..CHUNK_SIZE {
+                for k in 0..CHUNK_SIZE {
+                    if is_carved(here[last][y][k]) {
+                        carved += 1;
+                        across += is_carved(next[0][y][k]) as u32;
+                        within += is_carved(here[last - 1][y][k]) as u32;
+                    }
+                }
+            }
+        }
+    }
+
+    assert!(carved > 0, "no caves reach a chunk border");
+    // Caves are wide, smooth shapes, so crossing a border is no different from a step inside a
+    // chunk.
+    assert!(
+        across * 10 >= within * 9,
+        "caves break at chunk borders: {} of {} carved blocks continue across, {} within",
+        across,
+        carved,
+        within
+    );
+}
+
+#[test]
+fn lava_is_not_solid() {
+    common::register_blocks();
+    let lava = Block::get("lava").unwrap();
+
+    assert_eq!(lava.is_solid(), Some(false));
+    assert!(lava.collision_boxes().is_empty());
+}
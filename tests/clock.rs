@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use minecraft_rust::{
+    clock::{WorldClock, TICK},
+    server::command::{Command, TimeOfDay},
+};
+
+fn at(ticks: u64) -> WorldClock {
+    WorldClock {
+        ticks,
+        day_length: 1000,
+        paused: false,
+    }
+}
+
+fn assert_close(a: [f32; 3], b: [f32; 3]) {
+    for i in 0..3 {
+        assert!((a[i] - b[i]).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn clock_starts_at_noon_and_advances() {
+    let mut clock = WorldClock::new(1000);
+    assert_eq!(clock.ticks, 250);
+    assert_eq!(clock.time_of_day(), 0.25);
+
+    clock.advance(900);
+    assert_eq!(clock.day(), 1);
+    assert_eq!(clock.time_of_day(), 0.15);
+
+    assert_eq!(clock.after(TICK * 10).ticks, clock.ticks + 10);
+    assert_eq!(clock.after(TICK / 2).ticks, clock.ticks);
+}
+
+#[test]
+fn paused_clock_stands_still() {
+    let mut clock = at(100);
+    clock.paused = true;
+    clock.advance(50);
+    assert_eq!(clock.ticks, 100);
+    assert_eq!(clock.after(Duration::from_secs(60)).ticks, 100);
+}
+
+#[test]
+fn zero_day_length_is_not_allowed() {
+    let clock = WorldClock::new(0);
+    assert_eq!(clock.day_length, 1);
+    assert_eq!(clock.time_of_day(), 0.0);
+}
+
+#[test]
+fn sun_rises_and_sets() {
+    let sunrise = at(0).sun_direction();
+    let noon = at(250).sun_direction();
+    let sunset = at(500).sun_direction();
+    let midnight = at(750).sun_direction();
+
+    assert!(sunrise[0] > 0.9 && sunrise[1].abs() < 1e-4);
+    assert!(noon[1] > 0.9);
+    assert!(sunset[0] < -0.9 && sunset[1].abs() < 1e-4);
+    assert!(midnight[1] < -0.9);
+
+    // At night light comes from the moon, opposite the sun.
+    assert_close(at(250).light_direction(), noon);
+    let moon = at(750).light_direction();
+    assert_close(moon, [-midnight[0], -midnight[1], -midnight[2]]);
+}
+
+#[test]
+fn nights_are_darker_than_days() {
+    assert!((at(250).daylight() - 1.0).abs() < 1e-6);
+    assert!(at(750).daylight() < 0.25);
+    assert!(at(0).daylight() < 1.0 && at(0).daylight() > at(750).daylight());
+
+    // Noon has the same sky the game always had, night is much darker.
+    assert_close(at(250).sky_colour(), [0.53, 0.80, 0.92]);
+    let night = at(750).sky_colour();
+    assert!(night.iter().all(|&c| c < 0.1));
+
+    // Sunsets are red.
+    let sunset = at(500).sky_colour();
+    assert!(sunset[0] > sunset[2]);
+}
+
+#[test]
+fn commands_are_parsed() {
+    assert_eq!(Command::parse("time"), Ok(Command::QueryTime));
+    assert_eq!(Command::parse("  time   pause "), Ok(Command::PauseTime));
+    assert_eq!(Command::parse("time resume"), Ok(Command::ResumeTime));
+    assert_eq!(
+        Command::parse("time set 1234"),
+        Ok(Command::SetTime(TimeOfDay::Ticks(1234)))
+    );
+    assert_eq!(
+        Command::parse("time set midnight"),
+        Ok(Command::SetTime(TimeOfDay::Quarter(3)))
+    );
+
+    assert!(Command::parse("").is_err());
+    assert!(Command::parse("time set soon").is_err());
+    assert!(Command::parse("time stop").is_err());
+    assert_eq!(
+        Command::parse("weather clear"),
+        Err(String::from("Unknown command: weather"))
+    );
+}
+
+#[test]
+fn commands_change_the_clock() {
+    let mut clock = at(2100);
+
+    Command::PauseTime.apply(&mut clock);
+    assert!(clock.paused);
+    let message = Command::QueryTime.apply(&mut clock);
+    assert_eq!(message, "Day 2, tick 100 of 1000 (paused)");
+
+    Command::SetTime(TimeOfDay::Quarter(2)).apply(&mut clock);
+    assert_eq!(clock.ticks, 2500);
+    Command::SetTime(TimeOfDay::Ticks(42)).apply(&mut clock);
+    assert_eq!(clock.ticks, 42);
+
+    Command::ResumeTime.apply(&mut clock);
+    assert!(!clock.paused);
+}
@@ -0,0 +1,12 @@
+//! Fixtures shared by the integration tests.
+
+use std::sync::Once;
+
+use minecraft_rust::blocks::Block;
+
+static REGISTER: Once = Once::new();
+
+/// Registers the default blocks, once for the whole test binary.
+pub fn register_blocks() {
+    REGISTER.call_once(Block::register_defaults);
+}
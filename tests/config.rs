@@ -0,0 +1,74 @@
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+use minecraft_rust::{clock::DEFAULT_DAY_LENGTH, server::config::ServerConfig};
+
+fn write_config(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("minecraft-rust-{}-server.toml", name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+#[test]
+fn missing_config_falls_back_to_the_defaults() {
+    let path = env::temp_dir().join("minecraft-rust-missing-server.toml");
+    let _ = fs::remove_file(&path);
+    let config = ServerConfig::load(&path).unwrap();
+    assert_eq!(config.bind, "127.0.0.1:6429");
+    assert_eq!(config.world, "world");
+    assert_eq!(config.day_length, DEFAULT_DAY_LENGTH);
+    assert_eq!(config.generator.name, "perlin");
+    assert!(config.generator.options.is_empty());
+}
+
+#[test]
+fn generator_options_are_every_other_key_of_the_generator_table() {
+    let path = write_config(
+        "flat",
+        "seed = 7\n\
+         [generator]\n\
+         name = \"flat\"\n\
+         bottom = -6\n\
+         layers = [{ block = \"stone\", height = 3 }, { block = \"grass\", height = 1 }]\n",
+    );
+    let config = ServerConfig::load(&path).unwrap();
+    assert_eq!(config.seed, 7);
+    assert_eq!(config.world, "world");
+    assert_eq!(config.generator.name, "flat");
+
+    let options = &config.generator.options;
+    assert_eq!(options.len(), 2);
+    assert_eq!(options["bottom"].as_integer(), Some(-6));
+    let layers = options["layers"].as_array().unwrap();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[1]["block"].as_str(), Some("grass"));
+}
+
+#[test]
+fn bad_config_is_an_error() {
+    for (name, source) in [
+        ("syntax", "seed = \n"),
+        ("type", "seed = \"random\"\n"),
+        ("negative-seed", "seed = -1\n"),
+        ("generator-without-name", "[generator]\nbottom = 0\n"),
+        ("generator-not-a-table", "generator = \"flat\"\n"),
+    ] {
+        let path = write_config(name, source);
+        let result = ServerConfig::load(&path);
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn repo_config_loads() {
+    let config = ServerConfig::load(concat!(env!("CARGO_MANIFEST_DIR"), "/server.toml")).unwrap();
+    assert_eq!(config.generator.name, "perlin");
+    assert_eq!(
+        config.generator.options["ores"].as_str(),
+        Some("assets/ores.toml")
+    );
+}
@@ -0,0 +1,131 @@
+use minecraft_rust::{
+    blocks::CHUNK_SIZE,
+    collision::{Aabb, DetectCollision},
+    coords::{self, BLOCK_SIZE},
+};
+
+const SIZE: i32 = CHUNK_SIZE as i32;
+
+/// Every block coordinate within three chunks of the origin on one axis, which covers negative
+/// coordinates and both sides of several chunk borders.
+fn blocks() -> impl Iterator<Item = i32> {
+    -3 * SIZE..3 * SIZE
+}
+
+#[test]
+fn block_chunk_round_trip() {
+    for b in blocks() {
+        let block = [b, -b, b / 2];
+        let (chunk, local) = coords::block_to_chunk(block);
+
+        assert!(local.0 < CHUNK_SIZE && local.1 < CHUNK_SIZE && local.2 < CHUNK_SIZE);
+        assert_eq!(coords::chunk_to_block(chunk, local), block);
+    }
+}
+
+#[test]
+fn chunk_borders() {
+    assert_eq!(coords::block_to_chunk([0, 0, 0]), ((0, 0, 0), (0, 0, 0)));
+    assert_eq!(
+        coords::block_to_chunk([-1, -1, -1]),
+        (
+            (-1, -1, -1),
+            (CHUNK_SIZE - 1, CHUNK_SIZE - 1, CHUNK_SIZE - 1)
+        )
+    );
+    assert_eq!(
+        coords::block_to_chunk([SIZE - 1, -SIZE, SIZE]),
+        ((0, -1, 1), (CHUNK_SIZE - 1, 0, 0))
+    );
+    assert_eq!(coords::block_to_chunk([-SIZE - 1, 0, 0]).0, (-2, 0, 0));
+}
+
+#[test]
+fn consecutive_blocks_stay_in_order() {
+    let mut last = None;
+    for b in blocks() {
+        let ((chunk, _, _), (local, _, _)) = coords::block_to_chunk([b, 0, 0]);
+        if let Some((last_chunk, last_local)) = last {
+            if local == 0 {
+                assert_eq!(chunk, last_chunk + 1);
+                assert_eq!(last_local, CHUNK_SIZE - 1);
+            } else {
+                assert_eq!(chunk, last_chunk);
+                assert_eq!(local, last_local + 1);
+            }
+        }
+        last = Some((chunk, local));
+    }
+}
+
+#[test]
+fn world_positions_inside_block_map_to_it() {
+    for b in blocks() {
+        let block = [b, b, -b];
+        let centre = coords::block_centre(block);
+        let aabb = coords::block_aabb(block);
+
+        for &offset in [-0.24, -0.1, 0.0, 0.1, 0.24].iter() {
+            let pos = [centre[0] + offset, centre[1] - offset, centre[2] + offset];
+            assert_eq!(coords::world_to_block(pos), block, "{:?}", pos);
+
+            let point = Aabb {
+                centre: pos,
+                extents: [0.0; 3],
+            };
+            assert!(aabb.is_colliding(&point));
+        }
+    }
+}
+
+#[test]
+fn boundaries_belong_to_positive_block() {
+    for b in blocks() {
+        let boundary = (b as f32 + 0.5) * BLOCK_SIZE;
+        assert_eq!(
+            coords::world_to_block([boundary, boundary, boundary]),
+            [b + 1, b + 1, b + 1],
+            "boundary at {}",
+            boundary
+        );
+    }
+}
+
+#[test]
+fn world_to_chunk_matches_block_conversion() {
+    for b in blocks() {
+        let pos = coords::block_centre([b, -b, b]);
+        let (chunk_x, chunk_y, chunk_z, x, y, z) = coords::world_to_chunk(pos[0], pos[1], pos[2]);
+
+        assert_eq!(
+            coords::chunk_to_block((chunk_x, chunk_y, chunk_z), (x, y, z)),
+            [b, -b, b]
+        );
+    }
+}
+
+#[test]
+fn chunk_aabb_contains_exactly_its_blocks() {
+    for &chunk in [(0, 0, 0), (-1, -1, -1), (2, -3, 1)].iter() {
+        let aabb = coords::chunk_aabb(chunk);
+
+        for &local in [(0, 0, 0), (CHUNK_SIZE - 1, 0, CHUNK_SIZE - 1)].iter() {
+            let block = coords::block_aabb(coords::chunk_to_block(chunk, local));
+            for axis in 0..3 {
+                let min = aabb.centre[axis] - aabb.extents[axis];
+                let max = aabb.centre[axis] + aabb.extents[axis];
+                assert!(block.centre[axis] - block.extents[axis] >= min - f32::EPSILON);
+                assert!(block.centre[axis] + block.extents[axis] <= max + f32::EPSILON);
+            }
+        }
+
+        let origin = coords::block_to_chunk(coords::chunk_to_block(chunk, (0, 0, 0)));
+        assert_eq!(origin, (chunk, (0, 0, 0)));
+
+        let neighbour =
+            coords::chunk_to_block((chunk.0 - 1, chunk.1, chunk.2), (CHUNK_SIZE - 1, 0, 0));
+        let mut neighbour = coords::block_aabb(neighbour);
+        neighbour.extents = [BLOCK_SIZE * 0.49; 3];
+        assert!(!aabb.is_colliding(&neighbour));
+    }
+}
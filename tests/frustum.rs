@@ -0,0 +1,95 @@
+use minecraft_rust::{
+    client::{
+        camera::Camera,
+        shapes::frustum::{Aabb, Frustum},
+    },
+    coords,
+};
+
+/// 4:3, so the frustum is 90° high and about 106° wide.
+const DIMENSIONS: (u32, u32) = (800, 600);
+
+/// A camera at the origin looking towards positive x, turned by the given angle in milliradians
+/// towards negative z.
+fn camera(turn: i32) -> Camera {
+    let mut camera = Camera::new(10.0, 0.001, 90.0);
+    camera.turn_self(turn, 0);
+    camera
+}
+
+fn cube(centre: [f32; 3], extent: f32) -> Aabb {
+    Aabb {
+        centre,
+        extents: [extent; 3],
+    }
+}
+
+fn visible(frustum: &Frustum, centre: [f32; 3], extent: f32) -> bool {
+    cube(centre, extent).is_in_frustum(frustum)
+}
+
+#[test]
+fn planes_point_into_the_frustum() {
+    let frustum = camera(0).frustum(DIMENSIONS);
+    for plane in frustum.planes().iter() {
+        let length: f32 = plane.normal.iter().map(|n| n * n).sum();
+        assert!((length - 1.0).abs() < 1e-4);
+        assert!(plane.signed_distance([5.0, 1.0, -1.0]) > 0.0, "{:?}", plane);
+    }
+
+    // The side planes go through the camera, the near and far planes are in front of it.
+    for plane in [frustum.left, frustum.right, frustum.top, frustum.bottom].iter() {
+        assert!(plane.signed_distance([0.0; 3]).abs() < 1e-3, "{:?}", plane);
+    }
+    assert!((frustum.near.signed_distance([0.0; 3]) + 0.1).abs() < 1e-3);
+    assert!((frustum.far.signed_distance([0.0; 3]) - 1024.0).abs() < 1.0);
+}
+
+#[test]
+fn boxes_in_front_are_visible_and_behind_are_not() {
+    let frustum = camera(0).frustum(DIMENSIONS);
+    assert!(visible(&frustum, [20.0, 0.0, 0.0], 4.0));
+    assert!(visible(&frustum, [500.0, 30.0, -60.0], 4.0));
+    assert!(!visible(&frustum, [-20.0, 0.0, 0.0], 4.0));
+    assert!(!visible(&frustum, [-20.0, 5.0, 5.0], 4.0));
+}
+
+#[test]
+fn boxes_straddling_the_near_and_far_planes_are_visible() {
+    let frustum = camera(0).frustum(DIMENSIONS);
+    // The camera is inside this one.
+    assert!(visible(&frustum, [0.0; 3], 1.0));
+    assert!(visible(&frustum, [1024.0, 0.0, 0.0], 4.0));
+    assert!(!visible(&frustum, [1100.0, 0.0, 0.0], 4.0));
+}
+
+#[test]
+fn boxes_straddling_the_side_planes_are_visible() {
+    let frustum = camera(0).frustum(DIMENSIONS);
+
+    // 10 blocks ahead the frustum is 20 high and about 26.7 wide.
+    for &sign in [1.0, -1.0].iter() {
+        assert!(visible(&frustum, [10.0, 0.0, 14.0 * sign], 2.0));
+        assert!(!visible(&frustum, [10.0, 0.0, 20.0 * sign], 2.0));
+        assert!(visible(&frustum, [10.0, 11.0 * sign, 0.0], 2.0));
+        assert!(!visible(&frustum, [10.0, 15.0 * sign, 0.0], 2.0));
+    }
+}
+
+#[test]
+fn the_frustum_turns_with_the_camera() {
+    let frustum = camera(1571).frustum(DIMENSIONS);
+    assert!(visible(&frustum, [0.0, 0.0, -20.0], 4.0));
+    assert!(!visible(&frustum, [0.0, 0.0, 20.0], 4.0));
+    assert!(!visible(&frustum, [20.0, 0.0, 0.0], 2.0));
+}
+
+#[test]
+fn chunks_behind_the_camera_are_culled() {
+    let frustum = camera(0).frustum(DIMENSIONS);
+    assert!(coords::chunk_aabb((0, 0, 0)).is_in_frustum(&frustum));
+    assert!(coords::chunk_aabb((3, 0, 0)).is_in_frustum(&frustum));
+    assert!(coords::chunk_aabb((3, -1, 2)).is_in_frustum(&frustum));
+    assert!(!coords::chunk_aabb((-2, 0, 0)).is_in_frustum(&frustum));
+    assert!(!coords::chunk_aabb((1, 0, 8)).is_in_frustum(&frustum));
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use std::io::ErrorKind;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    server::{
+        chunk::ChunkGenerator,
+        generator::{self, DebugChunkGenerator, FlatChunkGenerator, FlatLayer, VoidChunkGenerator},
+    },
+};
+use toml::value::Table;
+
+fn block(name: &str) -> Block {
+    Block::get(name).unwrap()
+}
+
+fn layer(block: &str, height: u32) -> FlatLayer {
+    FlatLayer {
+        block: block.to_string(),
+        height,
+    }
+}
+
+fn options(source: &str) -> Table {
+    toml::from_str(source).unwrap()
+}
+
+/// Returns the column of blocks from world y level `bottom` up to `top`, excluding `top`.
+fn column(gen: &mut dyn ChunkGenerator, bottom: i32, top: i32) -> Vec<Block> {
+    (bottom..top)
+        .map(|y| {
+            let chunk_y = y.div_euclid(CHUNK_SIZE as i32);
+            let blocks = gen.generate(0, chunk_y, 0);
+            blocks[3][y.rem_euclid(CHUNK_SIZE as i32) as usize][5]
+        })
+        .collect()
+}
+
+#[test]
+fn flat_layers_stack_from_the_bottom_up() {
+    common::register_blocks();
+    let mut gen = FlatChunkGenerator::new(-2, &[layer("stone", 3), layer("dirt", 2)]).unwrap();
+
+    let air = Block::air();
+    let (stone, dirt) = (block("stone"), block("dirt"));
+    // The layers cross from chunk y -1 into chunk y 0.
+    assert_eq!(
+        column(&mut gen, -4, 5),
+        vec![air, air, stone, stone, stone, dirt, dirt, air, air]
+    );
+}
+
+#[test]
+fn flat_layers_fill_whole_chunk_layers() {
+    common::register_blocks();
+    let mut gen = FlatChunkGenerator::new(0, &[layer("grass", 1)]).unwrap();
+    let blocks = gen.generate(7, 0, -3);
+    for square in blocks.iter() {
+        assert!(square[0].iter().all(|&b| b == block("grass")));
+        assert!(square[1..]
+            .iter()
+            .all(|line| line.iter().all(|&b| b == Block::air())));
+    }
+    assert!(gen
+        .generate(7, 1, -3)
+        .iter()
+        .flatten()
+        .flatten()
+        .all(|&b| b == Block::air()));
+}
+
+#[test]
+fn flat_generator_defaults_to_stone_dirt_and_grass() {
+    common::register_blocks();
+    let mut gen = FlatChunkGenerator::from_seed(0);
+    let (stone, dirt, grass) = (block("stone"), block("dirt"), block("grass"));
+    assert_eq!(
+        column(&mut gen, -7, 1),
+        vec![
+            Block::air(),
+            stone,
+            stone,
+            stone,
+            dirt,
+            dirt,
+            grass,
+            Block::air()
+        ]
+    );
+}
+
+#[test]
+fn flat_options_are_read_from_the_generator_config() {
+    common::register_blocks();
+    let mut gen = FlatChunkGenerator::from_options(&options(
+        "bottom = 1\nlayers = [{ block = \"glass\", height = 2 }]",
+    ))
+    .unwrap();
+    let glass = block("glass");
+    assert_eq!(
+        column(&mut gen, 0, 4),
+        vec![Block::air(), glass, glass, Block::air()]
+    );
+}
+
+#[test]
+fn bad_flat_options_are_an_error() {
+    common::register_blocks();
+    let unknown = FlatChunkGenerator::new(0, &[layer("no_such_block", 1)]);
+    assert_eq!(unknown.err().unwrap().kind(), ErrorKind::InvalidData);
+
+    for source in [
+        "bottom = \"low\"",
+        "layers = [{ block = \"stone\", height = -1 }]",
+        "layers = [{ height = 1 }]",
+        "layers = [{ block = \"no_such_block\", height = 1 }]",
+    ] {
+        let result = FlatChunkGenerator::from_options(&options(source));
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn void_generator_is_all_air() {
+    common::register_blocks();
+    let mut gen = VoidChunkGenerator;
+    for &(x, y, z) in [(0, 0, 0), (-3, -1, 5), (2, -8, 2)].iter() {
+        assert!(gen
+            .generate(x, y, z)
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|&b| b == Block::air()));
+    }
+}
+
+#[test]
+fn debug_generator_lays_out_every_block_once() {
+    common::register_blocks();
+    let registered = Block::registered();
+    let mut gen = DebugChunkGenerator::new();
+
+    let mut placed = vec![];
+    for chunk_x in -1..3 {
+        for chunk_z in -1..3 {
+            for chunk_y in -1..=1 {
+                let blocks = gen.generate(chunk_x, chunk_y, chunk_z);
+                for (x, square) in blocks.iter().enumerate() {
+                    for (y, line) in square.iter().enumerate() {
+                        for (z, &b) in line.iter().enumerate() {
+                            if b == Block::air() {
+                                continue;
+                            }
+                            // Only on world y level 0, on even coordinates, away from the origin
+                            // in the positive direction.
+                            assert_eq!((chunk_y, y), (0, 0));
+                            let world_x = chunk_x * CHUNK_SIZE as i32 + x as i32;
+                            let world_z = chunk_z * CHUNK_SIZE as i32 + z as i32;
+                            assert!(world_x >= 0 && world_x % 2 == 0);
+                            assert!(world_z >= 0 && world_z % 2 == 0);
+                            placed.push(b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    placed.sort();
+    let mut expected = registered;
+    expected.sort();
+    assert_eq!(placed, expected);
+}
+
+#[test]
+fn generators_are_created_by_name() {
+    common::register_blocks();
+    generator::register_defaults();
+
+    for name in ["perlin", "flat", "void", "debug"] {
+        assert!(
+            generator::create(name, 1, &Table::new()).is_ok(),
+            "{}",
+            name
+        );
+    }
+
+    let mut flat = generator::create("flat", 1, &options("bottom = 0")).unwrap();
+    assert_eq!(flat.generate(0, 0, 0)[0][5][0], block("grass"));
+
+    let unknown = generator::create("no_such_generator", 1, &Table::new());
+    assert_eq!(unknown.err().unwrap().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn registered_generators_can_be_replaced() {
+    common::register_blocks();
+    generator::register("replaced", |_, _| Ok(Box::new(VoidChunkGenerator)));
+    generator::register("replaced", |_, options| {
+        Ok(Box::new(FlatChunkGenerator::from_options(options)?))
+    });
+
+    let mut gen = generator::create("replaced", 1, &options("bottom = 0")).unwrap();
+    assert_ne!(gen.generate(0, 0, 0)[0][0][0], Block::air());
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    client::light::{self, LightSource, LIGHT_RANGE},
+    coords,
+};
+
+fn light_at(location: [f32; 3]) -> LightSource {
+    LightSource::new(15, 15, 15, location)
+}
+
+#[test]
+fn only_emissive_blocks_are_lights() {
+    common::register_blocks();
+    let lava = Block::get("lava").unwrap();
+    assert_eq!(lava.light_colour(), Some([15, 9, 3]));
+    assert_eq!(Block::get("stone").unwrap().light_colour(), None);
+    assert_eq!(Block::air().light_colour(), None);
+
+    let light = LightSource::from_block(lava, [1, 2, 3]).unwrap();
+    assert_eq!((light.red(), light.green(), light.blue()), (15, 9, 3));
+    assert_eq!(light.location(), coords::block_centre([1, 2, 3]));
+    assert!(LightSource::from_block(Block::air(), [1, 2, 3]).is_none());
+}
+
+#[test]
+fn emissive_blocks_are_found_in_world_coordinates() {
+    common::register_blocks();
+    let lava = Block::get("lava").unwrap();
+    let mut blocks = Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+    blocks[1][2][3] = lava;
+    blocks[15][0][7] = lava;
+    blocks[4][4][4] = Block::get("stone").unwrap();
+
+    let lights = light::emissive_blocks((-1, 0, 2), &blocks);
+    assert_eq!(lights.len(), 2);
+    for &local in [(1, 2, 3), (15, 0, 7)].iter() {
+        let location = coords::block_centre(coords::chunk_to_block((-1, 0, 2), local));
+        assert!(lights.iter().any(|l| l.location() == location));
+    }
+}
+
+#[test]
+fn closest_lights_are_selected_first() {
+    let lights: Vec<_> = (0..10)
+        .rev()
+        .map(|i| light_at([i as f32, 0.0, 0.0]))
+        .collect();
+
+    let selected = light::select_lights(&lights, [0.0; 3], 4.0, 3);
+    let xs: Vec<_> = selected.iter().map(|l| l.location()[0]).collect();
+    assert_eq!(xs, vec![0.0, 1.0, 2.0]);
+
+    // Every light fits when the limit is high enough.
+    assert_eq!(light::select_lights(&lights, [0.0; 3], 4.0, 100).len(), 10);
+}
+
+#[test]
+fn lights_out_of_reach_are_skipped() {
+    let lights = vec![
+        light_at([0.0, 0.0, 1.0 + LIGHT_RANGE - 0.1]),
+        light_at([0.0, 0.0, 1.0 + LIGHT_RANGE + 0.1]),
+        light_at([-1.0 - LIGHT_RANGE - 0.1, 0.0, 0.0]),
+    ];
+
+    let selected = light::select_lights(&lights, [0.0; 3], 1.0, 8);
+    assert_eq!(selected, vec![lights[0].clone()]);
+}
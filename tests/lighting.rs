@@ -0,0 +1,273 @@
+mod common;
+
+use std::collections::HashMap;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    coords,
+    lighting::{Light, LightMap, MAX_LIGHT},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn stone() -> Block {
+    Block::get("stone").unwrap()
+}
+
+fn lava() -> Block {
+    Block::get("lava").unwrap()
+}
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+/// Blocks of a few loaded chunks.
+#[derive(Default)]
+struct World {
+    chunks: HashMap<(i32, i32, i32), Box<Blocks>>,
+}
+
+impl World {
+    fn add(&mut self, chunk: (i32, i32, i32), fill: Block) {
+        self.chunks.insert(
+            chunk,
+            Box::new([[[fill; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]),
+        );
+    }
+
+    fn get(&self, block: [i32; 3]) -> Option<Block> {
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        self.chunks.get(&chunk).map(|blocks| blocks[x][y][z])
+    }
+
+    fn set(&mut self, block: [i32; 3], value: Block) {
+        let (chunk, (x, y, z)) = coords::block_to_chunk(block);
+        self.chunks.get_mut(&chunk).unwrap()[x][y][z] = value;
+    }
+
+    /// Lights every chunk from scratch, adding them in the given order.
+    fn light(&self, order: &[(i32, i32, i32)]) -> LightMap {
+        let mut light = LightMap::new();
+        for &chunk in order {
+            light.add_chunk(chunk, |block| self.get(block));
+        }
+        light
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = [i32; 3]> + '_ {
+        self.chunks.keys().flat_map(|&chunk| {
+            (0..CHUNK_SIZE).flat_map(move |x| {
+                (0..CHUNK_SIZE).flat_map(move |y| {
+                    (0..CHUNK_SIZE).map(move |z| coords::chunk_to_block(chunk, (x, y, z)))
+                })
+            })
+        })
+    }
+}
+
+fn assert_same_light(world: &World, a: &LightMap, b: &LightMap) {
+    for block in world.blocks() {
+        assert_eq!(a.get(block), b.get(block), "light at {:?}", block);
+    }
+}
+
+#[test]
+fn open_sky_is_fully_lit() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), Block::air());
+    world.add((0, -1, 0), Block::air());
+    let light = world.light(&[(0, -1, 0), (0, 0, 0)]);
+
+    for block in world.blocks() {
+        assert_eq!(light.get(block), Some(Light::SKY));
+    }
+    assert_eq!(light.get([0, 100, 0]), None);
+}
+
+#[test]
+fn sky_light_is_blocked_and_spreads_under_overhangs() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), Block::air());
+    // A roof over x < 8 at y == 10.
+    for x in 0..8 {
+        for z in 0..CHUNK_SIZE as i32 {
+            world.set([x, 10, z], stone());
+        }
+    }
+    let light = world.light(&[(0, 0, 0)]);
+
+    assert_eq!(light.get([4, 11, 4]).unwrap().sky(), MAX_LIGHT);
+    assert_eq!(light.get([4, 10, 4]).unwrap().sky(), 0);
+    assert_eq!(light.get([8, 0, 4]).unwrap().sky(), MAX_LIGHT);
+    // Each block further under the roof is one level darker.
+    for x in 0..8 {
+        assert_eq!(
+            light.get([x, 5, 4]).unwrap().sky(),
+            MAX_LIGHT - (8 - x) as u8
+        );
+    }
+}
+
+#[test]
+fn enclosed_caves_are_dark() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), stone());
+    world.add((0, 1, 0), Block::air());
+    for x in 4..8 {
+        for y in 4..8 {
+            for z in 4..8 {
+                world.set([x, y, z], Block::air());
+            }
+        }
+    }
+    let light = world.light(&[(0, 0, 0), (0, 1, 0)]);
+
+    assert_eq!(light.get([5, 5, 5]), Some(Light::new(0)));
+    assert_eq!(light.get([5, 16, 5]).unwrap().sky(), MAX_LIGHT);
+}
+
+#[test]
+fn blocks_that_give_off_light_leave_caves_dark() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), stone());
+    world.add((0, 1, 0), stone());
+    for x in 4..12 {
+        world.set([x, 8, 8], Block::air());
+    }
+    world.set([4, 8, 8], lava());
+    let light = world.light(&[(0, 0, 0), (0, 1, 0)]);
+
+    // They are drawn as dynamic lights by the client instead.
+    for x in 4..12 {
+        assert_eq!(light.get([x, 8, 8]), Some(Light::new(0)), "x = {}", x);
+    }
+}
+
+#[test]
+fn non_cube_and_transparent_blocks_let_light_through() {
+    common::register_blocks();
+    for name in ["glass", "water", "stone_slab", "tall_grass_1"].iter() {
+        let block = Block::get(name).unwrap();
+        assert!(block.transmits_light(), "{}", name);
+    }
+    assert!(Block::air().transmits_light());
+    assert!(!stone().transmits_light());
+    assert!(!Block::invalid().transmits_light());
+}
+
+#[test]
+fn removing_and_placing_blocks_updates_light() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), stone());
+    world.add((0, 1, 0), Block::air());
+    world.set([5, 5, 5], Block::air());
+    let mut light = world.light(&[(0, 0, 0), (0, 1, 0)]);
+    assert_eq!(light.get([5, 5, 5]).unwrap().sky(), 0);
+
+    // Dig a shaft down to the hole from the surface.
+    for y in (6..CHUNK_SIZE as i32).rev() {
+        world.set([5, y, 5], Block::air());
+        let changed = light.update_block([5, y, 5], |block| world.get(block));
+        assert!(changed.contains(&(0, 0, 0)));
+    }
+    assert_eq!(light.get([5, 5, 5]).unwrap().sky(), MAX_LIGHT);
+    assert_same_light(&world, &light, &world.light(&[(0, 0, 0), (0, 1, 0)]));
+
+    // Cover it back up.
+    world.set([5, 15, 5], stone());
+    light.update_block([5, 15, 5], |block| world.get(block));
+    assert_eq!(light.get([5, 5, 5]).unwrap().sky(), 0);
+    assert_same_light(&world, &light, &world.light(&[(0, 0, 0), (0, 1, 0)]));
+}
+
+#[test]
+fn changed_border_blocks_remesh_neighbouring_chunks() {
+    common::register_blocks();
+    let mut world = World::default();
+    world.add((0, 0, 0), stone());
+    world.add((1, 0, 0), stone());
+    let mut light = world.light(&[(0, 0, 0), (1, 0, 0)]);
+
+    // Opening up the top of the border lets sky light in.
+    world.set([15, 15, 4], Block::air());
+    let changed = light.update_block([15, 15, 4], |block| world.get(block));
+    assert!(changed.contains(&(0, 0, 0)));
+    assert!(changed.contains(&(1, 0, 0)));
+    assert!(!changed.contains(&(0, 1, 0)));
+}
+
+fn random_world(rng: &mut StdRng, chunks: &[(i32, i32, i32)]) -> World {
+    let palette = [
+        Block::air(),
+        Block::air(),
+        Block::air(),
+        stone(),
+        stone(),
+        Block::get("glass").unwrap(),
+        Block::get("stone_slab").unwrap(),
+    ];
+
+    let mut world = World::default();
+    for &chunk in chunks {
+        world.add(chunk, Block::air());
+    }
+    let blocks: Vec<_> = world.blocks().collect();
+    for block in blocks {
+        let value = if rng.gen_range(0..400) == 0 {
+            lava()
+        } else {
+            palette[rng.gen_range(0..palette.len())]
+        };
+        world.set(block, value);
+    }
+    world
+}
+
+#[test]
+fn light_does_not_depend_on_chunk_load_order() {
+    common::register_blocks();
+    let mut rng = StdRng::seed_from_u64(0x11647);
+    let mut chunks = vec![];
+    for x in 0..2 {
+        for y in -1..1 {
+            for z in 0..2 {
+                chunks.push((x, y, z));
+            }
+        }
+    }
+    let world = random_world(&mut rng, &chunks);
+
+    let expected = world.light(&chunks);
+    for _ in 0..3 {
+        let mut order = chunks.clone();
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.gen_range(0..=i));
+        }
+        assert_same_light(&world, &world.light(&order), &expected);
+    }
+}
+
+#[test]
+fn incremental_updates_match_lighting_from_scratch() {
+    common::register_blocks();
+    let mut rng = StdRng::seed_from_u64(0xb10c);
+    let chunks = [(0, -1, 0), (0, 0, 0), (1, -1, 0), (1, 0, 0)];
+    let mut world = random_world(&mut rng, &chunks);
+    let mut light = world.light(&chunks);
+
+    let choices = [Block::air(), stone(), lava(), Block::get("glass").unwrap()];
+    for _ in 0..200 {
+        let block = [
+            rng.gen_range(0..2 * CHUNK_SIZE as i32),
+            rng.gen_range(-(CHUNK_SIZE as i32)..CHUNK_SIZE as i32),
+            rng.gen_range(0..CHUNK_SIZE as i32),
+        ];
+        world.set(block, choices[rng.gen_range(0..choices.len())]);
+        light.update_block(block, |block| world.get(block));
+    }
+
+    assert_same_light(&world, &light, &world.light(&chunks));
+}
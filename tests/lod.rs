@@ -0,0 +1,228 @@
+mod common;
+
+use std::{collections::HashMap, env, fs};
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
+    lod::{self, LodChunk, LOD_SCALES},
+    mesh::{self, Face},
+    server::{
+        chunk::{Chunk, ChunkGenerator, PerlinChunkGenerator},
+        generator::VoidChunkGenerator,
+        net,
+        save::WorldSave,
+    },
+};
+use tokio::sync::Mutex;
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+fn filled(block: Block) -> Box<Blocks> {
+    Box::new([[[block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE])
+}
+
+fn block(name: &str) -> Block {
+    Block::get(name).unwrap()
+}
+
+/// Returns the area of the faces in each direction, in blocks.
+fn area(faces: &[Face]) -> [usize; 6] {
+    let mut area = [0; 6];
+    for face in faces {
+        area[face.direction as usize] += face.width * face.height;
+    }
+    area
+}
+
+#[test]
+fn cells_take_the_most_common_cube() {
+    common::register_blocks();
+    let mut blocks = filled(Block::air());
+    // First cell: five stone, three dirt.
+    for (i, &(x, y, z)) in [
+        (0, 0, 0),
+        (0, 0, 1),
+        (0, 1, 0),
+        (0, 1, 1),
+        (1, 0, 0),
+        (1, 0, 1),
+        (1, 1, 0),
+        (1, 1, 1),
+    ]
+    .iter()
+    .enumerate()
+    {
+        blocks[x][y][z] = if i < 5 { block("stone") } else { block("dirt") };
+    }
+    // Second cell, along x: three dirt and five air.
+    blocks[2][0][0] = block("dirt");
+    blocks[3][0][0] = block("dirt");
+    blocks[2][1][0] = block("dirt");
+    // Third cell, along y: four dirt and four tall grass.
+    for x in 0..2 {
+        for z in 0..2 {
+            blocks[x][2][z] = block("dirt");
+            blocks[x][3][z] = block("tall_grass_1");
+        }
+    }
+
+    let lod = LodChunk::new((1, 2, 3), &blocks, 2);
+    assert_eq!(lod.coords(), (1, 2, 3));
+    assert_eq!(lod.size(), 8);
+    assert_eq!(lod.cell(0, 0, 0), block("stone"));
+    assert_eq!(lod.cell(1, 0, 0), Block::air());
+    assert_eq!(lod.cell(0, 1, 0), block("dirt"));
+    assert_eq!(lod.cell(7, 7, 7), Block::air());
+}
+
+#[test]
+fn expanded_cells_fill_the_blocks_they_cover() {
+    common::register_blocks();
+    let mut blocks = filled(Block::air());
+    for x in 0..CHUNK_SIZE {
+        for y in 0..4 {
+            for z in 0..CHUNK_SIZE {
+                blocks[x][y][z] = block("stone");
+            }
+        }
+    }
+
+    for &scale in LOD_SCALES.iter() {
+        let expanded = LodChunk::new((0, 0, 0), &blocks, scale).expand();
+        // Half of the first cell is stone at scale 8, which is enough to fill it.
+        let height = scale.max(4);
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let expected = if y < height {
+                        block("stone")
+                    } else {
+                        Block::air()
+                    };
+                    assert_eq!(expanded[x][y][z], expected, "scale {}", scale);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn scales_must_divide_chunks() {
+    common::register_blocks();
+    LodChunk::new((0, 0, 0), &filled(Block::air()), 3);
+}
+
+#[test]
+fn scale_doubles_with_distance() {
+    let scales: Vec<_> = (0..=30).map(|d| lod::lod_scale(d, 3)).collect();
+    assert!(scales[..=3].iter().all(|&s| s == 1));
+    assert!(scales[4..=6].iter().all(|&s| s == 2));
+    assert!(scales[7..=12].iter().all(|&s| s == 4));
+    assert!(scales[13..].iter().all(|&s| s == 8));
+}
+
+#[test]
+fn rings_surround_the_full_detail_chunks() {
+    let full = |(x, y, z): (i32, i32, i32)| x.abs() <= 3 && z.abs() <= 3 && y.abs() <= 1;
+    let rings = lod::lod_rings((0, 0, 0), 3, 12, -1..=1, full);
+
+    assert_eq!(rings.len(), (25 * 25 - 7 * 7) * 3);
+    assert!(!rings.contains_key(&(3, 0, -3)));
+    assert_eq!(rings[&(4, 1, 0)], 2);
+    assert_eq!(rings[&(0, -1, -7)], 4);
+    assert_eq!(rings[&(12, 0, 12)], 4);
+    assert!(!rings.contains_key(&(13, 0, 0)));
+    assert!(!rings.contains_key(&(0, 2, 0)));
+
+    // Once the camera moves away, chunks close to it that are not loaded in full detail get the
+    // finest downsampled scale.
+    let rings = lod::lod_rings((20, 0, 0), 3, 12, 0..=0, full);
+    assert_eq!(rings[&(20, 0, 0)], 2);
+    assert_eq!(rings[&(8, 0, 0)], 4);
+    assert!(!rings.contains_key(&(3, 0, 0)));
+    assert_eq!(lod::chunk_distance((20, 0, 0), (3, 5, -2)), 17);
+}
+
+#[test]
+fn downsampled_chunks_draw_their_borders() {
+    common::register_blocks();
+    for &scale in LOD_SCALES.iter() {
+        let faces = LodChunk::new((0, 0, 0), &filled(block("stone")), scale).mesh();
+        assert_eq!(faces.len(), 6);
+        assert_eq!(area(&faces), [CHUNK_SIZE * CHUNK_SIZE; 6]);
+    }
+}
+
+#[test]
+fn downsampled_faces_are_whole_cells() {
+    common::register_blocks();
+    let mut blocks = filled(Block::air());
+    blocks[5][9][2] = block("stone");
+    blocks[4][8][3] = block("stone");
+    blocks[5][8][3] = block("stone");
+    blocks[4][9][2] = block("stone");
+
+    let faces = LodChunk::new((0, 0, 0), &blocks, 2).mesh();
+    assert_eq!(area(&faces), [4; 6]);
+    let up = faces
+        .iter()
+        .find(|face| face.direction == FaceDirection::Up)
+        .unwrap();
+    assert_eq!((up.x, up.y, up.z), (4, 9, 2));
+}
+
+#[test]
+fn coarser_meshes_have_fewer_faces() {
+    common::register_blocks();
+    let mut gen = PerlinChunkGenerator::from_seed(42);
+    let blocks = gen.generate(0, 0, 0);
+
+    let air = |_, _, _| Block::air();
+    let sky = |_, _, _| minecraft_rust::lighting::Light::SKY;
+    let mut count = mesh::greedy_mesh_chunk(&blocks, air, sky).len();
+    for &scale in LOD_SCALES.iter() {
+        let faces = LodChunk::new((0, 0, 0), &blocks, scale).mesh().len();
+        assert!(faces <= count, "{} faces at scale {}", faces, scale);
+        count = faces;
+    }
+}
+
+#[tokio::test]
+async fn lod_requests_leave_the_world_alone() {
+    common::register_blocks();
+    let dir = env::temp_dir().join("minecraft-rust-save-lod");
+    let _ = fs::remove_dir_all(&dir);
+    let save = WorldSave::open(&dir).unwrap();
+    let chunks = Mutex::new(HashMap::new());
+    let mut gen = PerlinChunkGenerator::from_seed(42);
+
+    let lod = net::lod_chunk(&chunks, &mut gen, &save, (3, 0, -2), 4).await;
+    assert_eq!(lod, LodChunk::new((3, 0, -2), &gen.generate(3, 0, -2), 4));
+    assert!(chunks.lock().await.is_empty());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn lod_requests_use_chunks_that_were_changed() {
+    common::register_blocks();
+    let dir = env::temp_dir().join("minecraft-rust-save-lod-changed");
+    let _ = fs::remove_dir_all(&dir);
+    let save = WorldSave::open(&dir).unwrap();
+    let mut gen = PerlinChunkGenerator::from_seed(42);
+
+    // A chunk in the save that no longer matches what the generator makes.
+    let mut void = VoidChunkGenerator;
+    save.store(&Chunk::new(0, 0, 0, &mut void)).unwrap();
+    let chunks = Mutex::new(HashMap::new());
+    let lod = net::lod_chunk(&chunks, &mut gen, &save, (0, 0, 0), 2).await;
+    assert_eq!(lod, LodChunk::new((0, 0, 0), &filled(Block::air()), 2));
+    assert!(chunks.lock().await.is_empty());
+
+    // A chunk in memory is used before the save.
+    let generated = Chunk::new(0, 0, 0, &mut gen);
+    let expected = LodChunk::new((0, 0, 0), generated.get_blocks(), 2);
+    chunks.lock().await.insert((0, 0, 0), generated);
+    let lod = net::lod_chunk(&chunks, &mut gen, &save, (0, 0, 0), 2).await;
+    assert_eq!(lod, expected);
+}
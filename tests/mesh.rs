@@ -0,0 +1,497 @@
+mod common;
+
+use std::collections::HashMap;
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection, RenderLayer, CHUNK_SIZE},
+    lighting::Light,
+    mesh::{self, Face},
+    model::FaceShape,
+    server::chunk::{ChunkGenerator, PerlinChunkGenerator},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn empty() -> Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]> {
+    Box::new([[[Block::air(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE])
+}
+
+fn stone() -> Block {
+    Block::get("stone").unwrap()
+}
+
+fn sky(_: isize, _: isize, _: isize) -> Light {
+    Light::SKY
+}
+
+#[test]
+fn empty_chunk_has_no_faces() {
+    common::register_blocks();
+    assert!(mesh::mesh_chunk(&empty(), |_, _, _| stone(), sky).is_empty());
+}
+
+#[test]
+fn single_block_has_six_faces() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[3][4][5] = stone();
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(faces.len(), 6);
+    for &direction in FaceDirection::ALL.iter() {
+        assert!(faces.contains(&Face {
+            direction,
+            block: stone(),
+            x: 3,
+            y: 4,
+            z: 5,
+            width: 1,
+            height: 1,
+            shape: FaceShape::FULL,
+            light: Light::SKY,
+            ao: mesh::UNOCCLUDED,
+        }));
+    }
+}
+
+#[test]
+fn touching_blocks_hide_shared_faces() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[3][4][5] = stone();
+    blocks[4][4][5] = stone();
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(faces.len(), 10);
+    assert!(!faces
+        .iter()
+        .any(|f| f.x == 3 && matches!(f.direction, FaceDirection::Front)));
+    assert!(!faces
+        .iter()
+        .any(|f| f.x == 4 && matches!(f.direction, FaceDirection::Back)));
+}
+
+#[test]
+fn full_chunk_only_has_border_faces() {
+    common::register_blocks();
+    let blocks = Box::new([[[stone(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(faces.len(), 6 * CHUNK_SIZE * CHUNK_SIZE);
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| stone(), sky);
+    assert!(faces.is_empty());
+}
+
+#[test]
+fn neighbour_is_asked_only_outside_the_chunk() {
+    common::register_blocks();
+    let blocks = Box::new([[[stone(); CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+    let size = CHUNK_SIZE as isize;
+
+    // Only the chunk above is loaded, so the top of the chunk is hidden.
+    let neighbour = |x, y, z| {
+        assert!(!((0..size).contains(&x) && (0..size).contains(&y) && (0..size).contains(&z)));
+        if y == size {
+            stone()
+        } else {
+            Block::air()
+        }
+    };
+    let faces = mesh::mesh_chunk(&blocks, neighbour, sky);
+    assert_eq!(faces.len(), 5 * CHUNK_SIZE * CHUNK_SIZE);
+    assert!(!faces
+        .iter()
+        .any(|f| matches!(f.direction, FaceDirection::Up)));
+}
+
+/// Asserts that the greedy mesh covers exactly the faces of the naive mesh, each exactly once.
+fn assert_same_surface<F, L>(
+    blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
+    neighbour: F,
+    light: L,
+) where
+    F: Fn(isize, isize, isize) -> Block + Copy,
+    L: Fn(isize, isize, isize) -> Light + Copy,
+{
+    fn count(faces: impl Iterator<Item = Face>) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for face in faces {
+            *counts.entry(format!("{:?}", face)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    let naive = mesh::mesh_chunk(blocks, neighbour, light);
+    let greedy = mesh::greedy_mesh_chunk(blocks, neighbour, light);
+    assert!(greedy.len() <= naive.len());
+    assert_eq!(
+        count(greedy.iter().flat_map(|face| face.unit_faces())),
+        count(naive.into_iter())
+    );
+}
+
+#[test]
+fn greedy_merges_flat_plane_into_one_face() {
+    common::register_blocks();
+    let grass = Block::get("grass").unwrap();
+    let mut blocks = empty();
+    for square in blocks.iter_mut() {
+        for line in square[0..1].iter_mut() {
+            for block in line.iter_mut() {
+                *block = grass;
+            }
+        }
+    }
+
+    let faces = mesh::greedy_mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    let up: Vec<_> = faces
+        .iter()
+        .filter(|f| matches!(f.direction, FaceDirection::Up))
+        .collect();
+    assert_eq!(up.len(), 1);
+    assert_eq!((up[0].width, up[0].height), (CHUNK_SIZE, CHUNK_SIZE));
+    assert_eq!(faces.len(), 6);
+    assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+}
+
+#[test]
+fn greedy_does_not_merge_different_blocks() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[0][0][0] = stone();
+    blocks[1][0][0] = Block::get("dirt").unwrap();
+
+    let faces = mesh::greedy_mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(faces.len(), 10);
+    assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+}
+
+#[test]
+fn greedy_covers_same_surface_as_naive_for_random_chunks() {
+    common::register_blocks();
+    let palette = [
+        Block::air(),
+        stone(),
+        Block::get("dirt").unwrap(),
+        Block::get("grass").unwrap(),
+        Block::get("glass").unwrap(),
+        Block::get("water").unwrap(),
+        Block::get("stone_slab").unwrap(),
+        Block::get("tall_grass_1").unwrap(),
+        Block::get("wood_fence").unwrap(),
+    ];
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+
+    for _ in 0..8 {
+        let mut blocks = empty();
+        for square in blocks.iter_mut() {
+            for line in square.iter_mut() {
+                for block in line.iter_mut() {
+                    *block = palette[rng.gen_range(0..palette.len())];
+                }
+            }
+        }
+
+        assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+        assert_same_surface(
+            &blocks,
+            |x, _, _| if x < 0 { stone() } else { Block::air() },
+            sky,
+        );
+        assert_same_surface(
+            &blocks,
+            |_, _, _| Block::air(),
+            |x, y, z| Light::new(((x + y * 3 + z) / 4).rem_euclid(16) as u8),
+        );
+    }
+}
+
+#[test]
+fn greedy_covers_same_surface_as_naive_for_terrain() {
+    common::register_blocks();
+    let mut gen = PerlinChunkGenerator::from_seed(42);
+
+    for &(x, y, z) in [(0, 0, 0), (0, -1, 0), (-1, -1, 2), (3, -2, -1)].iter() {
+        let blocks = gen.generate(x, y, z);
+        assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+    }
+}
+
+#[test]
+fn transparent_blocks_do_not_hide_other_blocks() {
+    common::register_blocks();
+    let glass = Block::get("glass").unwrap();
+    let water = Block::get("water").unwrap();
+    let mut blocks = empty();
+    blocks[5][5][5] = stone();
+    blocks[6][5][5] = glass;
+    blocks[5][6][5] = water;
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    let has_face = |block, direction: FaceDirection, x, y, z| {
+        faces
+            .iter()
+            .any(|f| f.block == block && f.direction == direction && (f.x, f.y, f.z) == (x, y, z))
+    };
+
+    assert!(has_face(stone(), FaceDirection::Front, 5, 5, 5));
+    assert!(has_face(stone(), FaceDirection::Up, 5, 5, 5));
+    assert!(!has_face(glass, FaceDirection::Back, 6, 5, 5));
+    assert!(!has_face(water, FaceDirection::Down, 5, 6, 5));
+    assert_eq!(faces.len(), 6 + 5 + 5);
+}
+
+#[test]
+fn faces_between_the_same_transparent_block_are_hidden() {
+    common::register_blocks();
+    for name in ["glass", "leaves", "water"].iter() {
+        let block = Block::get(name).unwrap();
+        let mut blocks = empty();
+        blocks[5][5][5] = block;
+        blocks[5][5][6] = block;
+
+        let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+        assert_eq!(faces.len(), 10, "{}", name);
+    }
+
+    let mut blocks = empty();
+    blocks[5][5][5] = Block::get("glass").unwrap();
+    blocks[5][5][6] = Block::get("water").unwrap();
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(faces.len(), 12);
+}
+
+#[test]
+fn render_layers() {
+    common::register_blocks();
+    assert_eq!(stone().render_layer(), Some(RenderLayer::Opaque));
+    assert_eq!(
+        Block::get("glass").unwrap().render_layer(),
+        Some(RenderLayer::Cutout)
+    );
+    assert_eq!(
+        Block::get("leaves").unwrap().render_layer(),
+        Some(RenderLayer::Cutout)
+    );
+    assert_eq!(
+        Block::get("water").unwrap().render_layer(),
+        Some(RenderLayer::Translucent)
+    );
+    assert_eq!(Block::invalid().render_layer(), None);
+}
+
+#[test]
+fn partial_models_do_not_hide_their_neighbours() {
+    common::register_blocks();
+    let slab = Block::get("stone_slab").unwrap();
+    let plant = Block::get("tall_grass_1").unwrap();
+    let mut blocks = empty();
+    blocks[5][5][5] = stone();
+    blocks[6][5][5] = slab;
+    blocks[5][6][5] = slab;
+    blocks[4][5][5] = plant;
+
+    let faces = mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    let has_face = |block, direction, x, y, z| {
+        faces
+            .iter()
+            .any(|f| f.block == block && f.direction == direction && (f.x, f.y, f.z) == (x, y, z))
+    };
+
+    // Only the bottom of the slab above covers a whole side of the stone.
+    let stone_faces: Vec<_> = faces.iter().filter(|f| f.block == stone()).collect();
+    assert_eq!(stone_faces.len(), 5);
+    assert!(!has_face(stone(), FaceDirection::Up, 5, 5, 5));
+    assert!(has_face(stone(), FaceDirection::Front, 5, 5, 5));
+    assert!(has_face(stone(), FaceDirection::Back, 5, 5, 5));
+
+    // The stone hides the faces of the slabs touching it.
+    assert!(!has_face(slab, FaceDirection::Back, 6, 5, 5));
+    assert!(!has_face(slab, FaceDirection::Down, 5, 6, 5));
+    assert!(has_face(slab, FaceDirection::Up, 6, 5, 5));
+
+    let plant_faces: Vec<_> = faces.iter().filter(|f| f.block == plant).collect();
+    assert_eq!(plant_faces.len(), 4);
+    assert!(plant_faces
+        .iter()
+        .all(|f| matches!(f.shape, FaceShape::Diagonal(_))));
+}
+
+#[test]
+fn greedy_only_merges_full_faces() {
+    common::register_blocks();
+    let slab = Block::get("stone_slab").unwrap();
+    let mut blocks = empty();
+    for square in blocks.iter_mut() {
+        for line in square[0..1].iter_mut() {
+            for block in line.iter_mut() {
+                *block = slab;
+            }
+        }
+    }
+
+    let faces = mesh::greedy_mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    assert_eq!(
+        faces
+            .iter()
+            .filter(|f| f.direction == FaceDirection::Up)
+            .count(),
+        CHUNK_SIZE * CHUNK_SIZE
+    );
+    assert_eq!(
+        faces
+            .iter()
+            .filter(|f| f.direction == FaceDirection::Down)
+            .count(),
+        1
+    );
+    assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+}
+
+#[test]
+fn faces_take_the_light_of_the_block_they_look_into() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[3][4][5] = stone();
+    blocks[8][0][8] = Block::get("tall_grass_1").unwrap();
+
+    let light = |x: isize, y: isize, z: isize| Light::new((x + 2 * y + 4 * z).rem_euclid(16) as u8);
+    for face in mesh::mesh_chunk(&blocks, |_, _, _| Block::air(), light) {
+        let expected = if face.block == stone() {
+            let [dx, dy, dz] = face.direction.offset();
+            light(3 + dx, 4 + dy, 5 + dz)
+        } else {
+            light(8, 0, 8)
+        };
+        assert_eq!(face.light, expected);
+    }
+}
+
+#[test]
+fn greedy_does_not_merge_differently_lit_faces() {
+    common::register_blocks();
+    let mut blocks = empty();
+    for square in blocks.iter_mut() {
+        for line in square[0..1].iter_mut() {
+            for block in line.iter_mut() {
+                *block = stone();
+            }
+        }
+    }
+
+    // The half of the floor with x < 8 is in the shade.
+    let light = |x, _, _| Light::new(if x < 8 { 4 } else { 15 });
+    let faces = mesh::greedy_mesh_chunk(&blocks, |_, _, _| Block::air(), light);
+    let up: Vec<_> = faces
+        .iter()
+        .filter(|f| f.direction == FaceDirection::Up)
+        .collect();
+    assert_eq!(up.len(), 2);
+    for face in up {
+        assert_eq!((face.width, face.height), (CHUNK_SIZE / 2, CHUNK_SIZE));
+    }
+    assert_same_surface(&blocks, |_, _, _| Block::air(), light);
+}
+
+fn top_face_ao(blocks: &[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]) -> [u8; 4] {
+    mesh::mesh_chunk(blocks, |_, _, _| Block::air(), sky)
+        .into_iter()
+        .find(|f| (f.x, f.y, f.z) == (5, 5, 5) && f.direction == FaceDirection::Up)
+        .unwrap()
+        .ao
+}
+
+#[test]
+fn corners_next_to_blocks_are_occluded() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[5][5][5] = stone();
+    assert_eq!(top_face_ao(&blocks), mesh::UNOCCLUDED);
+
+    // A block above the +x side of the top face darkens both of its +x corners.
+    blocks[6][6][5] = stone();
+    assert_eq!(top_face_ao(&blocks), [3, 2, 3, 2]);
+
+    // With another one above the +z side, the corner between them is fully occluded.
+    blocks[5][6][6] = stone();
+    assert_eq!(top_face_ao(&blocks), [3, 2, 2, 0]);
+
+    // A block only touching the corner diagonally darkens just that corner.
+    let mut blocks = empty();
+    blocks[5][5][5] = stone();
+    blocks[4][6][4] = stone();
+    assert_eq!(top_face_ao(&blocks), [2, 3, 3, 3]);
+
+    // Blocks that let light through do not occlude.
+    blocks[4][6][4] = Block::get("glass").unwrap();
+    assert_eq!(top_face_ao(&blocks), mesh::UNOCCLUDED);
+}
+
+#[test]
+fn occlusion_looks_into_neighbouring_chunks() {
+    common::register_blocks();
+    let mut blocks = empty();
+    blocks[15][15][0] = stone();
+    let size = CHUNK_SIZE as isize;
+
+    // Only the block diagonally across the top +x -z corner, two chunks away, is solid.
+    let faces = mesh::mesh_chunk(
+        &blocks,
+        |x, y, z| {
+            if (x, y, z) == (size, size, -1) {
+                stone()
+            } else {
+                Block::air()
+            }
+        },
+        sky,
+    );
+    let up = faces
+        .iter()
+        .find(|f| f.direction == FaceDirection::Up)
+        .unwrap();
+    assert_eq!(up.ao, [3, 2, 3, 3]);
+}
+
+#[test]
+fn greedy_does_not_merge_occluded_faces() {
+    common::register_blocks();
+    let mut blocks = empty();
+    for square in blocks.iter_mut() {
+        for line in square[0..1].iter_mut() {
+            for block in line.iter_mut() {
+                *block = stone();
+            }
+        }
+    }
+    // A wall along x == 0 shades the floor next to it.
+    for line in blocks[0].iter_mut() {
+        for block in line.iter_mut() {
+            *block = stone();
+        }
+    }
+
+    let faces = mesh::greedy_mesh_chunk(&blocks, |_, _, _| Block::air(), sky);
+    let up: Vec<_> = faces
+        .iter()
+        .filter(|f| f.direction == FaceDirection::Up && f.y == 0)
+        .collect();
+    assert_eq!(up.len(), CHUNK_SIZE + 1);
+    for face in up {
+        if face.x == 1 {
+            assert_eq!((face.width, face.height), (1, 1));
+            // Corners at the ends of the wall have one occluding block, the others two.
+            let wall_side = if face.z == 0 {
+                [2, 1]
+            } else if face.z == CHUNK_SIZE - 1 {
+                [1, 2]
+            } else {
+                [1, 1]
+            };
+            assert_eq!(face.ao, [wall_side[0], 3, wall_side[1], 3]);
+        } else {
+            assert_eq!(face.ao, mesh::UNOCCLUDED);
+        }
+    }
+    assert_same_surface(&blocks, |_, _, _| Block::air(), sky);
+}
@@ -0,0 +1,130 @@
+//! Times the shared mesher against the per-block mesher the client used before it, on generated
+//! terrain. The shared mesher also works out render layers, model shapes, light and ambient
+//! occlusion for every face, which the per-block mesher never did, so the comparison shows what
+//! that costs rather than a like for like speed up.
+//!
+//! The timings are only printed, so the test is ignored by default; run it with
+//!
+//! ```text
+//! cargo test --release --test mesh_bench -- --ignored --nocapture
+//! ```
+
+mod common;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
+    lighting::Light,
+    mesh,
+    server::chunk::{ChunkGenerator, PerlinChunkGenerator},
+};
+
+type Blocks = Box<[[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>;
+
+const ROUNDS: u32 = 20;
+
+/// Generates the chunks around the origin, keyed by chunk coordinates.
+fn terrain() -> HashMap<(i32, i32, i32), Blocks> {
+    let mut gen = PerlinChunkGenerator::from_seed(42);
+    let mut chunks = HashMap::new();
+    for x in -2..=2 {
+        for y in -2..=1 {
+            for z in -2..=2 {
+                chunks.insert((x, y, z), gen.generate(x, y, z));
+            }
+        }
+    }
+    chunks
+}
+
+/// Returns the block at local coordinates of the given chunk, which may be outside of it, looking
+/// neighbouring chunks up in the map like the client's chunk map did.
+fn block_at(
+    chunks: &HashMap<(i32, i32, i32), Blocks>,
+    (chunk_x, chunk_y, chunk_z): (i32, i32, i32),
+    x: isize,
+    y: isize,
+    z: isize,
+) -> Block {
+    let size = CHUNK_SIZE as isize;
+    let chunk = (
+        chunk_x + x.div_euclid(size) as i32,
+        chunk_y + y.div_euclid(size) as i32,
+        chunk_z + z.div_euclid(size) as i32,
+    );
+    chunks.get(&chunk).map_or_else(Block::air, |blocks| {
+        blocks[x.rem_euclid(size) as usize][y.rem_euclid(size) as usize]
+            [z.rem_euclid(size) as usize]
+    })
+}
+
+/// The mesher as the client had it before the shared mesher: every block looks up each of its
+/// six neighbours through the chunk map, and a face is kept when the neighbour is air.
+fn per_block_mesh(
+    chunks: &HashMap<(i32, i32, i32), Blocks>,
+    coords: (i32, i32, i32),
+) -> Vec<(FaceDirection, usize, usize, usize)> {
+    let mut faces = vec![];
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let (bx, by, bz) = (x as isize, y as isize, z as isize);
+                let block = block_at(chunks, coords, bx, by, bz);
+                if !block.is_solid().unwrap_or(false) {
+                    continue;
+                }
+
+                for &direction in FaceDirection::ALL.iter() {
+                    let [dx, dy, dz] = direction.offset();
+                    if block_at(chunks, coords, bx + dx, by + dy, bz + dz) == Block::air() {
+                        faces.push((direction, x, y, z));
+                    }
+                }
+            }
+        }
+    }
+    faces
+}
+
+/// Runs `mesh` over every chunk `ROUNDS` times and returns the time per chunk and the number of
+/// faces in one round.
+fn time<F>(chunks: &HashMap<(i32, i32, i32), Blocks>, mut mesh: F) -> (Duration, usize)
+where
+    F: FnMut((i32, i32, i32)) -> usize,
+{
+    let start = Instant::now();
+    let mut faces = 0;
+    for _ in 0..ROUNDS {
+        faces = chunks.keys().map(|&coords| mesh(coords)).sum();
+    }
+    (start.elapsed() / (ROUNDS * chunks.len() as u32), faces)
+}
+
+#[test]
+#[ignore]
+fn mesher_timings() {
+    common::register_blocks();
+    let chunks = terrain();
+    let sky = |_, _, _| Light::SKY;
+
+    let per_block = time(&chunks, |coords| per_block_mesh(&chunks, coords).len());
+    let shared = time(&chunks, |coords| {
+        let neighbour = |x, y, z| block_at(&chunks, coords, x, y, z);
+        mesh::mesh_chunk(&chunks[&coords], neighbour, sky).len()
+    });
+    let greedy = time(&chunks, |coords| {
+        let neighbour = |x, y, z| block_at(&chunks, coords, x, y, z);
+        mesh::greedy_mesh_chunk(&chunks[&coords], neighbour, sky).len()
+    });
+
+    println!("{} chunks, per chunk:", chunks.len());
+    for (name, (elapsed, faces)) in [
+        ("per block", per_block),
+        ("shared", shared),
+        ("shared, greedy", greedy),
+    ] {
+        println!("{:>16}: {:>10.2?} {:>8} faces", name, elapsed, faces);
+    }
+}
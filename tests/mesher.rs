@@ -0,0 +1,100 @@
+mod common;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    client::mesher::{ChunkSnapshot, MeshScheduler},
+    lighting::Light,
+    mesh::MeshMode,
+    visibility::Visibility,
+};
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+#[test]
+fn closest_dirty_chunk_goes_first() {
+    let mut scheduler = MeshScheduler::new();
+    scheduler.mark_dirty((5, 0, 0));
+    scheduler.mark_dirty((1, 1, 0));
+    scheduler.mark_dirty((0, 0, -3));
+
+    assert_eq!(scheduler.next((0, 0, 0)), Some((1, 1, 0)));
+    // The camera moved closer to the furthest chunk.
+    assert_eq!(scheduler.next((4, 0, 0)), Some((5, 0, 0)));
+    assert_eq!(scheduler.next((4, 0, 0)), Some((0, 0, -3)));
+    assert_eq!(scheduler.next((4, 0, 0)), None);
+    assert_eq!(scheduler.meshing(), 3);
+}
+
+#[test]
+fn repeated_requests_are_coalesced() {
+    let mut scheduler = MeshScheduler::new();
+    for _ in 0..3 {
+        scheduler.mark_dirty((0, 0, 0));
+    }
+    assert!(scheduler.is_dirty((0, 0, 0)));
+    assert_eq!(scheduler.next((0, 0, 0)), Some((0, 0, 0)));
+    assert!(!scheduler.is_dirty((0, 0, 0)));
+    assert_eq!(scheduler.next((0, 0, 0)), None);
+}
+
+#[test]
+fn chunks_are_not_meshed_twice_at_once() {
+    let mut scheduler = MeshScheduler::new();
+    scheduler.mark_dirty((0, 0, 0));
+    assert_eq!(scheduler.next((0, 0, 0)), Some((0, 0, 0)));
+
+    // Changed while being meshed: handed out again only once the first mesh is done.
+    scheduler.mark_dirty((0, 0, 0));
+    scheduler.mark_dirty((2, 0, 0));
+    assert_eq!(scheduler.next((0, 0, 0)), Some((2, 0, 0)));
+    assert_eq!(scheduler.next((0, 0, 0)), None);
+
+    scheduler.finish((0, 0, 0));
+    assert_eq!(scheduler.meshing(), 1);
+    assert_eq!(scheduler.next((0, 0, 0)), Some((0, 0, 0)));
+}
+
+#[test]
+fn scheduler_is_idle_once_every_chunk_is_meshed() {
+    let mut scheduler = MeshScheduler::new();
+    assert!(scheduler.is_idle());
+    scheduler.mark_dirty((0, 0, 0));
+    assert!(!scheduler.is_idle());
+    assert_eq!(scheduler.next((0, 0, 0)), Some((0, 0, 0)));
+    assert!(!scheduler.is_idle());
+    scheduler.finish((0, 0, 0));
+    assert!(scheduler.is_idle());
+}
+
+#[test]
+fn snapshots_copy_the_blocks_around_the_chunk() {
+    common::register_blocks();
+    let stone = Block::get("stone").unwrap();
+    let blocks: Box<Blocks> = Box::new([[[stone; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]);
+    let edge = CHUNK_SIZE as isize;
+    // Stone below the chunk, air everywhere else around it.
+    let neighbour = |_: isize, y: isize, _: isize| if y < 0 { stone } else { Block::air() };
+    let light = |x: isize, y: isize, z: isize| {
+        if x == -1 && y == 0 && z == 0 {
+            Light::SKY
+        } else {
+            Light::default()
+        }
+    };
+    let snapshot = ChunkSnapshot::new((1, 2, 3), MeshMode::Greedy, &blocks, neighbour, light);
+
+    assert_eq!(snapshot.coords(), (1, 2, 3));
+    assert_eq!(snapshot.block(0, 0, 0), stone);
+    assert_eq!(snapshot.block(-1, -1, -1), stone);
+    assert_eq!(snapshot.block(edge, edge, edge), Block::air());
+    assert_eq!(snapshot.light(-1, 0, 0), Light::SKY);
+    assert_eq!(snapshot.light(0, 0, 0), Light::default());
+
+    // Meshing the snapshot gives the same faces as meshing the chunk in place.
+    let mesh = snapshot.mesh();
+    let faces = MeshMode::Greedy.mesh(&blocks, neighbour, light);
+    assert!(!faces.is_empty());
+    assert_eq!(mesh.solid.len(), faces.len());
+    assert!(mesh.translucent.is_empty());
+    assert_eq!(mesh.visibility, Visibility::CLOSED);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection},
+    coords,
+    model::{BlockModel, FaceShape, ModelBox},
+};
+
+#[test]
+fn cube_has_six_full_faces() {
+    let faces = BlockModel::Cube.faces();
+    assert_eq!(faces.len(), 6);
+    for &direction in FaceDirection::ALL.iter() {
+        assert!(faces.contains(&(direction, FaceShape::FULL)));
+        assert!(BlockModel::Cube.covers(direction));
+    }
+}
+
+#[test]
+fn slab_only_covers_its_bottom() {
+    for &direction in FaceDirection::ALL.iter() {
+        assert_eq!(
+            BlockModel::Slab.covers(direction),
+            direction == FaceDirection::Down
+        );
+    }
+
+    let faces = BlockModel::Slab.faces();
+    assert!(faces.contains(&(
+        FaceDirection::Up,
+        FaceShape::Rect {
+            u: [0, 16],
+            v: [0, 16],
+            depth: 8,
+        }
+    )));
+    assert!(faces.contains(&(
+        FaceDirection::Front,
+        FaceShape::Rect {
+            u: [0, 16],
+            v: [0, 8],
+            depth: 0,
+        }
+    )));
+}
+
+#[test]
+fn box_faces_are_measured_from_their_side() {
+    let post = ModelBox::new([6, 0, 4], [10, 16, 12]);
+    assert_eq!(
+        post.face(FaceDirection::Front),
+        FaceShape::Rect {
+            u: [4, 12],
+            v: [0, 16],
+            depth: 6,
+        }
+    );
+    assert_eq!(
+        post.face(FaceDirection::Right),
+        FaceShape::Rect {
+            u: [6, 10],
+            v: [0, 16],
+            depth: 4,
+        }
+    );
+    assert_eq!(
+        post.face(FaceDirection::Down),
+        FaceShape::Rect {
+            u: [6, 10],
+            v: [4, 12],
+            depth: 0,
+        }
+    );
+}
+
+#[test]
+fn cross_has_two_sided_diagonals_and_no_boxes() {
+    let faces = BlockModel::Cross.faces();
+    assert_eq!(faces.len(), 4);
+    assert!(faces.iter().all(|(_, shape)| !shape.is_on_side()));
+    assert!(BlockModel::Cross.collision_boxes().is_empty());
+    for &direction in FaceDirection::ALL.iter() {
+        assert!(!BlockModel::Cross.covers(direction));
+    }
+}
+
+#[test]
+fn collision_boxes_follow_the_model() {
+    common::register_blocks();
+    assert_eq!(
+        Block::get("stone").unwrap().collision_boxes(),
+        BlockModel::Cube.boxes()
+    );
+    assert_eq!(
+        Block::get("stone_slab").unwrap().collision_boxes(),
+        BlockModel::Slab.boxes()
+    );
+    assert!(Block::get("tall_grass_1")
+        .unwrap()
+        .collision_boxes()
+        .is_empty());
+    assert!(Block::get("water").unwrap().collision_boxes().is_empty());
+    assert!(Block::air().collision_boxes().is_empty());
+}
+
+#[test]
+fn every_block_but_air_can_be_selected() {
+    common::register_blocks();
+    // Blocks without collision boxes are still targeted by their shape.
+    assert_eq!(
+        Block::get("tall_grass_1").unwrap().selection_boxes(),
+        BlockModel::Cube.boxes()
+    );
+    assert_eq!(
+        Block::get("track").unwrap().selection_boxes(),
+        BlockModel::Flat.boxes()
+    );
+    assert_eq!(
+        Block::get("water").unwrap().selection_boxes(),
+        BlockModel::Cube.boxes()
+    );
+    assert_eq!(
+        Block::get("stone_slab").unwrap().selection_boxes(),
+        BlockModel::Slab.boxes()
+    );
+    assert!(Block::air().selection_boxes().is_empty());
+}
+
+#[test]
+fn model_box_aabb_matches_block_aabb_for_cubes() {
+    for &block in [[0, 0, 0], [3, -2, 7], [-16, 15, -1]].iter() {
+        let cube = coords::model_box_aabb(block, &BlockModel::Cube.boxes()[0]);
+        let expected = coords::block_aabb(block);
+        for i in 0..3 {
+            assert!((cube.centre[i] - expected.centre[i]).abs() < 1e-6);
+            assert!((cube.extents[i] - expected.extents[i]).abs() < 1e-6);
+        }
+    }
+
+    let slab = coords::model_box_aabb([0, 0, 0], &BlockModel::Slab.boxes()[0]);
+    assert!((slab.centre[1] - -0.125).abs() < 1e-6);
+    assert!((slab.extents[1] - 0.125).abs() < 1e-6);
+}
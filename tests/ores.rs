@@ -0,0 +1,113 @@
+mod common;
+
+use std::collections::HashMap;
+
+use minecraft_rust::{
+    blocks::{Block, CHUNK_SIZE},
+    server::{
+        chunk::{ChunkGenerator, PerlinChunkGenerator},
+        ores::OreTable,
+    },
+};
+
+const SEED: u32 = 1337;
+
+/// Counts every ore block in a 8x4x8 chunk region, keyed by ore name, along with the lowest and
+/// highest world y level each ore was found at.
+fn count_ores(
+    gen: &mut PerlinChunkGenerator,
+    table: &OreTable,
+) -> HashMap<String, (u32, i32, i32)> {
+    let mut counts = HashMap::new();
+
+    for chunk_x in -4..4 {
+        for chunk_y in -4..0 {
+            for chunk_z in -4..4 {
+                let blocks = gen.generate(chunk_x, chunk_y, chunk_z);
+                for square in blocks.iter() {
+                    for (y, line) in square.iter().enumerate() {
+                        let world_y = chunk_y * CHUNK_SIZE as i32 + y as i32;
+                        for block in line.iter() {
+                            for ore in table.ores.iter() {
+                                if Block::get(&ore.block) == Some(*block) {
+                                    let entry = counts.entry(ore.block.clone()).or_insert((
+                                        0,
+                                        i32::MAX,
+                                        i32::MIN,
+                                    ));
+                                    entry.0 += 1;
+                                    entry.1 = entry.1.min(world_y);
+                                    entry.2 = entry.2.max(world_y);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    counts
+}
+
+#[test]
+fn ore_counts_follow_table() {
+    common::register_blocks();
+    let table = OreTable::default();
+    let mut gen = PerlinChunkGenerator::with_ores(SEED, table.clone());
+    let counts = count_ores(&mut gen, &table);
+
+    for ore in table.ores.iter() {
+        let &(count, min_y, max_y) = counts
+            .get(&ore.block)
+            .unwrap_or_else(|| panic!("no {} generated", ore.block));
+
+        // Veins are random walks from their starting point, so they can stray a little outside of
+        // the configured range.
+        let slack = ore.vein_size as i32;
+        assert!(count > 0, "no {} generated", ore.block);
+        assert!(
+            min_y >= ore.min_y - slack,
+            "{} too low at {}",
+            ore.block,
+            min_y
+        );
+        assert!(
+            max_y <= ore.max_y + slack,
+            "{} too high at {}",
+            ore.block,
+            max_y
+        );
+    }
+
+    let coal = counts["coal_ore"].0;
+    let diamond = counts["diamond_ore"].0;
+    assert!(
+        coal > diamond * 4,
+        "coal ({}) should be far more common than diamond ({})",
+        coal,
+        diamond
+    );
+}
+
+#[test]
+fn ore_placement_is_deterministic() {
+    common::register_blocks();
+    let table = OreTable::default();
+    let mut gen_a = PerlinChunkGenerator::with_ores(SEED, table.clone());
+    let mut gen_b = PerlinChunkGenerator::with_ores(SEED, table.clone());
+
+    assert_eq!(
+        count_ores(&mut gen_a, &table),
+        count_ores(&mut gen_b, &table)
+    );
+}
+
+#[test]
+fn empty_table_places_no_ore() {
+    common::register_blocks();
+    let default = OreTable::default();
+    let mut gen = PerlinChunkGenerator::with_ores(SEED, OreTable { ores: vec![] });
+
+    assert!(count_ores(&mut gen, &default).is_empty());
+}
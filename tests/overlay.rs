@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use minecraft_rust::{
+    client::overlay::{self, BreakProgress, BREAK_STAGES, BREAK_TIME},
+    coords,
+    model::BlockModel,
+};
+
+const BLOCK: [i32; 3] = [1, 2, 3];
+const OTHER: [i32; 3] = [1, 3, 3];
+
+#[test]
+fn holding_the_button_breaks_the_block() {
+    let mut breaking = BreakProgress::default();
+    assert_eq!(breaking.stage(), None);
+
+    let step = BREAK_TIME / 4;
+    assert_eq!(breaking.update(Some(BLOCK), true, step), None);
+    assert_eq!(breaking.stage(), Some((BLOCK, BREAK_STAGES / 4)));
+    assert_eq!(breaking.update(Some(BLOCK), true, step), None);
+    assert_eq!(breaking.update(Some(BLOCK), true, step), None);
+    assert_eq!(breaking.stage(), Some((BLOCK, BREAK_STAGES * 3 / 4)));
+
+    assert_eq!(breaking.update(Some(BLOCK), true, step), Some(BLOCK));
+    assert_eq!(breaking.stage(), None);
+}
+
+#[test]
+fn breaking_starts_over() {
+    let mut breaking = BreakProgress::default();
+    let step = BREAK_TIME * 3 / 4;
+
+    // Releasing the button forgets the progress.
+    breaking.update(Some(BLOCK), true, step);
+    assert_eq!(breaking.update(Some(BLOCK), false, step), None);
+    assert_eq!(breaking.stage(), None);
+
+    // So does looking at another block.
+    breaking.update(Some(BLOCK), true, step);
+    assert_eq!(breaking.update(Some(OTHER), true, step), None);
+    assert_eq!(breaking.stage(), Some((OTHER, BREAK_STAGES * 3 / 4)));
+    assert_eq!(breaking.update(None, true, step), None);
+    assert_eq!(breaking.stage(), None);
+}
+
+#[test]
+fn last_stage_is_shown_until_the_block_breaks() {
+    let mut breaking = BreakProgress::default();
+    breaking.update(Some(BLOCK), true, BREAK_TIME - Duration::from_millis(1));
+    assert_eq!(breaking.stage(), Some((BLOCK, BREAK_STAGES - 1)));
+}
+
+#[test]
+fn overlay_covers_the_boxes_of_the_block() {
+    let centre = coords::block_centre(BLOCK);
+    let models = overlay::box_models(BLOCK, &BlockModel::Slab.boxes());
+    assert_eq!(models.len(), 1);
+
+    // The unit cube is stretched over the bottom half of the block, a little bigger than it.
+    let [x, y, z, _] = models[0][3];
+    assert_eq!([x, z], [centre[0], centre[2]]);
+    assert!((y - (centre[1] - coords::BLOCK_SIZE / 4.0)).abs() < 1e-6);
+    let (width, height) = (models[0][0][0], models[0][1][1]);
+    assert!(width > coords::BLOCK_SIZE && width < coords::BLOCK_SIZE * 1.1);
+    assert!(height > coords::BLOCK_SIZE / 2.0 && height < coords::BLOCK_SIZE * 0.6);
+
+    assert!(overlay::box_models(BLOCK, &[]).is_empty());
+}
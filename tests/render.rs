@@ -0,0 +1,313 @@
+//! Renders fixed scenes without a window and compares them with golden images in
+//! `tests/golden`, so that shader and mesher changes can be checked on machines without a GPU.
+//! Each scene is seen from the start of the camera path of the same name in that directory.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the golden images again after an intended change. The
+//! tests need software OpenGL (Mesa's surfaceless EGL platform) and fail without it, unless
+//! `SKIP_RENDER_TESTS=1` is set to skip them on machines that cannot provide it.
+
+mod common;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use glium::backend::Context;
+use glium::{Program, Surface};
+use image::RgbaImage;
+
+use minecraft_rust::{
+    blocks::Block,
+    client::{
+        camera::Camera,
+        capture::CameraPath,
+        chunk::{self, Chunk, Mesh},
+        headless::{self, Offscreen},
+        player::Player,
+        renderer::ChunkRenderer,
+        settings::{ClientSettings, FogSettings},
+        store::ChunkStore,
+    },
+    clock::WorldClock,
+    lighting::LightMap,
+    mesh::MeshMode,
+    server::chunk::{Chunk as ServerChunk, ChunkGenerator, PerlinChunkGenerator},
+};
+
+const CHUNKS_VERTEX_SHADER: &str = include_str!("../src/client/shaders/chunks-vertex.glsl");
+const CHUNKS_FRAGMENT_SHADER: &str = include_str!("../src/client/shaders/chunks-fragment.glsl");
+const ENTITY_VERTEX_SHADER: &str = include_str!("../src/client/shaders/entity-vertex.glsl");
+const ENTITY_FRAGMENT_SHADER: &str = include_str!("../src/client/shaders/entity-fragment.glsl");
+
+const SIZE: (u32, u32) = (320, 240);
+const SEED: u32 = 42;
+/// Largest difference in any channel for two pixels to count as the same, which leaves room for
+/// rounding differences between versions of the software rasteriser.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Largest share of pixels that may differ from the golden image.
+const PIXEL_TOLERANCE: f64 = 0.005;
+
+/// Returns a headless context to render with, or `None` if `SKIP_RENDER_TESTS` is set.
+fn context() -> Option<Rc<Context>> {
+    if env::var_os("SKIP_RENDER_TESTS").is_some() {
+        eprintln!("skipping, SKIP_RENDER_TESTS is set");
+        return None;
+    }
+    match headless::context(SIZE) {
+        Ok(context) => Some(context),
+        Err(e) => panic!(
+            "no headless OpenGL context, set SKIP_RENDER_TESTS=1 to skip: {}",
+            e
+        ),
+    }
+}
+
+/// Generates, lights and meshes the chunks around the origin, as the client does when they
+/// arrive.
+fn load_chunks(context: &Rc<Context>) -> ChunkRenderer {
+    let mut gen = PerlinChunkGenerator::from_seed(SEED);
+    let store = ChunkStore::new();
+    let mut light = LightMap::new();
+    let mut loaded = vec![];
+    for x in -1..=1 {
+        for y in -1..=0 {
+            for z in -1..=1 {
+                store.insert(Chunk::from_server_chunk(ServerChunk::new(
+                    x, y, z, &mut gen,
+                )));
+                light.add_chunk((x, y, z), |block| store.block(block));
+                loaded.push((x, y, z));
+            }
+        }
+    }
+
+    let mut renderer = ChunkRenderer::new(context);
+    for &coords in loaded.iter() {
+        let snapshot = store
+            .get(coords)
+            .unwrap()
+            .snapshot(&store, &light, MeshMode::Greedy);
+        renderer.set_mesh(context, coords, snapshot.mesh());
+        renderer.set_lights(coords, &chunk::select_chunk_lights(&store, coords));
+    }
+    renderer
+}
+
+/// Draws the chunks around the origin with a player standing on them, as seen from `camera`.
+fn render_terrain(
+    context: &Rc<Context>,
+    camera: &Camera,
+    clock: &WorldClock,
+    settings: &ClientSettings,
+) -> RgbaImage {
+    common::register_blocks();
+    let textures = Block::generate_atlas(context, None).unwrap();
+    let chunks_program =
+        Program::from_source(context, CHUNKS_VERTEX_SHADER, CHUNKS_FRAGMENT_SHADER, None).unwrap();
+    let entity_program =
+        Program::from_source(context, ENTITY_VERTEX_SHADER, ENTITY_FRAGMENT_SHADER, None).unwrap();
+    let square = Mesh::square(context);
+    let mut renderer = load_chunks(context);
+    let player = Player::new("golden".to_string(), [2.0, 1.0, 2.0], context);
+
+    let params = glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::draw_parameters::DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise,
+        ..Default::default()
+    };
+    let translucent_params = glium::DrawParameters {
+        depth: glium::Depth {
+            write: false,
+            ..params.depth
+        },
+        blend: glium::Blend::alpha_blending(),
+        ..params.clone()
+    };
+
+    let offscreen = Offscreen::new(context, SIZE);
+    let mut target = offscreen.framebuffer(context);
+    let [red, green, blue] = clock.sky_colour();
+    target.clear_color_and_depth((red, green, blue, 1.0), 1.0);
+    let fog = settings.fog(clock.sky_colour());
+
+    let perspective = camera.projection(SIZE);
+    let view = camera.view_matrix();
+    let mut drawn = vec![];
+    for x in -1..=1 {
+        for y in -1..=0 {
+            for z in -1..=1 {
+                drawn.push((x, y, z));
+            }
+        }
+    }
+    renderer.render(
+        &mut target,
+        &chunks_program,
+        perspective,
+        view,
+        &params,
+        &square,
+        &textures,
+        0,
+        clock,
+        &fog,
+        &drawn,
+    );
+    let translucent: Vec<_> = drawn
+        .into_iter()
+        .filter(|&coords| renderer.has_translucent_faces(coords))
+        .collect();
+    for &coords in translucent.iter() {
+        renderer.sort_translucent(coords, camera.get_pos());
+    }
+    renderer.render_translucent(
+        &mut target,
+        &chunks_program,
+        perspective,
+        view,
+        &translucent_params,
+        &square,
+        &textures,
+        0,
+        clock,
+        &fog,
+        &translucent,
+    );
+    player.render(
+        &mut target,
+        &entity_program,
+        perspective,
+        view,
+        &params,
+        &fog,
+    );
+
+    offscreen.read()
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name))
+}
+
+/// Returns the share of pixels that differ by more than [`CHANNEL_TOLERANCE`] in any channel.
+fn difference(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    let differing = a
+        .pixels()
+        .zip(b.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(&a, &b)| a.max(b) - a.min(b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    differing as f64 / (a.width() * a.height()) as f64
+}
+
+/// Compares a rendered image with its golden image, writing the golden image instead when
+/// `UPDATE_GOLDEN` is set.
+fn assert_matches_golden(name: &str, image: &RgbaImage) {
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        image.save(&path).unwrap();
+        return;
+    }
+
+    let golden = image::open(&path)
+        .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e))
+        .to_rgba8();
+    assert_eq!(golden.dimensions(), image.dimensions());
+    let difference = difference(&golden, image);
+    if difference > PIXEL_TOLERANCE {
+        let actual = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        image.save(&actual).unwrap();
+        panic!(
+            "{:.2}% of pixels differ from {}, the rendered image is at {}",
+            difference * 100.0,
+            path.display(),
+            actual.display(),
+        );
+    }
+}
+
+/// Returns a camera placed at the start of the camera path next to the golden images.
+fn camera(name: &str) -> Camera {
+    let path = golden_path(name).with_extension("path");
+    let (position, target) = CameraPath::load(&path).unwrap().pose(0.0);
+    let mut camera = Camera::new(10.0, 0.001, 90.0);
+    camera.look_at(position, target);
+    camera
+}
+
+#[test]
+fn terrain_at_noon() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let image = render_terrain(
+        &context,
+        &camera("terrain"),
+        &WorldClock::default(),
+        &ClientSettings::default(),
+    );
+    assert_matches_golden("terrain-noon", &image);
+}
+
+#[test]
+fn terrain_at_night() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let mut clock = WorldClock::default();
+    clock.advance(clock.day_length / 2);
+    let image = render_terrain(
+        &context,
+        &camera("terrain"),
+        &clock,
+        &ClientSettings::default(),
+    );
+    assert_matches_golden("terrain-night", &image);
+}
+
+#[test]
+fn terrain_in_fog() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    // A short view distance, so that the far side of the terrain is lost in the fog.
+    let settings = ClientSettings {
+        view_distance: 2,
+        fog: FogSettings {
+            enabled: true,
+            start: 0.25,
+        },
+    };
+    let image = render_terrain(
+        &context,
+        &camera("terrain"),
+        &WorldClock::default(),
+        &settings,
+    );
+    assert_matches_golden("terrain-fog", &image);
+}
+
+#[test]
+fn rendering_is_repeatable() {
+    let context = match context() {
+        Some(context) => context,
+        None => return,
+    };
+    let clock = WorldClock::default();
+    let settings = ClientSettings::default();
+    let first = render_terrain(&context, &camera("terrain"), &clock, &settings);
+    let second = render_terrain(&context, &camera("terrain"), &clock, &settings);
+    assert_eq!(difference(&first, &second), 0.0);
+}
@@ -0,0 +1,63 @@
+use minecraft_rust::client::renderer::{merge_ranges, RangeAllocator};
+
+#[test]
+fn ranges_are_allocated_first_fit() {
+    let mut allocator = RangeAllocator::new(100);
+    assert_eq!(allocator.allocate(10), Some(0..10));
+    assert_eq!(allocator.allocate(20), Some(10..30));
+    assert_eq!(allocator.allocate(0), Some(0..0));
+    assert_eq!(allocator.allocate(80), None);
+    assert_eq!(allocator.allocate(70), Some(30..100));
+    assert_eq!(allocator.allocate(1), None);
+
+    // A freed range is reused by the next allocation that fits in it.
+    allocator.free(0..10);
+    assert_eq!(allocator.allocate(11), None);
+    assert_eq!(allocator.allocate(4), Some(0..4));
+    assert_eq!(allocator.allocate(6), Some(4..10));
+}
+
+#[test]
+fn freed_ranges_are_merged() {
+    let mut allocator = RangeAllocator::new(30);
+    let a = allocator.allocate(10).unwrap();
+    let b = allocator.allocate(10).unwrap();
+    let c = allocator.allocate(10).unwrap();
+
+    allocator.free(a);
+    allocator.free(c);
+    assert_eq!(allocator.allocate(20), None);
+
+    // Freeing the middle range joins all three.
+    allocator.free(b);
+    assert_eq!(allocator.allocate(30), Some(0..30));
+}
+
+#[test]
+fn growing_extends_the_last_free_range() {
+    let mut allocator = RangeAllocator::new(10);
+    assert_eq!(allocator.allocate(6), Some(0..6));
+    assert_eq!(allocator.allocate(8), None);
+
+    allocator.grow(20);
+    assert_eq!(allocator.capacity(), 20);
+    assert_eq!(allocator.allocate(8), Some(6..14));
+    assert_eq!(allocator.allocate(6), Some(14..20));
+
+    let mut empty = RangeAllocator::new(0);
+    assert_eq!(empty.allocate(1), None);
+    empty.grow(4);
+    assert_eq!(empty.allocate(4), Some(0..4));
+}
+
+#[test]
+fn adjacent_ranges_are_merged_in_order() {
+    assert_eq!(
+        merge_ranges([0..4, 4..8, 10..12, 12..12, 12..20, 2..3]),
+        vec![0..8, 10..20, 2..3]
+    );
+    assert_eq!(merge_ranges([5..5, 7..7]), vec![]);
+
+    // Ranges that touch but are out of order stay apart, as the order is the drawing order.
+    assert_eq!(merge_ranges([4..8, 0..4]), vec![4..8, 0..4]);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+use minecraft_rust::server::{
+    chunk::{Chunk, ChunkGenerator, PerlinChunkGenerator},
+    save::WorldSave,
+};
+
+/// Returns an empty directory for a save, removing anything left there by an earlier run.
+fn save_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("minecraft-rust-save-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn stored_chunks_load_back_unchanged() {
+    common::register_blocks();
+    let save = WorldSave::open(save_dir("round-trip")).unwrap();
+    let mut gen = PerlinChunkGenerator::from_seed(3);
+    let chunk = Chunk::new(2, -1, -5, &mut gen);
+
+    assert!(!save.contains(2, -1, -5));
+    save.store(&chunk).unwrap();
+    assert!(save.contains(2, -1, -5));
+
+    let loaded = save.load(2, -1, -5).unwrap().unwrap();
+    assert_eq!(
+        (
+            loaded.get_chunk_x(),
+            loaded.get_chunk_y(),
+            loaded.get_chunk_z()
+        ),
+        (2, -1, -5)
+    );
+    assert!(loaded.get_blocks() == chunk.get_blocks());
+}
+
+#[test]
+fn missing_chunks_load_as_none() {
+    let save = WorldSave::open(save_dir("missing")).unwrap();
+    assert!(!save.contains(0, 0, 0));
+    assert!(save.load(0, 0, 0).unwrap().is_none());
+}
+
+#[test]
+fn opening_creates_the_directory() {
+    let dir = save_dir("nested").join("world");
+    WorldSave::open(&dir).unwrap();
+    assert!(dir.is_dir());
+}
+
+#[test]
+fn corrupt_chunks_are_an_error() {
+    let dir = save_dir("corrupt");
+    let save = WorldSave::open(&dir).unwrap();
+    fs::write(dir.join("0_0_0.chunk"), b"not a chunk").unwrap();
+    assert_eq!(
+        save.load(0, 0, 0).err().map(|e| e.kind()),
+        Some(ErrorKind::InvalidData)
+    );
+}
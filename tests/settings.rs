@@ -0,0 +1,75 @@
+use std::{env, fs, io::ErrorKind};
+
+use minecraft_rust::client::settings::{ClientSettings, FogSettings};
+
+#[test]
+fn missing_settings_fall_back_to_the_defaults() {
+    let path = env::temp_dir().join("minecraft-rust-missing-client.toml");
+    let _ = fs::remove_file(&path);
+    let settings = ClientSettings::load(&path).unwrap();
+    assert_eq!(settings.view_distance, 12);
+    assert!(settings.fog.enabled);
+}
+
+#[test]
+fn settings_left_out_keep_their_defaults() {
+    let path = env::temp_dir().join("minecraft-rust-partial-client.toml");
+    fs::write(&path, "view_distance = 4\n[fog]\nstart = 0.5\n").unwrap();
+    let settings = ClientSettings::load(&path).unwrap();
+    assert_eq!(settings.view_distance, 4);
+    assert_eq!(settings.fog.start, 0.5);
+    assert!(settings.fog.enabled);
+
+    fs::write(&path, "view_distance = \"far\"\n").unwrap();
+    assert!(ClientSettings::load(&path).is_err());
+}
+
+#[test]
+fn fog_has_to_start_before_the_view_distance() {
+    let path = env::temp_dir().join("minecraft-rust-fog-client.toml");
+    for start in ["1.0", "1.5", "-0.1"] {
+        fs::write(&path, format!("[fog]\nstart = {}\n", start)).unwrap();
+        assert_eq!(
+            ClientSettings::load(&path).err().map(|e| e.kind()),
+            Some(ErrorKind::InvalidData),
+            "{}",
+            start
+        );
+    }
+
+    fs::write(&path, "[fog]\nstart = 0.0\n").unwrap();
+    assert_eq!(ClientSettings::load(&path).unwrap().fog.start, 0.0);
+}
+
+#[test]
+fn fog_ends_at_the_view_distance() {
+    let settings = ClientSettings {
+        view_distance: 4,
+        fog: FogSettings {
+            enabled: true,
+            start: 0.5,
+        },
+    };
+    let fog = settings.fog([1.0, 0.0, 0.5]);
+    // Chunks are 16 blocks of half a world unit.
+    assert_eq!(fog.end, 32.0);
+    assert_eq!(fog.start, 16.0);
+    // The sky colour is written to the framebuffer as it is, while shader output is converted
+    // from linear colour, so the fog colour is the sky colour in linear space.
+    assert_eq!(fog.colour[0], 1.0);
+    assert_eq!(fog.colour[1], 0.0);
+    assert!((fog.colour[2] - 0.214).abs() < 0.001);
+}
+
+#[test]
+fn disabled_fog_is_left_out() {
+    let settings = ClientSettings {
+        fog: FogSettings {
+            enabled: false,
+            ..FogSettings::default()
+        },
+        ..ClientSettings::default()
+    };
+    let fog = settings.fog([1.0, 1.0, 1.0]);
+    assert!(fog.end <= fog.start);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use std::sync::Arc;
+use std::thread;
+
+use minecraft_rust::{
+    blocks::Block,
+    client::{chunk::Chunk, store::ChunkStore},
+    collision::Aabb,
+    coords,
+    server::{
+        chunk::{Chunk as ServerChunk, ChunkGenerator},
+        generator::VoidChunkGenerator,
+    },
+};
+
+fn empty_chunk(coords: (i32, i32, i32)) -> Chunk {
+    let mut gen = VoidChunkGenerator::from_seed(0);
+    Chunk::from_server_chunk(ServerChunk::new(coords.0, coords.1, coords.2, &mut gen))
+}
+
+#[test]
+fn requested_chunks_are_due_until_they_arrive() {
+    common::register_blocks();
+    let store = ChunkStore::new();
+    store.request((0, 0, 0));
+    store.request((1, 0, 0));
+
+    let mut due = store.due_requests(1_000, 100);
+    due.sort();
+    assert_eq!(due, [(0, 0, 0), (1, 0, 0)]);
+    // Requested again only once the interval has passed.
+    assert!(store.due_requests(1_050, 100).is_empty());
+
+    store.insert(empty_chunk((1, 0, 0)));
+    assert!(store.contains((0, 0, 0)));
+    assert!(!store.is_loaded((0, 0, 0)));
+    assert!(store.is_loaded((1, 0, 0)));
+    assert_eq!(store.due_requests(1_100, 100), [(0, 0, 0)]);
+
+    assert!(store.has_requests());
+    store.insert(empty_chunk((0, 0, 0)));
+    assert!(!store.has_requests());
+}
+
+#[test]
+fn readers_keep_their_copy_while_a_chunk_changes() {
+    common::register_blocks();
+    let store = ChunkStore::new();
+    store.insert(empty_chunk((0, 0, 0)));
+    let stone = Block::get("stone").unwrap();
+
+    let before = store.get((0, 0, 0)).unwrap();
+    store.update((0, 0, 0), |chunk| *chunk.block_mut(1, 2, 3) = stone);
+
+    assert_eq!(before.block(1, 2, 3), Block::air());
+    assert_eq!(store.get((0, 0, 0)).unwrap().block(1, 2, 3), stone);
+    assert_eq!(store.block([1, 2, 3]), Some(stone));
+    assert_eq!(store.block([1, 2, -1]), None);
+    assert_eq!(store.update((0, 0, -1), |_| ()), None);
+}
+
+#[test]
+fn neighbourhood_holds_the_loaded_chunks_around_a_chunk() {
+    common::register_blocks();
+    let store = ChunkStore::new();
+    for coords in [(0, 0, 0), (1, 1, 1), (2, 0, 0), (-1, 0, 0)] {
+        store.insert(empty_chunk(coords));
+    }
+    store.request((0, 1, 0));
+
+    let mut neighbourhood: Vec<_> = store.neighbourhood((0, 0, 0)).into_keys().collect();
+    neighbourhood.sort();
+    assert_eq!(neighbourhood, [(-1, 0, 0), (0, 0, 0), (1, 1, 1)]);
+}
+
+#[test]
+fn chunks_can_be_read_from_other_threads() {
+    common::register_blocks();
+    let store = Arc::new(ChunkStore::new());
+    store.insert(empty_chunk((0, 0, 0)));
+
+    let reader = {
+        let store = store.clone();
+        thread::spawn(move || store.get((0, 0, 0)).unwrap().block(0, 0, 0))
+    };
+    assert_eq!(reader.join().unwrap(), Block::air());
+}
+
+#[test]
+fn boxes_collide_with_the_shape_of_blocks() {
+    common::register_blocks();
+    let store = ChunkStore::new();
+    store.insert(empty_chunk((0, 0, 0)));
+    store.update((0, 0, 0), |chunk| {
+        *chunk.block_mut(1, 1, 1) = Block::get("stone_slab").unwrap();
+        *chunk.block_mut(3, 1, 1) = Block::get("tall_grass_1").unwrap();
+    });
+    let small = |block: [i32; 3], height: f32| Aabb {
+        centre: {
+            let [x, y, z] = coords::block_centre(block);
+            [x, y + height * coords::BLOCK_SIZE, z]
+        },
+        extents: [0.05; 3],
+    };
+
+    // Only the bottom half of a slab collides.
+    assert!(store.collides(&small([1, 1, 1], -0.25)));
+    assert!(!store.collides(&small([1, 1, 1], 0.25)));
+    // Plants and air do not collide at all.
+    assert!(!store.collides(&small([3, 1, 1], 0.0)));
+    assert!(!store.collides(&small([5, 1, 1], 0.0)));
+    // A box reaching down into the slab from the block above collides.
+    assert!(store.collides(&Aabb {
+        centre: coords::block_centre([1, 2, 1]),
+        extents: [0.1, coords::BLOCK_SIZE, 0.1],
+    }));
+}
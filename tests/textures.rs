@@ -0,0 +1,210 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use image::{Rgba, RgbaImage};
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection},
+    textures::{self, pad_transparent, TextureArrayBuilder},
+};
+
+/// Creates an empty directory for a test to write textures to.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("minecraft-rust-textures-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_texture(path: &Path, width: u32, height: u32, colour: [u8; 4]) -> String {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    RgbaImage::from_pixel(width, height, Rgba(colour))
+        .save(path)
+        .unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn same_asset_is_loaded_once() {
+    let dir = test_dir("dedup");
+    let a = write_texture(&dir.join("a.png"), 16, 16, [255, 0, 0, 255]);
+    let b = write_texture(&dir.join("b.png"), 16, 16, [0, 255, 0, 255]);
+
+    let mut builder = TextureArrayBuilder::new();
+    let first = builder.add(&a).unwrap();
+    let second = builder.add(&b).unwrap();
+    assert_eq!(builder.add(&a).unwrap(), first);
+    assert_ne!(first, second);
+    assert_ne!(first, 0);
+
+    let layers = builder.build();
+    assert_eq!(layers.size, 16);
+    assert_eq!(layers.layers.len(), 3);
+    assert_eq!(
+        layers.layers[first as usize].get_pixel(0, 0).0,
+        [255, 0, 0, 255]
+    );
+}
+
+#[test]
+fn mismatched_sizes_are_reported() {
+    let dir = test_dir("sizes");
+    let big = write_texture(&dir.join("big.png"), 32, 32, [0; 4]);
+    let small = write_texture(&dir.join("small.png"), 16, 16, [0; 4]);
+    let wide = write_texture(&dir.join("wide.png"), 32, 16, [0; 4]);
+    let odd = write_texture(&dir.join("odd.png"), 12, 12, [0; 4]);
+
+    let mut builder = TextureArrayBuilder::new();
+    builder.add(&big).unwrap();
+
+    let error = builder.add(&small).unwrap_err().to_string();
+    assert!(error.contains("small.png"), "{}", error);
+    assert!(error.contains("big.png"), "{}", error);
+    assert!(error.contains("16 pixels wide"), "{}", error);
+
+    let error = builder.add(&wide).unwrap_err().to_string();
+    assert!(
+        error.contains("wide.png") && error.contains("square"),
+        "{}",
+        error
+    );
+
+    let error = builder.add(&odd).unwrap_err().to_string();
+    assert!(
+        error.contains("odd.png") && error.contains("power of two"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn missing_texture_is_reported() {
+    let mut builder = TextureArrayBuilder::new();
+    let error = builder
+        .add("assets/textures/PNG/Tiles/does_not_exist.png")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("does_not_exist.png"), "{}", error);
+}
+
+#[test]
+fn resource_pack_overrides_defaults() {
+    let dir = test_dir("pack");
+    write_texture(
+        &dir.join("textures/PNG/Tiles/dirt.png"),
+        128,
+        128,
+        [1, 2, 3, 255],
+    );
+
+    let mut builder = TextureArrayBuilder::new().with_resource_pack(&dir);
+    assert_eq!(
+        builder.resolve("assets/textures/PNG/Tiles/dirt.png"),
+        dir.join("textures/PNG/Tiles/dirt.png")
+    );
+    assert_eq!(
+        builder.resolve("assets/textures/PNG/Tiles/stone.png"),
+        PathBuf::from("assets/textures/PNG/Tiles/stone.png")
+    );
+
+    let dirt = builder.add("assets/textures/PNG/Tiles/dirt.png").unwrap();
+    let stone = builder.add("assets/textures/PNG/Tiles/stone.png").unwrap();
+    let layers = builder.build();
+    assert_eq!(
+        layers.layers[dirt as usize].get_pixel(5, 5).0,
+        [1, 2, 3, 255]
+    );
+    assert_ne!(
+        layers.layers[stone as usize].get_pixel(5, 5).0,
+        [1, 2, 3, 255]
+    );
+}
+
+#[test]
+fn transparent_pixels_take_the_colour_of_opaque_ones() {
+    let mut texture = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 0]));
+    texture.put_pixel(2, 2, Rgba([200, 100, 50, 255]));
+    pad_transparent(&mut texture);
+
+    for pixel in texture.pixels() {
+        assert_eq!(&pixel.0[..3], &[200, 100, 50]);
+    }
+    assert_eq!(texture.get_pixel(2, 2)[3], 255);
+    assert_eq!(texture.get_pixel(6, 7)[3], 0);
+}
+
+#[test]
+fn default_block_textures_are_valid() {
+    Block::register_defaults();
+    let mut builder = TextureArrayBuilder::new();
+    for block in Block::registered() {
+        for &face in FaceDirection::ALL.iter() {
+            // A square texture is a single frame strip, so this checks still and animated
+            // textures alike.
+            if let Some(asset) = block.get_texture_asset(face) {
+                builder.add_animated(&asset).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn animated_textures_are_split_into_frames() {
+    let dir = test_dir("animated");
+    let still = write_texture(&dir.join("still.png"), 16, 16, [9, 9, 9, 255]);
+    let path = dir.join("strip.png");
+    let mut strip = RgbaImage::new(16, 64);
+    for (_, y, pixel) in strip.enumerate_pixels_mut() {
+        *pixel = Rgba([(y / 16) as u8, 0, 0, 255]);
+    }
+    strip.save(&path).unwrap();
+    let strip = path.to_str().unwrap();
+
+    let mut builder = TextureArrayBuilder::new();
+    let still = builder.add(&still).unwrap();
+    let (first, frames) = builder.add_animated(strip).unwrap();
+    assert_eq!(frames, 4);
+    assert_eq!(first, still + 1);
+    assert_eq!(builder.add_animated(strip).unwrap(), (first, frames));
+
+    let layers = builder.build();
+    assert_eq!(layers.layers.len(), 6);
+    for frame in 0..frames {
+        let layer = &layers.layers[(first + frame) as usize];
+        assert_eq!(layer.dimensions(), (16, 16));
+        assert_eq!(layer.get_pixel(3, 3)[0], frame as u8);
+    }
+
+    // A strip is not a valid still texture.
+    let error = TextureArrayBuilder::new()
+        .add(strip)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("strip.png"), "{}", error);
+
+    let bad = write_texture(&dir.join("bad.png"), 16, 40, [0; 4]);
+    let error = TextureArrayBuilder::new()
+        .add_animated(&bad)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("bad.png") && error.contains("frames"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn texture_words_pack_animation() {
+    assert_eq!(textures::pack_texture(7, 1, 0).unwrap(), 7);
+    assert_eq!(
+        textures::pack_texture(300, 8, 4).unwrap(),
+        300 | 7 << 16 | 4 << 24
+    );
+    assert!(textures::pack_texture(0x10000, 1, 0).is_err());
+    assert!(textures::pack_texture(1, 0, 0).is_err());
+    assert!(textures::pack_texture(1, 257, 1).is_err());
+    assert!(textures::pack_texture(1, 2, 256).is_err());
+}
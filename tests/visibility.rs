@@ -0,0 +1,206 @@
+mod common;
+
+use std::collections::HashMap;
+
+use minecraft_rust::{
+    blocks::{Block, FaceDirection, CHUNK_SIZE},
+    visibility::{self, Visibility},
+};
+
+type Blocks = [[[Block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE];
+
+fn filled(block: Block) -> Box<Blocks> {
+    Box::new([[[block; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE])
+}
+
+fn stone() -> Block {
+    Block::get("stone").unwrap()
+}
+
+/// Returns the pairs of different faces that are connected.
+fn connections(visibility: Visibility) -> Vec<(FaceDirection, FaceDirection)> {
+    let mut connections = vec![];
+    for (i, &a) in FaceDirection::ALL.iter().enumerate() {
+        for &b in FaceDirection::ALL[i + 1..].iter() {
+            if visibility.connects(a, b) {
+                assert!(visibility.connects(b, a));
+                connections.push((a, b));
+            }
+        }
+    }
+    connections
+}
+
+#[test]
+fn air_and_stone_chunks() {
+    common::register_blocks();
+    assert_eq!(Visibility::compute(&filled(Block::air())), Visibility::OPEN);
+    assert_eq!(Visibility::compute(&filled(stone())), Visibility::CLOSED);
+    assert_eq!(connections(Visibility::OPEN).len(), 15);
+}
+
+#[test]
+fn tunnels_connect_the_faces_they_go_through() {
+    common::register_blocks();
+    let mut blocks = filled(stone());
+    for x in 0..CHUNK_SIZE {
+        blocks[x][5][5] = Block::air();
+    }
+    assert_eq!(
+        connections(Visibility::compute(&blocks)),
+        vec![(FaceDirection::Front, FaceDirection::Back)]
+    );
+
+    // Bend it upwards instead of going out through the front.
+    blocks[15][5][5] = stone();
+    for y in 5..CHUNK_SIZE {
+        blocks[14][y][5] = Block::air();
+    }
+    assert_eq!(
+        connections(Visibility::compute(&blocks)),
+        vec![(FaceDirection::Up, FaceDirection::Back)]
+    );
+}
+
+#[test]
+fn separate_caves_are_not_connected() {
+    common::register_blocks();
+    let mut blocks = filled(stone());
+    // One cave from the top to the left, another from the bottom to the right.
+    for y in 8..CHUNK_SIZE {
+        blocks[4][y][8] = Block::air();
+    }
+    for z in 8..CHUNK_SIZE {
+        blocks[4][8][z] = Block::air();
+    }
+    for y in 0..6 {
+        blocks[10][y][2] = Block::air();
+    }
+    for z in 0..3 {
+        blocks[10][5][z] = Block::air();
+    }
+
+    assert_eq!(
+        connections(Visibility::compute(&blocks)),
+        vec![
+            (FaceDirection::Up, FaceDirection::Left),
+            (FaceDirection::Down, FaceDirection::Right),
+        ]
+    );
+}
+
+#[test]
+fn see_through_blocks_do_not_block_visibility() {
+    common::register_blocks();
+    let mut blocks = filled(Block::get("glass").unwrap());
+    blocks[0][0][0] = stone();
+    assert_eq!(Visibility::compute(&blocks), Visibility::OPEN);
+
+    // A single stone layer across the middle splits the chunk into a top and a bottom.
+    let mut blocks = filled(Block::air());
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            blocks[x][8][z] = stone();
+        }
+    }
+    let visibility = Visibility::compute(&blocks);
+    assert!(!visibility.connects(FaceDirection::Up, FaceDirection::Down));
+    assert!(visibility.connects(FaceDirection::Up, FaceDirection::Front));
+    assert!(visibility.connects(FaceDirection::Down, FaceDirection::Front));
+    assert!(visibility.connects(FaceDirection::Left, FaceDirection::Right));
+}
+
+/// Visibility of a small world, where chunks are open unless given.
+fn world(
+    size: i32,
+    chunks: &[((i32, i32, i32), Visibility)],
+) -> impl Fn((i32, i32, i32)) -> Option<Visibility> {
+    let chunks: HashMap<_, _> = chunks.iter().cloned().collect();
+    move |(x, y, z)| {
+        if [x, y, z].iter().all(|c| c.abs() <= size) {
+            Some(*chunks.get(&(x, y, z)).unwrap_or(&Visibility::OPEN))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn open_worlds_are_fully_visible() {
+    let visible = visibility::visible_chunks((0, 0, 0), world(2, &[]), |_| true);
+    assert_eq!(visible.len(), 5 * 5 * 5);
+    assert!(visibility::visible_chunks((9, 0, 0), world(2, &[]), |_| true).is_empty());
+}
+
+#[test]
+fn closed_chunks_hide_what_is_behind_them() {
+    // A column of closed chunks along x in front of the camera.
+    let walls: Vec<_> = (-2..=2)
+        .flat_map(|y| (-2..=2).map(move |z| ((1, y, z), Visibility::CLOSED)))
+        .collect();
+    let visible = visibility::visible_chunks((0, 0, 0), world(2, &walls), |_| true);
+
+    // The wall itself is seen, but nothing behind it.
+    assert!(visible.contains(&(1, 0, 0)));
+    assert!(visible.contains(&(1, 2, -2)));
+    assert!(!visible.contains(&(2, 0, 0)));
+    assert!(visible.contains(&(-2, 0, 0)));
+    assert_eq!(visible.len(), 5 * 5 * 4);
+}
+
+#[test]
+fn chunks_are_only_left_through_faces_connected_to_the_way_in() {
+    let mut tunnel = Visibility::CLOSED;
+    tunnel.connect(FaceDirection::Back, FaceDirection::Front);
+    let chunks = [((1, 0, 0), tunnel), ((2, 0, 0), tunnel)];
+    let visible = visibility::visible_chunks((0, 0, 0), world(3, &chunks), |(x, y, z)| {
+        // Only look down the tunnel.
+        x >= 0 && y == 0 && z == 0
+    });
+
+    let mut visible: Vec<_> = visible.into_iter().collect();
+    visible.sort();
+    assert_eq!(visible, vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (3, 0, 0)]);
+
+    // A tunnel that turns upwards is not seen through going straight.
+    let mut bend = Visibility::CLOSED;
+    bend.connect(FaceDirection::Back, FaceDirection::Up);
+    let chunks = [((1, 0, 0), bend)];
+    let visible =
+        visibility::visible_chunks((0, 0, 0), world(3, &chunks), |(_, y, z)| y >= 0 && z == 0);
+    assert!(visible.contains(&(1, 1, 0)));
+    assert!(!visible.contains(&(2, 0, 0)));
+}
+
+#[test]
+fn chunks_out_of_view_are_not_entered() {
+    let visible = visibility::visible_chunks((0, 0, 0), world(3, &[]), |(x, _, _)| x >= 0);
+    assert_eq!(visible.len(), 4 * 7 * 7);
+    assert!(visible.iter().all(|&(x, _, _)| x >= 0));
+}
+
+#[test]
+fn the_search_does_not_turn_back_towards_the_camera() {
+    // A U-shaped tunnel: out along x, up two chunks, and back along -x above a closed chunk
+    // over the camera.
+    let connected = |a, b| {
+        let mut visibility = Visibility::CLOSED;
+        visibility.connect(a, b);
+        visibility
+    };
+    let chunks = [
+        ((1, 0, 0), connected(FaceDirection::Back, FaceDirection::Up)),
+        ((1, 1, 0), connected(FaceDirection::Down, FaceDirection::Up)),
+        (
+            (1, 2, 0),
+            connected(FaceDirection::Down, FaceDirection::Back),
+        ),
+        ((0, 1, 0), Visibility::CLOSED),
+    ];
+    let visible =
+        visibility::visible_chunks((0, 0, 0), world(2, &chunks), |(x, _, z)| x >= 0 && z == 0);
+
+    assert!(visible.contains(&(1, 2, 0)));
+    // Reaching it would mean going towards -x after going towards +x.
+    assert!(!visible.contains(&(0, 2, 0)));
+}